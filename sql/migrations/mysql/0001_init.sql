@@ -0,0 +1,74 @@
+-- 初始表结构，与 postgres/0001_init.sql 对应，需要 MySQL 8.0.13+
+-- uuid 使用 BINARY(16)，时间使用 DATETIME(6) 保存UTC时间，jsonb 使用 JSON
+
+CREATE TABLE IF NOT EXISTS users (
+    id INT NOT NULL AUTO_INCREMENT,
+    uuid BINARY(16) NOT NULL,
+    username VARCHAR(50),
+    password VARCHAR(64),
+    salt BINARY(16),
+    mobile VARCHAR(11),
+    create_time DATETIME(6),
+    update_time DATETIME(6),
+    is_del SMALLINT DEFAULT 0,
+    is_enabled SMALLINT DEFAULT 1,
+    last_login_time DATETIME(6),
+    last_login_ip VARCHAR(15),
+    user_type SMALLINT DEFAULT 0,
+    name VARCHAR(50),
+    -- mysql 不支持部分索引，未删除用户的手机号通过生成列实现唯一
+    mobile_key VARCHAR(11) GENERATED ALWAYS AS (IF(is_del = 0, mobile, NULL)) STORED,
+    CONSTRAINT users_pk PRIMARY KEY (id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS authorizations (
+    id INT NOT NULL AUTO_INCREMENT,
+    user_id INT NOT NULL,
+    uuid BINARY(16) NOT NULL,
+    client_type SMALLINT DEFAULT 0 NOT NULL,
+    refresh_token BINARY(16) NOT NULL,
+    create_time DATETIME(6) NOT NULL,
+    update_time DATETIME(6),
+    last_refresh_time DATETIME(6),
+    access_token_id BINARY(16) NOT NULL,
+    access_token_exp DATETIME(6) NOT NULL,
+    access_token_iat DATETIME(6) NOT NULL,
+    is_enabled SMALLINT DEFAULT 1 NOT NULL,
+    CONSTRAINT authorizations_pk PRIMARY KEY (id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS authorizations_blacklist (
+    id INT NOT NULL AUTO_INCREMENT,
+    access_token_id BINARY(16) NOT NULL,
+    access_token_exp DATETIME(6) NOT NULL,
+    user_id INT DEFAULT 0 NOT NULL,
+    CONSTRAINT authorizations_blacklist_pk PRIMARY KEY (id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS authorizations_logs (
+    id INT NOT NULL AUTO_INCREMENT,
+    user_id INT,
+    log_type SMALLINT NOT NULL,
+    ip VARCHAR(15),
+    log_time DATETIME(6) NOT NULL,
+    client_type SMALLINT NOT NULL,
+    auth_id INT,
+    log VARCHAR(250),
+    user_agent TEXT,
+    CONSTRAINT authorizations_logs_pk PRIMARY KEY (id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+-- 唯一约束
+CREATE UNIQUE INDEX users_uuid_key ON users (uuid);
+-- 用户名唯一，同时用于按用户名登录
+CREATE UNIQUE INDEX users_username_key ON users (username);
+-- 未删除用户的手机号唯一，mobile_key 为 NULL 的行不参与唯一检查
+CREATE UNIQUE INDEX users_mobile_key ON users (mobile_key);
+CREATE UNIQUE INDEX authorizations_uuid_key ON authorizations (uuid);
+CREATE UNIQUE INDEX authorizations_blacklist_access_token_id_key ON authorizations_blacklist (access_token_id);
+
+-- 索引
+CREATE INDEX users_username_idx ON users (username);
+CREATE INDEX authorizations_user_id_idx ON authorizations (user_id);
+CREATE INDEX authorizations_logs_user_id_idx ON authorizations_logs (user_id);
+CREATE INDEX authorizations_logs_log_time_idx ON authorizations_logs (log_time);
@@ -0,0 +1,2 @@
+-- 用户头像和注销后的匿名化时间
+ALTER TABLE users ADD COLUMN avatar VARCHAR(255), ADD COLUMN anonymize_time DATETIME(6);
@@ -0,0 +1,23 @@
+-- 多租户，已有数据属于默认租户
+CREATE TABLE IF NOT EXISTS tenants (
+    id INT NOT NULL AUTO_INCREMENT,
+    code VARCHAR(50) NOT NULL,
+    name VARCHAR(100),
+    is_enabled SMALLINT DEFAULT 1 NOT NULL,
+    create_time DATETIME(6) NOT NULL,
+    CONSTRAINT tenants_pk PRIMARY KEY (id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+CREATE UNIQUE INDEX tenants_code_key ON tenants (code);
+
+INSERT IGNORE INTO tenants (id, code, name, is_enabled, create_time) VALUES (1, 'default', 'Default', 1, UTC_TIMESTAMP(6));
+
+ALTER TABLE users ADD COLUMN tenant_id INT DEFAULT 1 NOT NULL;
+ALTER TABLE authorizations ADD COLUMN tenant_id INT DEFAULT 1 NOT NULL;
+ALTER TABLE authorizations_logs ADD COLUMN tenant_id INT DEFAULT 1 NOT NULL;
+ALTER TABLE authorizations_blacklist ADD COLUMN tenant_id INT DEFAULT 1 NOT NULL;
+
+-- 用户名和未删除用户的手机号改为在租户内唯一
+DROP INDEX users_username_key ON users;
+DROP INDEX users_mobile_key ON users;
+CREATE UNIQUE INDEX users_tenant_id_username_key ON users (tenant_id, username);
+CREATE UNIQUE INDEX users_tenant_id_mobile_key ON users (tenant_id, mobile_key);
@@ -0,0 +1,18 @@
+-- 用户邀请，只保存token的sha256
+CREATE TABLE IF NOT EXISTS invitations (
+    id INT NOT NULL AUTO_INCREMENT,
+    tenant_id INT DEFAULT 1 NOT NULL,
+    email VARCHAR(100),
+    mobile VARCHAR(20),
+    user_type SMALLINT DEFAULT 0 NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    expire_time DATETIME(6) NOT NULL,
+    create_time DATETIME(6) NOT NULL,
+    created_by INT NOT NULL,
+    accept_time DATETIME(6),
+    user_id INT,
+    revoke_time DATETIME(6),
+    CONSTRAINT invitations_pk PRIMARY KEY (id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+CREATE UNIQUE INDEX invitations_token_hash_key ON invitations (token_hash);
+CREATE INDEX invitations_tenant_id_idx ON invitations (tenant_id);
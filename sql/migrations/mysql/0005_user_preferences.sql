@@ -0,0 +1,7 @@
+-- 用户偏好设置
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id INT NOT NULL,
+    data JSON DEFAULT (JSON_OBJECT()) NOT NULL,
+    update_time DATETIME(6) NOT NULL,
+    CONSTRAINT user_preferences_pk PRIMARY KEY (user_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
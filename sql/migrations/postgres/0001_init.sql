@@ -0,0 +1,72 @@
+-- 初始表结构，与原 sql/db.sql 一致，并补充索引和唯一约束
+-- 使用 IF NOT EXISTS，已通过 db.sql 导入的数据库也可以直接执行，之后新增的列和表在后续迁移中添加
+
+CREATE TABLE IF NOT EXISTS users (
+    id serial NOT NULL,
+    uuid uuid NOT NULL,
+    username character varying(50),
+    password character varying(64),
+    salt uuid,
+    mobile character varying(11),
+    create_time timestamp with time zone,
+    update_time timestamp with time zone,
+    is_del smallint DEFAULT 0,
+    is_enabled smallint DEFAULT 1,
+    last_login_time timestamp with time zone,
+    last_login_ip character varying(15),
+    user_type smallint DEFAULT 0,
+    name character varying(50),
+    CONSTRAINT users_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS authorizations (
+    id serial NOT NULL,
+    user_id integer NOT NULL,
+    uuid uuid NOT NULL,
+    client_type smallint DEFAULT 0 NOT NULL,
+    refresh_token uuid NOT NULL,
+    create_time timestamp with time zone NOT NULL,
+    update_time timestamp with time zone,
+    last_refresh_time timestamp with time zone,
+    access_token_id uuid NOT NULL,
+    access_token_exp timestamp with time zone NOT NULL,
+    access_token_iat timestamp with time zone NOT NULL,
+    is_enabled smallint DEFAULT 1 NOT NULL,
+    CONSTRAINT authorizations_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS authorizations_blacklist (
+    id serial NOT NULL,
+    access_token_id uuid NOT NULL,
+    access_token_exp timestamp with time zone NOT NULL,
+    user_id integer DEFAULT 0 NOT NULL,
+    CONSTRAINT authorizations_blacklist_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS authorizations_logs (
+    id serial NOT NULL,
+    user_id integer,
+    log_type smallint NOT NULL,
+    ip character varying(15),
+    log_time timestamp with time zone NOT NULL,
+    client_type smallint NOT NULL,
+    auth_id integer,
+    log character varying(250),
+    user_agent text,
+    CONSTRAINT authorizations_logs_pk PRIMARY KEY (id)
+);
+
+-- 唯一约束
+CREATE UNIQUE INDEX IF NOT EXISTS users_uuid_key ON users (uuid);
+-- 用户名唯一，同时用于按用户名登录
+CREATE UNIQUE INDEX IF NOT EXISTS users_username_key ON users (username);
+-- 未删除用户的手机号唯一
+CREATE UNIQUE INDEX IF NOT EXISTS users_mobile_key ON users (mobile) WHERE is_del = 0 AND mobile IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS authorizations_uuid_key ON authorizations (uuid);
+CREATE UNIQUE INDEX IF NOT EXISTS authorizations_blacklist_access_token_id_key ON authorizations_blacklist (access_token_id);
+
+-- 索引
+CREATE INDEX IF NOT EXISTS users_username_idx ON users (username);
+CREATE INDEX IF NOT EXISTS authorizations_user_id_idx ON authorizations (user_id);
+CREATE INDEX IF NOT EXISTS authorizations_logs_user_id_idx ON authorizations_logs (user_id);
+CREATE INDEX IF NOT EXISTS authorizations_logs_log_time_idx ON authorizations_logs (log_time);
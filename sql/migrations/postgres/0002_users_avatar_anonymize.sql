@@ -0,0 +1,3 @@
+-- 用户头像和注销后的匿名化时间
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar character varying(255);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS anonymize_time timestamp with time zone;
@@ -0,0 +1,24 @@
+-- 多租户，已有数据属于默认租户
+CREATE TABLE IF NOT EXISTS tenants (
+    id serial NOT NULL,
+    code character varying(50) NOT NULL,
+    name character varying(100),
+    is_enabled smallint DEFAULT 1 NOT NULL,
+    create_time timestamp with time zone NOT NULL,
+    CONSTRAINT tenants_pk PRIMARY KEY (id)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS tenants_code_key ON tenants (code);
+
+INSERT INTO tenants (id, code, name, is_enabled, create_time) VALUES (1, 'default', 'Default', 1, now()) ON CONFLICT DO NOTHING;
+SELECT setval('tenants_id_seq', GREATEST((SELECT MAX(id) FROM tenants), 1));
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tenant_id integer DEFAULT 1 NOT NULL;
+ALTER TABLE authorizations ADD COLUMN IF NOT EXISTS tenant_id integer DEFAULT 1 NOT NULL;
+ALTER TABLE authorizations_logs ADD COLUMN IF NOT EXISTS tenant_id integer DEFAULT 1 NOT NULL;
+ALTER TABLE authorizations_blacklist ADD COLUMN IF NOT EXISTS tenant_id integer DEFAULT 1 NOT NULL;
+
+-- 用户名和未删除用户的手机号改为在租户内唯一
+DROP INDEX IF EXISTS users_username_key;
+DROP INDEX IF EXISTS users_mobile_key;
+CREATE UNIQUE INDEX IF NOT EXISTS users_tenant_id_username_key ON users (tenant_id, username);
+CREATE UNIQUE INDEX IF NOT EXISTS users_tenant_id_mobile_key ON users (tenant_id, mobile) WHERE is_del = 0 AND mobile IS NOT NULL;
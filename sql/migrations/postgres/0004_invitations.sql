@@ -0,0 +1,18 @@
+-- 用户邀请，只保存token的sha256
+CREATE TABLE IF NOT EXISTS invitations (
+    id serial NOT NULL,
+    tenant_id integer DEFAULT 1 NOT NULL,
+    email character varying(100),
+    mobile character varying(20),
+    user_type smallint DEFAULT 0 NOT NULL,
+    token_hash character(64) NOT NULL,
+    expire_time timestamp with time zone NOT NULL,
+    create_time timestamp with time zone NOT NULL,
+    created_by integer NOT NULL,
+    accept_time timestamp with time zone,
+    user_id integer,
+    revoke_time timestamp with time zone,
+    CONSTRAINT invitations_pk PRIMARY KEY (id)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS invitations_token_hash_key ON invitations (token_hash);
+CREATE INDEX IF NOT EXISTS invitations_tenant_id_idx ON invitations (tenant_id);
@@ -0,0 +1,7 @@
+-- 用户偏好设置
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id integer NOT NULL,
+    data jsonb DEFAULT '{}'::jsonb NOT NULL,
+    update_time timestamp with time zone NOT NULL,
+    CONSTRAINT user_preferences_pk PRIMARY KEY (user_id)
+);
@@ -0,0 +1,183 @@
+use actix_web::{web, get, HttpResponse, HttpRequest};
+use actix_web::web::Bytes;
+use serde::{Serialize, Deserialize};
+use chrono::prelude::*;
+use crate::AppState;
+use crate::lib::{error, auth};
+use super::{service, AuthorizationLog, LogFilter, LogType};
+
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+#[derive(Deserialize)]
+pub struct SearchLogsReqQuery {
+    user_id: Option<i32>,
+    log_type: Option<i16>,
+    ip: Option<String>,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    page: Option<i64>,
+    page_size: Option<i64>,
+}
+
+impl SearchLogsReqQuery {
+    fn filter(&self, tenant_id: i32) -> Result<LogFilter, error::Error> {
+        if let Some(log_type) = self.log_type {
+            if LogType::from_code(log_type).is_none() {
+                return Err(error::new(400002, "日志类型错误", 422));
+            }
+        }
+
+        if let (Some(start_time), Some(end_time)) = (self.start_time, self.end_time) {
+            if start_time >= end_time {
+                return Err(error::new(400002, "开始时间必须早于结束时间", 422));
+            }
+        }
+
+        Ok(LogFilter {
+            tenant_id,
+            user_id: self.user_id,
+            log_type: self.log_type,
+            ip: self.ip.clone().filter(|v| !v.is_empty()),
+            start_time: self.start_time,
+            end_time: self.end_time,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ResLogJson {
+    #[serde(flatten)]
+    log: AuthorizationLog,
+    log_type_name: &'static str,
+    log_type_description: &'static str,
+}
+
+impl From<AuthorizationLog> for ResLogJson {
+    fn from(log: AuthorizationLog) -> Self {
+        let log_type = LogType::from_code(log.log_type);
+        ResLogJson {
+            log,
+            log_type_name: log_type.map(|v| v.name()).unwrap_or(""),
+            log_type_description: log_type.map(|v| v.description()).unwrap_or(""),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ResLogListJson {
+    total: i64,
+    page: i64,
+    page_size: i64,
+    items: Vec<ResLogJson>,
+}
+
+// 日志类型列表
+#[get("/authorizations/logs/types")]
+pub async fn log_types(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let types: Vec<_> = LogType::ALL.iter().map(|v| v.info()).collect();
+
+    Ok(HttpResponse::Ok().json(types))
+}
+
+// 查询日志
+#[get("/authorizations/logs")]
+pub async fn list_logs(query: web::Query<SearchLogsReqQuery>, req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let filter = query.filter(auth_info.tenant_id)?;
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let (items, total) = service::search_logs(&filter, page, page_size, &state).await?;
+
+    Ok(HttpResponse::Ok().json(ResLogListJson {
+        total,
+        page,
+        page_size,
+        items: items.into_iter().map(ResLogJson::from).collect(),
+    }))
+}
+
+// 防止导出的内容在表格软件中被当作公式执行
+fn csv_cell(v: &Option<String>) -> String {
+    match v {
+        None => String::new(),
+        Some(v) if v.starts_with(['=', '+', '-', '@']) => format!("'{}", v),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn csv_chunk(logs: &[AuthorizationLog], with_header: bool) -> Result<Bytes, error::Error> {
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    let mut result = Ok(());
+
+    if with_header {
+        result = wtr.write_record(["id", "user_id", "log_type", "log_type_name", "ip", "log_time", "client_type", "auth_id", "log", "user_agent"]);
+    }
+
+    for v in logs {
+        if result.is_err() {
+            break;
+        }
+        let log_type_name = LogType::from_code(v.log_type).map(|v| v.name()).unwrap_or("");
+        result = wtr.write_record([
+            v.id.to_string(),
+            v.user_id.map(|v| v.to_string()).unwrap_or_default(),
+            v.log_type.to_string(),
+            log_type_name.to_string(),
+            csv_cell(&v.ip),
+            v.log_time.to_rfc3339(),
+            v.client_type.to_string(),
+            v.auth_id.map(|v| v.to_string()).unwrap_or_default(),
+            csv_cell(&v.log),
+            csv_cell(&v.user_agent),
+        ]);
+    }
+
+    match result.map_err(|_| ()).and_then(|_| wtr.into_inner().map_err(|_| ())) {
+        Ok(v) => Ok(Bytes::from(v)),
+        Err(_) => Err(error::err500()),
+    }
+}
+
+// 导出日志为CSV，分批查询并以流的方式输出
+#[get("/authorizations/logs/export")]
+pub async fn export_logs(query: web::Query<SearchLogsReqQuery>, req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let filter = query.filter(auth_info.tenant_id)?;
+
+    // (最后一条id, 是否已输出表头, 是否结束)
+    let stream = futures::stream::unfold((0, false, false), move |(after_id, header, done)| {
+        let state = state.clone();
+        let filter = filter.clone();
+        async move {
+            if done {
+                return None;
+            }
+
+            let logs = match service::get_logs_after(&filter, after_id, EXPORT_BATCH_SIZE, &state).await {
+                Ok(v) => v,
+                Err(e) => return Some((Err(e), (after_id, true, true))),
+            };
+
+            if logs.is_empty() && header {
+                return None;
+            }
+
+            let last_id = logs.last().map(|v| v.id).unwrap_or(after_id);
+            let done = (logs.len() as i64) < EXPORT_BATCH_SIZE;
+
+            Some((csv_chunk(&logs, !header), (last_id, true, done)))
+        }
+    });
+
+    let filename = format!("authorizations-logs-{}.csv", Utc::now().format("%Y%m%d%H%M%S"));
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+        .streaming(stream))
+}
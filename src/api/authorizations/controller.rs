@@ -2,9 +2,9 @@ use actix_web::{web, post, put, delete, HttpResponse, HttpRequest};
 use actix_web::dev::ConnectionInfo;
 use serde::{Serialize, Deserialize};
 use crate::AppState;
-use crate::lib::{error, validator, client, auth};
-use crate::api::user;
-use super::{service, AuthBlacklist, Authorization};
+use crate::lib::{error, validator, client, auth, log};
+use crate::api::{user, tenant};
+use super::{service, AuthBlacklist, Authorization, LogType};
 use chrono::prelude::*;
 
 #[derive(Deserialize)]
@@ -14,13 +14,13 @@ pub struct CreateAuthReqJson {
 }
 
 #[derive(Serialize)]
-struct ResTokenJson {
-    id: String,
-    access_token: String,
-    expires_in: i64,
-    refresh_token: String,
-    created_at: String,
-    updated_at: String,
+pub struct ResTokenJson {
+    pub id: String,
+    pub access_token: String,
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub created_at: String,
+    pub updated_at: String,
 }
 
 // 创建授权
@@ -30,11 +30,12 @@ pub async fn create_auth(req_info: web::Json<CreateAuthReqJson>, state: web::Dat
     let password = validator::required_str(&req_info.password, "密码")?;
 
     let client = client::get_client_info(&state, &req, &conn);
+    let tenant_id = tenant::service::resolve(&req, &state).await?.id;
 
-    let result = user::service::get_by_username(&username, &state).await?;
+    let result = user::service::get_by_username(&username, tenant_id, &state).await?;
     let u = match result {
         None => {
-            service::insert_log(1003, &username, 0, 0, &client, &state).await?;
+            service::insert_log(LogType::LoginUserNotFound, &username, 0, 0, tenant_id, &client, &state).await?;
             return Err(error::new(100400, "帐号或密码不正确", 422));
         },
         Some(v) => v
@@ -55,7 +56,7 @@ pub async fn create_auth(req_info: web::Json<CreateAuthReqJson>, state: web::Dat
         Some(v) => v,
     };
     if is_del != 0 {
-        service::insert_log(1004, "", user_id, 0, &client, &state).await?;
+        service::insert_log(LogType::LoginUserDeleted, "", user_id, 0, tenant_id, &client, &state).await?;
         return Err(error::new(100400, "帐号或密码不正确", 422));
     }
 
@@ -64,7 +65,7 @@ pub async fn create_auth(req_info: web::Json<CreateAuthReqJson>, state: web::Dat
         Some(v) => v,
     };
     if is_enabled != 1 {
-        service::insert_log(1002, "", user_id, 0, &client, &state).await?;
+        service::insert_log(LogType::LoginUserDisabled, "", user_id, 0, tenant_id, &client, &state).await?;
         return Err(error::new(100400, "帐号或密码不正确", 422));
     }
 
@@ -78,12 +79,17 @@ pub async fn create_auth(req_info: web::Json<CreateAuthReqJson>, state: web::Dat
     };
     let pwd = auth::crypt_password(&password, &salt);
     if user_password != pwd {
-        service::insert_log(1001, "", user_id, 0, &client, &state).await?;
+        service::insert_log(LogType::LoginWrongPassword, "", user_id, 0, tenant_id, &client, &state).await?;
         return Err(error::new(100400, "帐号或密码不正确", 422));
     }
 
-    let auth = auth::create_auth(user_id, user_type, &client, &state).await?;
-    service::insert_log(1, "", user_id, auth.auth_id, &client, &state).await?;
+    log::set_user_id(&req, user_id);
+
+    // 创建授权、更新最后登录时间和登录日志在一个事务中
+    let mut tx = service::begin(&state).await?;
+    let auth = auth::create_auth(user_id, user_type, tenant_id, &client, tx.as_mut(), &state).await?;
+    service::insert_log_tx(LogType::Login, "", user_id, auth.auth_id, tenant_id, &client, tx.as_mut(), &state).await?;
+    service::commit(tx, &state).await?;
 
     Ok(HttpResponse::Ok().json(ResTokenJson {
         id: auth.refresh_token_id.to_string(),
@@ -116,6 +122,7 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
     };
     
     let client = client::get_client_info(&state, &req, &conn);
+    let tenant_id = tenant::service::resolve(&req, &state).await?.id;
 
     let claims = auth::parse_token(&token)?;
     let mut have_permission = false;
@@ -126,15 +133,20 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
         }
     }
     if !have_permission {
-        service::insert_log(1053, "", 0, 0, &client, &state).await?;
+        service::insert_log(LogType::RefreshNoPermission, "", 0, 0, tenant_id, &client, &state).await?;
         return Err(error::new(100404, "No permission", 403));
     }
 
     let auth_id = claims.sub.parse::<i32>().unwrap();
 
-    let auth_data = match service::get_by_id(auth_id, &state).await? {
+    if claims.tenant != tenant_id {
+        service::insert_log(LogType::RefreshAuthNotFound, "", 0, auth_id, tenant_id, &client, &state).await?;
+        return Err(error::new(100403, "Authentication failure", 401));
+    }
+
+    let auth_data = match service::get_by_id(auth_id, tenant_id, &state).await? {
         None => {
-            service::insert_log(1058, "", 0, auth_id, &client, &state).await?;
+            service::insert_log(LogType::RefreshAuthNotFound, "", 0, auth_id, tenant_id, &client, &state).await?;
             return Err(error::new(100403, "Authentication failure", 401));
         },
         Some(v) => v
@@ -145,9 +157,9 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
         Some(v) => v,
     };
 
-    let user_data = match user::service::get_by_id(user_id, &state).await? {
+    let user_data = match user::service::get_by_id(user_id, tenant_id, &state).await? {
         None => {
-            service::insert_log(1058, "", 0, auth_id, &client, &state).await?;
+            service::insert_log(LogType::RefreshAuthNotFound, "", 0, auth_id, tenant_id, &client, &state).await?;
             return Err(error::new(100403, "Authentication failure", 401));
         },
         Some(v) => v
@@ -157,7 +169,7 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
         None => return Err(error::new(100403, "Authentication failure", 401)),
         Some(v) => {
             if v.to_string() != id {
-                service::insert_log(1059, "", user_id, auth_id, &client, &state).await?;
+                service::insert_log(LogType::RefreshIdMismatch, "", user_id, auth_id, tenant_id, &client, &state).await?;
                 return Err(error::new(100403, "Authentication failure", 401));
             }
         },
@@ -167,19 +179,20 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
         None => return Err(error::new(100403, "Authentication failure", 401)),
         Some(v) => {
             if v != 1 {
-                service::insert_log(1060, "", user_id, auth_id, &client, &state).await?;
+                service::insert_log(LogType::RefreshAuthInvalid, "", user_id, auth_id, tenant_id, &client, &state).await?;
                 return Err(error::new(100403, "Authentication failure", 401));
             }
         },
     };
 
-    match auth_data.refresh_token {
+    let old_refresh_token = match auth_data.refresh_token {
         None => return Err(error::new(100403, "Authentication failure", 401)),
         Some(v) => {
             if v.to_string() != claims.jti {
-                service::insert_log(1060, "", user_id, auth_id, &client, &state).await?;
+                service::insert_log(LogType::RefreshAuthInvalid, "", user_id, auth_id, tenant_id, &client, &state).await?;
                 return Err(error::new(100403, "Authentication failure", 401));
             }
+            v
         },
     };
 
@@ -187,7 +200,7 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
         None => return Err(error::new(100403, "Authentication failure", 401)),
         Some(v) => {
             if v != 1 {
-                service::insert_log(1061, "", user_id, auth_id, &client, &state).await?;
+                service::insert_log(LogType::RefreshUserDisabled, "", user_id, auth_id, tenant_id, &client, &state).await?;
                 return Err(error::new(100403, "Authentication failure", 401));
             }
         },
@@ -197,7 +210,7 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
         None => return Err(error::new(100403, "Authentication failure", 401)),
         Some(v) => {
             if v != 0 {
-                service::insert_log(1062, "", user_id, auth_id, &client, &state).await?;
+                service::insert_log(LogType::RefreshUserDeleted, "", user_id, auth_id, tenant_id, &client, &state).await?;
                 return Err(error::new(100403, "Authentication failure", 401));
             }
         },
@@ -226,17 +239,16 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
     let refresh_token_jti = uuid::Uuid::new_v4();
     let update_time = Utc::now();
 
-    let access_token = auth::create_access_token(user_id, user_type, &state.config);
-    let refresh_token = auth::create_refresh_token(auth_id, refresh_token_jti, &state.config);
+    let access_token = auth::create_access_token(user_id, user_type, tenant_id, &state.config.get());
+    let refresh_token = auth::create_refresh_token(auth_id, refresh_token_jti, tenant_id, &state.config.get());
 
     let authorization_blacklist = AuthBlacklist {
         id: None,
         access_token_id,
         access_token_exp,
         user_id,
+        tenant_id,
     };
-    
-    service::add_black_list(&authorization_blacklist, &state).await?;
 
     let authorization = Authorization {
         id: Some(auth_id),
@@ -251,10 +263,20 @@ pub async fn refresh_auth(req: HttpRequest, state: web::Data<AppState>, conn: Co
         access_token_exp: Some(access_token.expire_time),
         access_token_iat: Some(access_token.create_time),
         is_enabled: None,
+        tenant_id: None,
     };
 
-    service::update_auth(&authorization, &state).await?;
-    service::insert_log(2, "", user_id, auth_id, &client, &state).await?;
+    // 更新授权、旧token加入黑名单和刷新日志在一个事务中
+    // 只更新 refresh_token 仍是旧值的授权，同一个refresh token并发刷新时只有一个成功，其他请求等待行锁后更新0行
+    let mut tx = service::begin(&state).await?;
+    if service::update_auth(&authorization, old_refresh_token, tenant_id, tx.as_mut(), &state).await?.is_none() {
+        drop(tx);
+        service::insert_log(LogType::RefreshAuthInvalid, "", user_id, auth_id, tenant_id, &client, &state).await?;
+        return Err(error::new(100403, "Authentication failure", 401));
+    }
+    service::add_black_list_tx(&authorization_blacklist, tx.as_mut(), &state).await?;
+    service::insert_log_tx(LogType::Refresh, "", user_id, auth_id, tenant_id, &client, tx.as_mut(), &state).await?;
+    service::commit(tx, &state).await?;
 
     Ok(HttpResponse::Ok().json(ResTokenJson {
         id,
@@ -273,11 +295,12 @@ pub async fn delete_auth(req: HttpRequest, state: web::Data<AppState>, conn: Con
     validator::uuid(&id, "授权id")?;
 
     let client = client::get_client_info(&state, &req, &conn);
-    
-    let auth_data = service::get_by_uuid(&id, &state).await?;
+    let tenant_id = tenant::service::resolve(&req, &state).await?.id;
+
+    let auth_data = service::get_by_uuid(&id, tenant_id, &state).await?;
     let auth_data = match auth_data {
         None => {
-            service::insert_log(1101, "", 0, 0, &client, &state).await?;
+            service::insert_log(LogType::LogoutAuthNotFound, "", 0, 0, tenant_id, &client, &state).await?;
             return Err(error::new(100403, "Authentication failure", 401));
         },
         Some(v) => v
@@ -297,7 +320,7 @@ pub async fn delete_auth(req: HttpRequest, state: web::Data<AppState>, conn: Con
         None => return Err(error::new(100403, "Authentication failure", 401)),
         Some(v) => {
             if v != 1 {
-                service::insert_log(1102, "", user_id, auth_id, &client, &state).await?;
+                service::insert_log(LogType::LogoutAuthDisabled, "", user_id, auth_id, tenant_id, &client, &state).await?;
                 return Err(error::new(100403, "Authentication failure", 401));
             }
         },
@@ -313,18 +336,22 @@ pub async fn delete_auth(req: HttpRequest, state: web::Data<AppState>, conn: Con
         Some(v) => v,
     };
 
-    service::revoke_auth(auth_id, &state).await?;
+    // 禁用授权、token加入黑名单和退出日志在一个事务中
+    let mut tx = service::begin(&state).await?;
+    service::revoke_auth(auth_id, tenant_id, tx.as_mut(), &state).await?;
 
     let authorization_blacklist = AuthBlacklist {
         id: None,
         access_token_id,
         access_token_exp,
         user_id,
+        tenant_id,
     };
     
-    service::add_black_list(&authorization_blacklist, &state).await?;
+    service::add_black_list_tx(&authorization_blacklist, tx.as_mut(), &state).await?;
 
-    service::insert_log(3, "", user_id, auth_id, &client, &state).await?;
+    service::insert_log_tx(LogType::Logout, "", user_id, auth_id, tenant_id, &client, tx.as_mut(), &state).await?;
+    service::commit(tx, &state).await?;
 
     Ok(HttpResponse::Ok().body(""))
 }
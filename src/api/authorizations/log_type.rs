@@ -0,0 +1,148 @@
+use serde::Serialize;
+
+// authorizations_logs.log_type 的取值
+// 1-999 为成功操作，1000以上为失败记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i16)]
+pub enum LogType {
+    Login = 1,
+    Refresh = 2,
+    Logout = 3,
+    ChangePassword = 5,
+    UpdateProfile = 6,
+    UpdateAvatar = 7,
+    CloseAccount = 8,
+    ExportData = 9,
+    AcceptInvitation = 10,
+    AdminCreateUser = 201,
+    AdminUpdateUser = 202,
+    AdminEnableUser = 203,
+    AdminDisableUser = 204,
+    AdminDeleteUser = 205,
+    AdminRestoreUser = 206,
+    AdminResetPassword = 207,
+    AdminCreateInvitation = 208,
+    AdminRevokeInvitation = 209,
+    AdminRevokeSessions = 210,
+    AdminReloadSettings = 211,
+    LoginWrongPassword = 1001,
+    LoginUserDisabled = 1002,
+    LoginUserNotFound = 1003,
+    LoginUserDeleted = 1004,
+    RefreshNoPermission = 1053,
+    RefreshAuthNotFound = 1058,
+    RefreshIdMismatch = 1059,
+    RefreshAuthInvalid = 1060,
+    RefreshUserDisabled = 1061,
+    RefreshUserDeleted = 1062,
+    LogoutAuthNotFound = 1101,
+    LogoutAuthDisabled = 1102,
+    AcceptInvitationInvalid = 1151,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogTypeInfo {
+    pub code: i16,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+impl LogType {
+    pub const ALL: [LogType; 33] = [
+        LogType::Login,
+        LogType::Refresh,
+        LogType::Logout,
+        LogType::ChangePassword,
+        LogType::UpdateProfile,
+        LogType::UpdateAvatar,
+        LogType::CloseAccount,
+        LogType::ExportData,
+        LogType::AcceptInvitation,
+        LogType::AdminCreateUser,
+        LogType::AdminUpdateUser,
+        LogType::AdminEnableUser,
+        LogType::AdminDisableUser,
+        LogType::AdminDeleteUser,
+        LogType::AdminRestoreUser,
+        LogType::AdminResetPassword,
+        LogType::AdminCreateInvitation,
+        LogType::AdminRevokeInvitation,
+        LogType::AdminRevokeSessions,
+        LogType::AdminReloadSettings,
+        LogType::LoginWrongPassword,
+        LogType::LoginUserDisabled,
+        LogType::LoginUserNotFound,
+        LogType::LoginUserDeleted,
+        LogType::RefreshNoPermission,
+        LogType::RefreshAuthNotFound,
+        LogType::RefreshIdMismatch,
+        LogType::RefreshAuthInvalid,
+        LogType::RefreshUserDisabled,
+        LogType::RefreshUserDeleted,
+        LogType::LogoutAuthNotFound,
+        LogType::LogoutAuthDisabled,
+        LogType::AcceptInvitationInvalid,
+    ];
+
+    pub fn code(self) -> i16 {
+        self as i16
+    }
+
+    pub fn from_code(code: i16) -> Option<LogType> {
+        LogType::ALL.iter().copied().find(|v| v.code() == code)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.names().0
+    }
+
+    pub fn description(self) -> &'static str {
+        self.names().1
+    }
+
+    pub fn info(self) -> LogTypeInfo {
+        LogTypeInfo {
+            code: self.code(),
+            name: self.name(),
+            description: self.description(),
+        }
+    }
+
+    fn names(self) -> (&'static str, &'static str) {
+        match self {
+            LogType::Login => ("login", "登录成功"),
+            LogType::Refresh => ("refresh", "刷新授权"),
+            LogType::Logout => ("logout", "退出登录"),
+            LogType::ChangePassword => ("change_password", "修改密码"),
+            LogType::UpdateProfile => ("update_profile", "修改个人资料"),
+            LogType::UpdateAvatar => ("update_avatar", "修改头像"),
+            LogType::CloseAccount => ("close_account", "注销账号"),
+            LogType::ExportData => ("export_data", "导出个人数据"),
+            LogType::AcceptInvitation => ("accept_invitation", "接受邀请注册"),
+            LogType::AdminCreateUser => ("admin_create_user", "管理员创建用户"),
+            LogType::AdminUpdateUser => ("admin_update_user", "管理员编辑用户"),
+            LogType::AdminEnableUser => ("admin_enable_user", "管理员启用用户"),
+            LogType::AdminDisableUser => ("admin_disable_user", "管理员禁用用户"),
+            LogType::AdminDeleteUser => ("admin_delete_user", "管理员删除用户"),
+            LogType::AdminRestoreUser => ("admin_restore_user", "管理员恢复用户"),
+            LogType::AdminResetPassword => ("admin_reset_password", "管理员重置密码"),
+            LogType::AdminCreateInvitation => ("admin_create_invitation", "管理员创建邀请"),
+            LogType::AdminRevokeInvitation => ("admin_revoke_invitation", "管理员撤销邀请"),
+            LogType::AdminRevokeSessions => ("admin_revoke_sessions", "管理员撤销用户所有授权"),
+            LogType::AdminReloadSettings => ("admin_reload_settings", "管理员重新加载配置"),
+            LogType::LoginWrongPassword => ("login_wrong_password", "登录失败：密码错误"),
+            LogType::LoginUserDisabled => ("login_user_disabled", "登录失败：用户已禁用"),
+            LogType::LoginUserNotFound => ("login_user_not_found", "登录失败：用户不存在"),
+            LogType::LoginUserDeleted => ("login_user_deleted", "登录失败：用户已删除"),
+            LogType::RefreshNoPermission => ("refresh_no_permission", "刷新失败：不是refresh token"),
+            LogType::RefreshAuthNotFound => ("refresh_auth_not_found", "刷新失败：授权或用户不存在"),
+            LogType::RefreshIdMismatch => ("refresh_id_mismatch", "刷新失败：授权id不匹配"),
+            LogType::RefreshAuthInvalid => ("refresh_auth_invalid", "刷新失败：授权已撤销或refresh token已使用"),
+            LogType::RefreshUserDisabled => ("refresh_user_disabled", "刷新失败：用户已禁用"),
+            LogType::RefreshUserDeleted => ("refresh_user_deleted", "刷新失败：用户已删除"),
+            LogType::LogoutAuthNotFound => ("logout_auth_not_found", "退出失败：授权不存在"),
+            LogType::LogoutAuthDisabled => ("logout_auth_disabled", "退出失败：授权已撤销"),
+            LogType::AcceptInvitationInvalid => ("accept_invitation_invalid", "接受邀请失败：邀请无效或已过期"),
+        }
+    }
+}
@@ -1,23 +1,30 @@
 pub mod controller;
+pub mod admin_controller;
 pub mod model;
 pub mod service;
+pub mod log_type;
 
 use chrono::prelude::*;
+use serde::Serialize;
 
-#[derive(Debug)]
+pub use log_type::LogType;
+
+#[derive(Debug, Clone)]
 pub struct AuthBlacklist {
     pub id: Option<i32>,
     pub access_token_id: uuid::Uuid,
     pub access_token_exp: DateTime<Utc>,
     pub user_id: i32,
+    pub tenant_id: i32,
 }
 
-#[derive(Debug, sqlx::FromRow)]
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
 pub struct Authorization {
     pub id: Option<i32>,
     pub user_id: Option<i32>,
     pub uuid: Option<uuid::Uuid>,
     pub client_type: Option<i16>,
+    #[serde(skip_serializing)]
     pub refresh_token: Option<uuid::Uuid>,
     pub create_time: Option<DateTime<Utc>>,
     pub update_time: Option<DateTime<Utc>>,
@@ -26,10 +33,36 @@ pub struct Authorization {
     pub access_token_exp: Option<DateTime<Utc>>,
     pub access_token_iat: Option<DateTime<Utc>>,
     pub is_enabled: Option<i16>,
+    pub tenant_id: Option<i32>,
 }
 
 #[derive(Debug)]
 pub struct AuthorizationInfo {
     pub id: i32,
+    pub tenant_id: i32,
     pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct AuthorizationLog {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub log_type: i16,
+    pub ip: Option<String>,
+    pub log_time: DateTime<Utc>,
+    pub client_type: i16,
+    pub auth_id: Option<i32>,
+    pub log: Option<String>,
+    pub user_agent: Option<String>,
+    pub tenant_id: i32,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct LogFilter {
+    pub tenant_id: i32,
+    pub user_id: Option<i32>,
+    pub log_type: Option<i16>,
+    pub ip: Option<String>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
 }
\ No newline at end of file
@@ -1,12 +1,12 @@
-use crate::lib::{client::ClientInfo, error};
+use crate::lib::{client::ClientInfo, db, error, trace::Traced};
 use chrono::{DateTime, Utc};
-use super::{AuthBlacklist, Authorization};
+use super::{AuthBlacklist, Authorization, AuthorizationLog, LogFilter};
 
 // 添加日志
-pub async fn insert_log(log_type: i16, msg: &str, user_id: i32, auth_id: i32, client: &ClientInfo, log_time: DateTime<Utc>, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<(), error::Error> {
-    let r = sqlx::query(r#"
-        INSERT INTO authorizations_logs (user_id, log_type, ip, log_time, client_type, auth_id, log, user_agent)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#)
+pub async fn insert_log<'e, E: sqlx::Executor<'e, Database = db::Db>>(log_type: i16, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, log_time: DateTime<Utc>, db: E, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql(r#"
+        INSERT INTO authorizations_logs (user_id, log_type, ip, log_time, client_type, auth_id, log, user_agent, tenant_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"#))
         .bind(user_id)
         .bind(log_type)
         .bind(&client.ip)
@@ -15,7 +15,9 @@ pub async fn insert_log(log_type: i16, msg: &str, user_id: i32, auth_id: i32, cl
         .bind(auth_id)
         .bind(msg)
         .bind(&client.user_agent)
+        .bind(tenant_id)
         .execute(db)
+        .traced("authorizations.insert_log")
         .await;
     
     if let Err(err) = r {
@@ -27,14 +29,16 @@ pub async fn insert_log(log_type: i16, msg: &str, user_id: i32, auth_id: i32, cl
 }
 
 // 将用户登录的token加入黑名单
-pub async fn insert_auth_black_list(auth_black_list: &AuthBlacklist, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<(), error::Error> {
-    let r = sqlx::query(r#"
-        INSERT INTO authorizations_blacklist (access_token_id, access_token_exp, user_id)
-	    VALUES ($1, $2, $3)"#)
-        .bind(&auth_black_list.access_token_id)
-        .bind(&auth_black_list.access_token_exp)
-        .bind(&auth_black_list.user_id)
+pub async fn insert_auth_black_list<'e, E: sqlx::Executor<'e, Database = db::Db>>(auth_black_list: &AuthBlacklist, db: E, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql(r#"
+        INSERT INTO authorizations_blacklist (access_token_id, access_token_exp, user_id, tenant_id)
+	    VALUES ($1, $2, $3, $4)"#))
+        .bind(auth_black_list.access_token_id)
+        .bind(auth_black_list.access_token_exp)
+        .bind(auth_black_list.user_id)
+        .bind(auth_black_list.tenant_id)
         .execute(db)
+        .traced("authorizations.insert_auth_black_list")
         .await;
     
     if let Err(err) = r {
@@ -45,23 +49,41 @@ pub async fn insert_auth_black_list(auth_black_list: &AuthBlacklist, db: &sqlx::
     Ok(())
 }
 
-// 插入授权
-pub async fn insert_auth(authorization: &Authorization, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<Authorization, error::Error> {
-    let r = sqlx::query_as::<_, Authorization>(r#"
-        INSERT INTO authorizations (user_id, uuid, client_type, refresh_token, create_time, access_token_id, access_token_exp, access_token_iat, is_enabled)
-	    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-	    RETURNING *"#)
-        .bind(&authorization.user_id)
-        .bind(&authorization.uuid)
-        .bind(&authorization.client_type)
-        .bind(&authorization.refresh_token)
-        .bind(&authorization.create_time)
-        .bind(&authorization.access_token_id)
-        .bind(&authorization.access_token_exp)
-        .bind(&authorization.access_token_iat)
-        .bind(&authorization.is_enabled)
-        .fetch_one(db)
+// 删除已过期的黑名单记录，返回删除数量
+pub async fn purge_auth_black_list(now: DateTime<Utc>, db: &db::Pool, log: &slog::Logger) -> Result<u64, error::Error> {
+    let r = sqlx::query(&db::sql("DELETE FROM authorizations_blacklist WHERE access_token_exp < $1"))
+        .bind(now)
+        .execute(db)
+        .traced("authorizations.purge_auth_black_list")
         .await;
+
+    match r {
+        Ok(v) => Ok(v.rows_affected()),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 插入授权
+pub async fn insert_auth(authorization: &Authorization, db: &mut db::Connection, log: &slog::Logger) -> Result<Authorization, error::Error> {
+    let sql = db::returning(r#"
+        INSERT INTO authorizations (user_id, uuid, client_type, refresh_token, create_time, access_token_id, access_token_exp, access_token_iat, is_enabled, tenant_id)
+	    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"#);
+    let q = sqlx::query(&sql)
+        .bind(authorization.user_id)
+        .bind(authorization.uuid)
+        .bind(authorization.client_type)
+        .bind(authorization.refresh_token)
+        .bind(authorization.create_time)
+        .bind(authorization.access_token_id)
+        .bind(authorization.access_token_exp)
+        .bind(authorization.access_token_iat)
+        .bind(authorization.is_enabled)
+        .bind(authorization.tenant_id);
+
+    let r = db::fetch_returning::<Authorization>(q, "authorizations", None, db).traced("authorizations.insert_auth").await;
     
     match r {
         Ok(v) => Ok(v),
@@ -74,11 +96,13 @@ pub async fn insert_auth(authorization: &Authorization, db: &sqlx::Pool<sqlx::Po
 
 
 // 禁用授权
-pub async fn disable_auth(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<(), error::Error> {
-    let r = sqlx::query("UPDATE authorizations SET is_enabled=0, update_time=$1 WHERE id=$2")
+pub async fn disable_auth<'e, E: sqlx::Executor<'e, Database = db::Db>>(id: i32, tenant_id: i32, db: E, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql("UPDATE authorizations SET is_enabled=0, update_time=$1 WHERE id=$2 AND tenant_id=$3"))
         .bind(Utc::now())
         .bind(id)
+        .bind(tenant_id)
         .execute(db)
+        .traced("authorizations.disable_auth")
         .await;
     
     if let Err(err) = r {
@@ -90,13 +114,12 @@ pub async fn disable_auth(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::
 }
 
 // 通过id获取授权信息
-pub async fn get_by_id(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
-    let r = sqlx::query_as::<_, Authorization>(r#"
-        SELECT a.*
-        FROM authorizations a INNER JOIN users b ON a.user_id=b.id
-        WHERE a.id=$1 AND b.is_enabled=1 AND b.is_del=0"#)
+pub async fn get_by_id(id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+    let r = sqlx::query_as::<_, Authorization>(&db::sql("SELECT * FROM authorizations WHERE id=$1 AND tenant_id=$2"))
         .bind(id)
+        .bind(tenant_id)
         .fetch_optional(db)
+        .traced("authorizations.get_by_id")
         .await;
     
     match r {
@@ -109,10 +132,12 @@ pub async fn get_by_id(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Log
 }
 
 // 通过uuid获取授权信息
-pub async fn get_by_uuid(uuid: uuid::Uuid, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
-    let r = sqlx::query_as::<_, Authorization>("SELECT * FROM authorizations WHERE uuid=$1")
+pub async fn get_by_uuid(uuid: uuid::Uuid, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+    let r = sqlx::query_as::<_, Authorization>(&db::sql("SELECT * FROM authorizations WHERE uuid=$1 AND tenant_id=$2"))
         .bind(uuid)
+        .bind(tenant_id)
         .fetch_optional(db)
+        .traced("authorizations.get_by_uuid")
         .await;
     
     match r {
@@ -124,12 +149,9 @@ pub async fn get_by_uuid(uuid: uuid::Uuid, db: &sqlx::Pool<sqlx::Postgres>, log:
     }
 }
 
-// 更新授权
-pub async fn update_auth(authorization: &Authorization, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<Authorization, error::Error> {
-    let id = match authorization.id {
-        Some(v) => v,
-        None => 0,
-    };
+// 更新授权，refresh_token 不是 old_refresh_token 时不更新并返回 None，防止同一个refresh token被并发重复使用
+pub async fn update_auth(authorization: &Authorization, old_refresh_token: uuid::Uuid, tenant_id: i32, db: &mut db::Connection, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+    let id = authorization.id.unwrap_or_default();
 
     if id <= 0 {
         error!(log, "update id error: {}", id);
@@ -139,30 +161,30 @@ pub async fn update_auth(authorization: &Authorization, db: &sqlx::Pool<sqlx::Po
     let mut sql1 = vec![format!("update_time = $1")];
     let mut sql_index = 2;
 
-    if let Some(_) = authorization.refresh_token {
+    if authorization.refresh_token.is_some() {
         sql1.push(format!("refresh_token = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = authorization.last_refresh_time {
+    if authorization.last_refresh_time.is_some() {
         sql1.push(format!("last_refresh_time = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = authorization.access_token_id {
+    if authorization.access_token_id.is_some() {
         sql1.push(format!("access_token_id = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = authorization.access_token_exp {
+    if authorization.access_token_exp.is_some() {
         sql1.push(format!("access_token_exp = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = authorization.access_token_iat {
+    if authorization.access_token_iat.is_some() {
         sql1.push(format!("access_token_iat = ${}", sql_index));
         sql_index += 1;
     }
 
-    let sql = format!("UPDATE authorizations SET {} WHERE id = ${} RETURNING *", sql1.join(","), sql_index);
+    let sql = db::returning(&format!("UPDATE authorizations SET {} WHERE id = ${} AND refresh_token = ${} AND tenant_id = ${}", sql1.join(","), sql_index, sql_index + 1, sql_index + 2));
 
-    let mut q = sqlx::query_as::<_, Authorization>(&sql);
+    let mut q = sqlx::query(&sql);
 
     q = q.bind(Utc::now());
     if let Some(refresh_token) = authorization.refresh_token {
@@ -181,8 +203,220 @@ pub async fn update_auth(authorization: &Authorization, db: &sqlx::Pool<sqlx::Po
         q = q.bind(access_token_iat);
     }
     q = q.bind(id);
+    q = q.bind(old_refresh_token);
+    q = q.bind(tenant_id);
+
+    let r = db::fetch_returning::<Authorization>(q, "authorizations", Some(id), db).traced("authorizations.update_auth").await;
+    
+    match r {
+        Ok(v) => Ok(Some(v)),
+        Err(sqlx::Error::RowNotFound) => Ok(None),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 获取用户所有授权
+pub async fn get_by_user_id(user_id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Vec<Authorization>, error::Error> {
+    let r = sqlx::query_as::<_, Authorization>(&db::sql("SELECT * FROM authorizations WHERE user_id=$1 AND tenant_id=$2 ORDER BY id"))
+        .bind(user_id)
+        .bind(tenant_id)
+        .fetch_all(db)
+        .traced("authorizations.get_by_user_id")
+        .await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 禁用用户所有授权
+pub async fn disable_by_user_id<'e, E: sqlx::Executor<'e, Database = db::Db>>(user_id: i32, tenant_id: i32, db: E, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql("UPDATE authorizations SET is_enabled=0, update_time=$1 WHERE user_id=$2 AND tenant_id=$3 AND is_enabled=1"))
+        .bind(Utc::now())
+        .bind(user_id)
+        .bind(tenant_id)
+        .execute(db)
+        .traced("authorizations.disable_by_user_id")
+        .await;
+    
+    if let Err(err) = r {
+        error!(log, "{}", err);
+        return Err(error::err500());
+    }
+
+    Ok(())
+}
+
+// 获取用户日志
+pub async fn get_logs_by_user_id(user_id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+    let r = sqlx::query_as::<_, AuthorizationLog>(&db::sql("SELECT * FROM authorizations_logs WHERE user_id=$1 AND tenant_id=$2 ORDER BY id"))
+        .bind(user_id)
+        .bind(tenant_id)
+        .fetch_all(db)
+        .traced("authorizations.get_logs_by_user_id")
+        .await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 清除用户日志中的个人信息
+pub async fn anonymize_logs(user_id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql("UPDATE authorizations_logs SET ip=NULL, user_agent=NULL, log=NULL WHERE user_id=$1 AND tenant_id=$2"))
+        .bind(user_id)
+        .bind(tenant_id)
+        .execute(db)
+        .traced("authorizations.anonymize_logs")
+        .await;
+    
+    if let Err(err) = r {
+        error!(log, "{}", err);
+        return Err(error::err500());
+    }
+
+    Ok(())
+}
+
+// 拼接日志查询条件，返回 WHERE 子句和下一个参数序号
+fn logs_where(filter: &LogFilter) -> (String, i32) {
+    let mut sql1 = vec![String::from("tenant_id = $1")];
+    let mut sql_index = 2;
+
+    if filter.user_id.is_some() {
+        sql1.push(format!("user_id = ${}", sql_index));
+        sql_index += 1;
+    }
+    if filter.log_type.is_some() {
+        sql1.push(format!("log_type = ${}", sql_index));
+        sql_index += 1;
+    }
+    if filter.ip.is_some() {
+        sql1.push(format!("ip = ${}", sql_index));
+        sql_index += 1;
+    }
+    if filter.start_time.is_some() {
+        sql1.push(format!("log_time >= ${}", sql_index));
+        sql_index += 1;
+    }
+    if filter.end_time.is_some() {
+        sql1.push(format!("log_time < ${}", sql_index));
+        sql_index += 1;
+    }
+
+    (sql1.join(" AND "), sql_index)
+}
+
+// 分页查询日志，按id倒序
+pub async fn search_logs(filter: &LogFilter, offset: i64, limit: i64, db: &db::Pool, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+    let (sql_where, sql_index) = logs_where(filter);
+    let sql = format!("SELECT * FROM authorizations_logs WHERE {} ORDER BY id DESC LIMIT ${} OFFSET ${}", sql_where, sql_index, sql_index + 1);
+    let sql = db::sql(&sql);
+
+    let mut q = sqlx::query_as::<_, AuthorizationLog>(&sql);
+
+    q = q.bind(filter.tenant_id);
+    if let Some(user_id) = filter.user_id {
+        q = q.bind(user_id);
+    }
+    if let Some(log_type) = filter.log_type {
+        q = q.bind(log_type);
+    }
+    if let Some(ip) = &filter.ip {
+        q = q.bind(ip);
+    }
+    if let Some(start_time) = filter.start_time {
+        q = q.bind(start_time);
+    }
+    if let Some(end_time) = filter.end_time {
+        q = q.bind(end_time);
+    }
+    q = q.bind(limit).bind(offset);
+
+    let r = q.fetch_all(db).traced("authorizations.search_logs").await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+pub async fn count_logs(filter: &LogFilter, db: &db::Pool, log: &slog::Logger) -> Result<i64, error::Error> {
+    let (sql_where, _) = logs_where(filter);
+    let sql = format!("SELECT COUNT(*) FROM authorizations_logs WHERE {}", sql_where);
+    let sql = db::sql(&sql);
+
+    let mut q = sqlx::query_scalar::<_, i64>(&sql);
+
+    q = q.bind(filter.tenant_id);
+    if let Some(user_id) = filter.user_id {
+        q = q.bind(user_id);
+    }
+    if let Some(log_type) = filter.log_type {
+        q = q.bind(log_type);
+    }
+    if let Some(ip) = &filter.ip {
+        q = q.bind(ip);
+    }
+    if let Some(start_time) = filter.start_time {
+        q = q.bind(start_time);
+    }
+    if let Some(end_time) = filter.end_time {
+        q = q.bind(end_time);
+    }
+
+    let r = q.fetch_one(db).traced("authorizations.count_logs").await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 按id正序获取after_id之后的日志，用于分批导出
+pub async fn get_logs_after(filter: &LogFilter, after_id: i32, limit: i64, db: &db::Pool, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+    let (sql_where, sql_index) = logs_where(filter);
+    let sql = format!("SELECT * FROM authorizations_logs WHERE {} AND id > ${} ORDER BY id LIMIT ${}", sql_where, sql_index, sql_index + 1);
+    let sql = db::sql(&sql);
+
+    let mut q = sqlx::query_as::<_, AuthorizationLog>(&sql);
+
+    q = q.bind(filter.tenant_id);
+    if let Some(user_id) = filter.user_id {
+        q = q.bind(user_id);
+    }
+    if let Some(log_type) = filter.log_type {
+        q = q.bind(log_type);
+    }
+    if let Some(ip) = &filter.ip {
+        q = q.bind(ip);
+    }
+    if let Some(start_time) = filter.start_time {
+        q = q.bind(start_time);
+    }
+    if let Some(end_time) = filter.end_time {
+        q = q.bind(end_time);
+    }
+    q = q.bind(after_id).bind(limit);
 
-    let r = q.fetch_one(db).await;
+    let r = q.fetch_all(db).traced("authorizations.get_logs_after").await;
     
     match r {
         Ok(v) => Ok(v),
@@ -1,58 +1,77 @@
 use actix_web::web;
-use futures::future::{BoxFuture, join_all};
 use crate::AppState;
-use crate::{lib, lib::{client::ClientInfo, error}};
-use crate::api::authorizations::model;
+use crate::lib::{client::ClientInfo, error};
 use chrono::prelude::*;
-use super::{AuthBlacklist, Authorization};
-use crate::api::user;
+use super::{AuthBlacklist, Authorization, AuthorizationLog, LogFilter, LogType};
+use crate::api::{repository::Transaction, user};
+
+// 开始事务，多步写入通过事务执行，commit 之前出错返回时自动回滚
+pub async fn begin(state: &web::Data<AppState>) -> Result<Box<dyn Transaction>, error::Error> {
+    state.repo.transactions.begin(&state.log).await
+}
+
+// 提交事务
+pub async fn commit(tx: Box<dyn Transaction>, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    tx.commit(&state.log).await
+}
+
+// 日志内容的最大字符数，与 authorizations_logs.log 的长度一致
+const LOG_MSG_MAX: usize = 250;
+
+// 超长的日志内容截断保存
+fn truncate_msg(msg: &str) -> &str {
+    match msg.char_indices().nth(LOG_MSG_MAX) {
+        Some((i, _)) => &msg[..i],
+        None => msg,
+    }
+}
 
 // 添加日志
-pub async fn insert_log(log_type: i16, msg: &str, user_id: i32, auth_id: i32, client: &ClientInfo, state: &web::Data<AppState>) -> Result<(), error::Error> {
-    model::insert_log(log_type, msg, user_id, auth_id, client, Utc::now(), &state.db, &state.log).await?;
-    
+pub async fn insert_log(log_type: LogType, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    state.repo.logs.insert(log_type.code(), truncate_msg(msg), user_id, auth_id, tenant_id, client, Utc::now(), &state.log).await?;
+    state.metrics.auth_event(log_type);
+
+    Ok(())
+}
+
+// 在事务中添加日志，提交成功后才计入指标
+pub async fn insert_log_tx(log_type: LogType, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, tx: &mut dyn Transaction, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    tx.insert_log(log_type.code(), truncate_msg(msg), user_id, auth_id, tenant_id, client, Utc::now(), &state.log).await?;
+    let metrics = state.metrics.clone();
+    tx.after_commit(Box::new(move || metrics.auth_event(log_type)));
+
     Ok(())
 }
 
 // 将用户登录的token加入黑名单
 pub async fn add_black_list(auth_black_list: &AuthBlacklist, state: &web::Data<AppState>) -> Result<(), error::Error> {
-    let task1 = model::insert_auth_black_list(&auth_black_list, &state.db, &state.log);
-    let mut hold: Vec<BoxFuture<_>> = vec![Box::pin(task1)];
-
-    let diff = auth_black_list
-        .access_token_exp
-        .signed_duration_since(Utc::now())
-        .num_seconds();
-    if diff > 0 {
-        let task2 = lib::redis::set_with_expire(
-            format!("auth_black_list_{}", auth_black_list.access_token_id),
-            auth_black_list.user_id,
-            diff,
-            &state.redis,
-            &state.log
-        );
-        hold.push(Box::pin(task2));
-    }
+    state.repo.black_list.insert(auth_black_list, &state.log).await
+}
 
-    for result in join_all(hold).await {
-        result?;
-    }
+// 在事务中将token加入黑名单
+pub async fn add_black_list_tx(auth_black_list: &AuthBlacklist, tx: &mut dyn Transaction, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    tx.insert_black_list(auth_black_list, &state.log).await
+}
 
-    Ok(())
+// 清理已过期的黑名单
+pub async fn purge_black_list(state: &web::Data<AppState>) -> Result<u64, error::Error> {
+    let result = state.repo.black_list.purge(Utc::now(), &state.log).await?;
+
+    Ok(result)
 }
 
 // 检查id是否在黑名单中
-pub async fn is_in_black_list(id: &String, state: &web::Data<AppState>) -> Result<bool, error::Error> {
-    let result = lib::redis::has_key(format!("auth_black_list_{}", id), &state.redis, &state.log).await?;
+pub async fn is_in_black_list(id: &str, state: &web::Data<AppState>) -> Result<bool, error::Error> {
+    let result = state.repo.black_list.contains(id, &state.log).await?;
 
     Ok(result)
 }
 
 // 创建授权
-pub async fn create_auth(authorization: &Authorization, client: &ClientInfo, state: &web::Data<AppState>) -> Result<i32, error::Error> {
-    let result = model::insert_auth(authorization, &state.db, &state.log).await?;
+pub async fn create_auth(authorization: &Authorization, client: &ClientInfo, tx: &mut dyn Transaction, state: &web::Data<AppState>) -> Result<i32, error::Error> {
+    let result = tx.insert_auth(authorization, &state.log).await?;
     if let Some(user_id) = authorization.user_id {
-        user::service::update_last_login(Utc::now(), user_id, &client, &state).await?;
+        user::service::update_last_login(Utc::now(), user_id, authorization.tenant_id.unwrap_or_default(), client, tx, state).await?;
     }
     if let Some(v) = result.id {
         return Ok(v);
@@ -62,34 +81,98 @@ pub async fn create_auth(authorization: &Authorization, client: &ClientInfo, sta
 }
 
 // 撤销授权
-pub async fn revoke_auth(id: i32, state: &web::Data<AppState>) -> Result<(), error::Error> {
-    model::disable_auth(id, &state.db, &state.log).await?;
+pub async fn revoke_auth(id: i32, tenant_id: i32, tx: &mut dyn Transaction, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    tx.disable_auth(id, tenant_id, &state.log).await?;
 
     Ok(())
 }
 
 // 通过id获取授权信息
-pub async fn get_by_id(id: i32, state: &web::Data<AppState>) -> Result<Option<Authorization>, error::Error> {
-    let result = model::get_by_id(id, &state.db, &state.log).await?;
+pub async fn get_by_id(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<Authorization>, error::Error> {
+    let result = state.repo.authorizations.get_by_id(id, tenant_id, &state.log).await?;
 
     Ok(result)
 }
 
 // 通过uuid获取授权信息
-pub async fn get_by_uuid(uuid: &str, state: &web::Data<AppState>) -> Result<Option<Authorization>, error::Error> {
+pub async fn get_by_uuid(uuid: &str, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<Authorization>, error::Error> {
     let uid = match uuid::Uuid::parse_str(uuid) {
         Err(_) => return Err(error::new(100403, "Authentication failure", 401)),
         Ok(v) => v
     };
     
-    let result = model::get_by_uuid(uid, &state.db, &state.log).await?;
+    let result = state.repo.authorizations.get_by_uuid(uid, tenant_id, &state.log).await?;
 
     Ok(result)
 }
 
-// 更新授权
-pub async fn update_auth(authorization: &Authorization, state: &web::Data<AppState>) -> Result<Authorization, error::Error> {
-    let result = model::update_auth(authorization, &state.db, &state.log).await?;
+// 更新授权，refresh token 已被使用过时返回 None
+pub async fn update_auth(authorization: &Authorization, old_refresh_token: uuid::Uuid, tenant_id: i32, tx: &mut dyn Transaction, state: &web::Data<AppState>) -> Result<Option<Authorization>, error::Error> {
+    let result = tx.update_auth(authorization, old_refresh_token, tenant_id, &state.log).await?;
+
+    Ok(result)
+}
+
+// 撤销用户所有授权，并将未过期的access token加入黑名单
+pub async fn revoke_all_by_user(user_id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    let mut tx = begin(state).await?;
+    revoke_all_by_user_tx(user_id, tenant_id, tx.as_mut(), state).await?;
+
+    commit(tx, state).await
+}
+
+// 在事务中撤销用户所有授权，与其他写入一起提交
+pub async fn revoke_all_by_user_tx(user_id: i32, tenant_id: i32, tx: &mut dyn Transaction, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    let auths = state.repo.authorizations.get_by_user_id(user_id, tenant_id, &state.log).await?;
+    let now = Utc::now();
+
+    for v in auths {
+        if v.is_enabled != Some(1) {
+            continue;
+        }
+        if let (Some(access_token_id), Some(access_token_exp)) = (v.access_token_id, v.access_token_exp) {
+            if access_token_exp > now {
+                let authorization_blacklist = AuthBlacklist {
+                    id: None,
+                    access_token_id,
+                    access_token_exp,
+                    user_id,
+                    tenant_id,
+                };
+                add_black_list_tx(&authorization_blacklist, tx, state).await?;
+            }
+        }
+    }
+
+    tx.disable_auth_by_user_id(user_id, tenant_id, &state.log).await
+}
+
+// 获取用户所有授权
+pub async fn get_by_user_id(user_id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<Vec<Authorization>, error::Error> {
+    let result = state.repo.authorizations.get_by_user_id(user_id, tenant_id, &state.log).await?;
+
+    Ok(result)
+}
+
+// 获取用户日志
+pub async fn get_logs_by_user_id(user_id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<Vec<AuthorizationLog>, error::Error> {
+    let result = state.repo.logs.get_by_user_id(user_id, tenant_id, &state.log).await?;
+
+    Ok(result)
+}
+
+// 分页查询日志，返回当前页数据和总数
+pub async fn search_logs(filter: &LogFilter, page: i64, page_size: i64, state: &web::Data<AppState>) -> Result<(Vec<AuthorizationLog>, i64), error::Error> {
+    let offset = (page - 1) * page_size;
+    let items = state.repo.logs.search(filter, offset, page_size, &state.log).await?;
+    let total = state.repo.logs.count(filter, &state.log).await?;
+
+    Ok((items, total))
+}
+
+// 分批获取日志，用于导出
+pub async fn get_logs_after(filter: &LogFilter, after_id: i32, limit: i64, state: &web::Data<AppState>) -> Result<Vec<AuthorizationLog>, error::Error> {
+    let result = state.repo.logs.get_after(filter, after_id, limit, &state.log).await?;
 
     Ok(result)
 }
@@ -0,0 +1,30 @@
+use actix_web::{web, get, HttpResponse};
+use crate::AppState;
+use crate::lib::{error, storage};
+
+fn content_type(key: &str) -> &'static str {
+    match key.rsplit('.').next() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+// 读取存储中的文件
+#[get("/files/{key:.*}")]
+pub async fn get_file(path: web::Path<String>, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let key = path.into_inner();
+    if !storage::valid_key(&key) {
+        return Err(error::new(404, "Not Found", 404));
+    }
+
+    match state.storage.get(&key).await? {
+        None => Err(error::new(404, "Not Found", 404)),
+        Some(v) => Ok(HttpResponse::Ok()
+            .content_type(content_type(&key))
+            .insert_header(("Cache-Control", "public, max-age=86400"))
+            .body(v)),
+    }
+}
@@ -0,0 +1,58 @@
+use actix_web::{web, get, HttpResponse};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use crate::AppState;
+use crate::lib::{self, health::{self, DependencyStatus}};
+
+#[derive(Serialize)]
+struct ResHealthJson {
+    status: &'static str,
+    checks: BTreeMap<&'static str, DependencyStatus>,
+}
+
+async fn check(state: &web::Data<AppState>) -> BTreeMap<&'static str, DependencyStatus> {
+    let config = state.config.get();
+    // 内存存储不依赖数据库和redis
+    if config.repository.is_memory() {
+        return BTreeMap::new();
+    }
+
+    let timeout = Duration::from_millis(config.health.timeout_ms);
+    let (db, redis) = futures::join!(
+        health::check_db(&state.db, timeout),
+        health::check_redis(&state.redis, timeout),
+    );
+
+    BTreeMap::from([(lib::db::NAME, db), ("redis", redis)])
+}
+
+// 存活检查，进程能处理请求即返回200，依赖不可用时status为degraded
+#[get("/healthz")]
+pub async fn healthz(state: web::Data<AppState>) -> HttpResponse {
+    let checks = check(&state).await;
+    let status = if checks.values().all(|v| v.is_up()) { "ok" } else { "degraded" };
+
+    HttpResponse::Ok().json(ResHealthJson {status, checks})
+}
+
+// 就绪检查，依赖不可用或正在停止服务时返回503
+#[get("/readyz")]
+pub async fn readyz(state: web::Data<AppState>) -> HttpResponse {
+    let checks = check(&state).await;
+
+    let status = if !state.ready.load(Ordering::Relaxed) {
+        "shutting_down"
+    } else if checks.values().all(|v| v.is_up()) {
+        "ok"
+    } else {
+        "unavailable"
+    };
+
+    if status == "ok" {
+        HttpResponse::Ok().json(ResHealthJson {status, checks})
+    } else {
+        HttpResponse::ServiceUnavailable().json(ResHealthJson {status, checks})
+    }
+}
\ No newline at end of file
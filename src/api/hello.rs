@@ -9,7 +9,7 @@ struct Hello {
 
 #[get("/hello")]
 pub async fn hello(state: web::Data<AppState>) -> impl Responder {
-    let name = state.config.get::<String>("app.name").unwrap();
+    let name = state.config.get().app.name.clone();
     info!(state.log, "hello {}", name);
     HttpResponse::Ok().json(Hello {msg: format!("hello {}", name)})
 }
@@ -0,0 +1,156 @@
+use actix_web::{web, get, post, delete, HttpResponse, HttpRequest};
+use actix_web::dev::ConnectionInfo;
+use serde::{Serialize, Deserialize};
+use crate::AppState;
+use crate::lib::{error, validator, client, auth};
+use crate::api::user::admin_controller::check_user_type;
+use crate::api::authorizations::{self, LogType};
+use super::{service, Invitation, InvitationFilter};
+
+const STATUSES: [&str; 4] = ["pending", "accepted", "revoked", "expired"];
+
+#[derive(Deserialize)]
+pub struct CreateInvitationReqJson {
+    email: Option<String>,
+    mobile: Option<String>,
+    user_type: Option<i16>,
+    expire_hours: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct SearchInvitationsReqQuery {
+    status: Option<String>,
+    email: Option<String>,
+    mobile: Option<String>,
+    page: Option<i64>,
+    page_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ResInvitationJson {
+    #[serde(flatten)]
+    invitation: Invitation,
+    status: &'static str,
+    // 明文token只在创建时返回一次
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+impl From<Invitation> for ResInvitationJson {
+    fn from(invitation: Invitation) -> Self {
+        ResInvitationJson {
+            status: invitation.status(),
+            invitation,
+            token: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ResInvitationListJson {
+    total: i64,
+    page: i64,
+    page_size: i64,
+    items: Vec<ResInvitationJson>,
+}
+
+fn non_empty(v: &Option<String>) -> Option<String> {
+    match v {
+        Some(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        _ => None,
+    }
+}
+
+// 创建邀请
+#[post("/invitations")]
+pub async fn create(req_info: web::Json<CreateInvitationReqJson>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let email = non_empty(&req_info.email);
+    if let Some(v) = &email {
+        validator::email(v, "邮箱")?;
+        validator::max_len(v, 100, "邮箱")?;
+    }
+
+    let mobile = non_empty(&req_info.mobile);
+    if let Some(v) = &mobile {
+        validator::mobile(v, "手机号")?;
+    }
+
+    if email.is_none() && mobile.is_none() {
+        return Err(error::new(400002, "邮箱和手机号不能都为空", 422));
+    }
+
+    let user_type = req_info.user_type.unwrap_or(0);
+    check_user_type(user_type)?;
+
+    let config = state.config.get();
+    let max_expire_hours = config.invitation.max_expire_hours;
+    let expire_hours = req_info.expire_hours.unwrap_or(config.invitation.expire_hours);
+    if expire_hours < 1 || expire_hours > max_expire_hours {
+        return Err(error::new(400002, &format!("有效期需在1到{}小时之间", max_expire_hours)[..], 422));
+    }
+
+    let (invitation, token) = service::create(email, mobile, user_type, expire_hours, auth_info.tenant_id, auth_info.id, &state).await?;
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminCreateInvitation, &format!("operator={},invitation={}", auth_info.id, invitation.id), 0, 0, auth_info.tenant_id, &client, &state).await?;
+
+    let mut res = ResInvitationJson::from(invitation);
+    res.token = Some(token);
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+// 邀请列表
+#[get("/invitations")]
+pub async fn list(query: web::Query<SearchInvitationsReqQuery>, req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let status = non_empty(&query.status);
+    if let Some(v) = &status {
+        if !STATUSES.contains(&&v[..]) {
+            return Err(error::new(400002, "邀请状态错误", 422));
+        }
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let filter = InvitationFilter {
+        tenant_id: auth_info.tenant_id,
+        status,
+        email: non_empty(&query.email),
+        mobile: non_empty(&query.mobile),
+    };
+
+    let (items, total) = service::search(&filter, page, page_size, &state).await?;
+
+    Ok(HttpResponse::Ok().json(ResInvitationListJson {
+        total,
+        page,
+        page_size,
+        items: items.into_iter().map(ResInvitationJson::from).collect(),
+    }))
+}
+
+// 撤销邀请
+#[delete("/invitations/{id}")]
+pub async fn revoke(path: web::Path<i32>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let id = path.into_inner();
+    let invitation = match service::get_by_id(id, auth_info.tenant_id, &state).await? {
+        None => return Err(error::new(400021, "邀请不存在", 422)),
+        Some(v) => v,
+    };
+
+    if invitation.status() != "pending" || !service::revoke(id, auth_info.tenant_id, &state).await? {
+        return Err(error::new(400022, "只能撤销未使用的邀请", 422));
+    }
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminRevokeInvitation, &format!("operator={},invitation={}", auth_info.id, id), 0, 0, auth_info.tenant_id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().body(""))
+}
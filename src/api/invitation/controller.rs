@@ -0,0 +1,59 @@
+use actix_web::{web, post, HttpResponse, HttpRequest};
+use actix_web::dev::ConnectionInfo;
+use serde::Deserialize;
+use crate::AppState;
+use crate::lib::{error, validator, client};
+use crate::api::{tenant, user};
+use crate::api::authorizations::{self, LogType, controller::ResTokenJson};
+use super::service;
+
+#[derive(Deserialize)]
+pub struct AcceptInvitationReqJson {
+    token: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    name: Option<String>,
+}
+
+// 接受邀请，创建账号并登录
+#[post("/invitations/accept")]
+pub async fn accept(req_info: web::Json<AcceptInvitationReqJson>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let token = validator::required_str(&req_info.token, "邀请码")?;
+    let username = validator::required_str(&req_info.username, "用户名")?;
+    let password = validator::required_str(&req_info.password, "密码")?;
+
+    let name = match &req_info.name {
+        Some(v) if !v.trim().is_empty() => Some(v.trim().to_string()),
+        _ => None,
+    };
+    if let Some(v) = &name {
+        validator::max_len(v, 50, "姓名")?;
+    }
+
+    let client = client::get_client_info(&state, &req, &conn);
+    let tenant_id = tenant::service::resolve(&req, &state).await?.id;
+
+    let invitation = match service::get_by_token(&token, tenant_id, &state).await? {
+        Some(v) if v.status() == "pending" => v,
+        v => {
+            let msg = v.map(|v| format!("invitation={}", v.id)).unwrap_or_default();
+            authorizations::service::insert_log(LogType::AcceptInvitationInvalid, &msg, 0, 0, tenant_id, &client, &state).await?;
+            return Err(error::new(400020, "邀请无效或已过期", 422));
+        }
+    };
+
+    if user::service::get_by_username(&username, tenant_id, &state).await?.is_some() {
+        return Err(error::new(400009, "用户名已存在", 422));
+    }
+
+    let auth = service::accept(&invitation, username, &password, name, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().json(ResTokenJson {
+        id: auth.refresh_token_id.to_string(),
+        access_token: auth.access_token.token,
+        expires_in: auth.access_token.expire,
+        refresh_token: auth.refresh_token.token,
+        created_at: format!("{:?}", auth.access_token.create_time),
+        updated_at: format!("{:?}", auth.access_token.create_time),
+    }))
+}
@@ -0,0 +1,58 @@
+pub mod controller;
+pub mod admin_controller;
+pub mod model;
+pub mod service;
+
+use chrono::prelude::*;
+use serde::Serialize;
+
+#[derive(Debug, sqlx::FromRow, Serialize)]
+pub struct Invitation {
+    pub id: i32,
+    pub tenant_id: i32,
+    pub email: Option<String>,
+    pub mobile: Option<String>,
+    pub user_type: i16,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub expire_time: DateTime<Utc>,
+    pub create_time: DateTime<Utc>,
+    pub created_by: i32,
+    pub accept_time: Option<DateTime<Utc>>,
+    pub user_id: Option<i32>,
+    pub revoke_time: Option<DateTime<Utc>>,
+}
+
+impl Invitation {
+    // pending: 待接受，accepted: 已接受，revoked: 已撤销，expired: 已过期
+    pub fn status(&self) -> &'static str {
+        if self.accept_time.is_some() {
+            "accepted"
+        } else if self.revoke_time.is_some() {
+            "revoked"
+        } else if self.expire_time <= Utc::now() {
+            "expired"
+        } else {
+            "pending"
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NewInvitation {
+    pub tenant_id: i32,
+    pub email: Option<String>,
+    pub mobile: Option<String>,
+    pub user_type: i16,
+    pub token_hash: String,
+    pub expire_time: DateTime<Utc>,
+    pub created_by: i32,
+}
+
+#[derive(Debug, Default)]
+pub struct InvitationFilter {
+    pub tenant_id: i32,
+    pub status: Option<String>,
+    pub email: Option<String>,
+    pub mobile: Option<String>,
+}
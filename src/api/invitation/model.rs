@@ -0,0 +1,207 @@
+use crate::lib::{db, error, trace::Traced};
+use chrono::prelude::*;
+use super::{Invitation, NewInvitation, InvitationFilter};
+
+pub async fn insert(invitation: &NewInvitation, db: &db::Pool, log: &slog::Logger) -> Result<Invitation, error::Error> {
+    let sql = db::returning(r#"
+        INSERT INTO invitations (tenant_id, email, mobile, user_type, token_hash, expire_time, create_time, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#);
+    let q = sqlx::query(&sql)
+        .bind(invitation.tenant_id)
+        .bind(&invitation.email)
+        .bind(&invitation.mobile)
+        .bind(invitation.user_type)
+        .bind(&invitation.token_hash)
+        .bind(invitation.expire_time)
+        .bind(Utc::now())
+        .bind(invitation.created_by);
+
+    let r = async {
+        let mut conn = db.acquire().await?;
+        db::fetch_returning::<Invitation>(q, "invitations", None, &mut conn).await
+    }.traced("invitation.insert").await;
+
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+pub async fn get_by_id(id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<Invitation>, error::Error> {
+    let r = sqlx::query_as::<_, Invitation>(&db::sql("SELECT * FROM invitations WHERE id=$1 AND tenant_id=$2"))
+        .bind(id)
+        .bind(tenant_id)
+        .fetch_optional(db)
+        .traced("invitation.get_by_id")
+        .await;
+
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+pub async fn get_by_token_hash(token_hash: &str, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<Invitation>, error::Error> {
+    let r = sqlx::query_as::<_, Invitation>(&db::sql("SELECT * FROM invitations WHERE token_hash=$1 AND tenant_id=$2"))
+        .bind(token_hash)
+        .bind(tenant_id)
+        .fetch_optional(db)
+        .traced("invitation.get_by_token_hash")
+        .await;
+
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 获取同一邮箱或手机号未使用且未过期的邀请
+pub async fn get_pending_by_contact(email: &Option<String>, mobile: &Option<String>, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<Invitation>, error::Error> {
+    let r = sqlx::query_as::<_, Invitation>(&db::sql(r#"
+        SELECT * FROM invitations
+        WHERE tenant_id=$1 AND (email=$2 OR mobile=$3)
+            AND accept_time IS NULL AND revoke_time IS NULL AND expire_time > $4
+        LIMIT 1"#))
+        .bind(tenant_id)
+        .bind(email)
+        .bind(mobile)
+        .bind(Utc::now())
+        .fetch_optional(db)
+        .traced("invitation.get_pending_by_contact")
+        .await;
+
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 撤销邀请，只有未使用的邀请可以撤销，返回是否撤销成功
+pub async fn revoke(id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<bool, error::Error> {
+    let r = sqlx::query(&db::sql("UPDATE invitations SET revoke_time=$1 WHERE id=$2 AND tenant_id=$3 AND accept_time IS NULL AND revoke_time IS NULL"))
+        .bind(Utc::now())
+        .bind(id)
+        .bind(tenant_id)
+        .execute(db)
+        .traced("invitation.revoke")
+        .await;
+
+    match r {
+        Ok(v) => Ok(v.rows_affected() == 1),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 标记邀请已接受，条件更新保证邀请只能使用一次，返回是否标记成功
+pub async fn accept<'e, E: sqlx::Executor<'e, Database = db::Db>>(id: i32, user_id: i32, db: E, log: &slog::Logger) -> Result<bool, error::Error> {
+    let now = Utc::now();
+    let r = sqlx::query(&db::sql("UPDATE invitations SET accept_time=$1, user_id=$2 WHERE id=$3 AND accept_time IS NULL AND revoke_time IS NULL AND expire_time > $4"))
+        .bind(now)
+        .bind(user_id)
+        .bind(id)
+        .bind(now)
+        .execute(db)
+        .traced("invitation.accept")
+        .await;
+
+    match r {
+        Ok(v) => Ok(v.rows_affected() == 1),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 拼接邀请查询条件，返回 WHERE 子句和下一个参数序号
+fn search_where(filter: &InvitationFilter) -> (String, i32) {
+    let mut sql1 = vec![String::from("tenant_id = $1")];
+    let mut sql_index = 2;
+
+    match filter.status.as_deref() {
+        Some("pending") => sql1.push(String::from("accept_time IS NULL AND revoke_time IS NULL AND expire_time > now()")),
+        Some("accepted") => sql1.push(String::from("accept_time IS NOT NULL")),
+        Some("revoked") => sql1.push(String::from("revoke_time IS NOT NULL")),
+        Some("expired") => sql1.push(String::from("accept_time IS NULL AND revoke_time IS NULL AND expire_time <= now()")),
+        _ => {}
+    }
+    if filter.email.is_some() {
+        sql1.push(format!("email LIKE ${} {}", sql_index, db::LIKE_ESCAPE));
+        sql_index += 1;
+    }
+    if filter.mobile.is_some() {
+        sql1.push(format!("mobile LIKE ${} {}", sql_index, db::LIKE_ESCAPE));
+        sql_index += 1;
+    }
+
+    (sql1.join(" AND "), sql_index)
+}
+
+// 分页查询邀请，按id倒序
+pub async fn search(filter: &InvitationFilter, offset: i64, limit: i64, db: &db::Pool, log: &slog::Logger) -> Result<Vec<Invitation>, error::Error> {
+    let (sql_where, sql_index) = search_where(filter);
+    let sql = format!("SELECT * FROM invitations WHERE {} ORDER BY id DESC LIMIT ${} OFFSET ${}", sql_where, sql_index, sql_index + 1);
+    let sql = db::sql(&sql);
+
+    let mut q = sqlx::query_as::<_, Invitation>(&sql);
+
+    q = q.bind(filter.tenant_id);
+    if let Some(email) = &filter.email {
+        q = q.bind(db::like(email));
+    }
+    if let Some(mobile) = &filter.mobile {
+        q = q.bind(db::like(mobile));
+    }
+    q = q.bind(limit).bind(offset);
+
+    let r = q.fetch_all(db).traced("invitation.search").await;
+
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+pub async fn count(filter: &InvitationFilter, db: &db::Pool, log: &slog::Logger) -> Result<i64, error::Error> {
+    let (sql_where, _) = search_where(filter);
+    let sql = format!("SELECT COUNT(*) FROM invitations WHERE {}", sql_where);
+    let sql = db::sql(&sql);
+
+    let mut q = sqlx::query_scalar::<_, i64>(&sql);
+
+    q = q.bind(filter.tenant_id);
+    if let Some(email) = &filter.email {
+        q = q.bind(db::like(email));
+    }
+    if let Some(mobile) = &filter.mobile {
+        q = q.bind(db::like(mobile));
+    }
+
+    let r = q.fetch_one(db).traced("invitation.count").await;
+
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
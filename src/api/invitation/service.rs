@@ -0,0 +1,140 @@
+use actix_web::web;
+use crate::AppState;
+use crate::lib::{self, client::ClientInfo, error, auth};
+use crate::api::{authorizations, user};
+use rand::Rng;
+use sha2::{Sha256, Digest};
+use chrono::prelude::*;
+use chrono::Duration;
+use super::{model, Invitation, NewInvitation, InvitationFilter};
+
+// 数据库只保存token的哈希值
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::new().chain_update(token).finalize())
+}
+
+fn new_token() -> String {
+    rand::rng().random::<[u8; 32]>().iter().map(|v| format!("{:02x}", v)).collect()
+}
+
+pub async fn get_by_id(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<Invitation>, error::Error> {
+    let result = model::get_by_id(id, tenant_id, &state.db, &state.log).await?;
+
+    Ok(result)
+}
+
+pub async fn get_by_token(token: &str, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<Invitation>, error::Error> {
+    let result = model::get_by_token_hash(&hash_token(token), tenant_id, &state.db, &state.log).await?;
+
+    Ok(result)
+}
+
+// 创建邀请并通过邮件或短信发送，返回邀请和明文token
+pub async fn create(email: Option<String>, mobile: Option<String>, user_type: i16, expire_hours: i64, tenant_id: i32, operator_id: i32, state: &web::Data<AppState>) -> Result<(Invitation, String), error::Error> {
+    if let Some(v) = &mobile {
+        if user::service::get_by_mobile(v, tenant_id, state).await?.is_some() {
+            return Err(error::new(400013, "手机号已被使用", 422));
+        }
+    }
+
+    if model::get_pending_by_contact(&email, &mobile, tenant_id, &state.db, &state.log).await?.is_some() {
+        return Err(error::new(400019, "该联系方式已有未使用的邀请", 422));
+    }
+
+    let token = new_token();
+    let invitation = NewInvitation {
+        tenant_id,
+        email,
+        mobile,
+        user_type,
+        token_hash: hash_token(&token),
+        expire_time: Utc::now() + Duration::hours(expire_hours),
+        created_by: operator_id,
+    };
+    let invitation = model::insert(&invitation, &state.db, &state.log).await?;
+
+    let config = state.config.get();
+    let accept_url = &config.invitation.accept_url;
+    let content = if accept_url.is_empty() {
+        format!("您收到一个注册邀请，邀请码：{}，{}小时内有效", token, expire_hours)
+    } else {
+        format!("您收到一个注册邀请，请访问 {}?token={} 完成注册，{}小时内有效", accept_url, token, expire_hours)
+    };
+    let sent = async {
+        if let Some(v) = &invitation.email {
+            lib::mail::send(v, "注册邀请", &content, &state.log).await?;
+        }
+        if let Some(v) = &invitation.mobile {
+            lib::sms::send(v, lib::sms::TEMPLATE_INVITATION, &[&content], &state.log).await?;
+        }
+        Ok::<(), error::Error>(())
+    }.await;
+    // 发送失败时撤销邀请，避免重试时被未使用的邀请拦住
+    if let Err(e) = sent {
+        model::revoke(invitation.id, tenant_id, &state.db, &state.log).await?;
+        return Err(e);
+    }
+    // 不记录token，只记录邀请id
+    info!(state.log, "invitation {} sent", invitation.id);
+
+    Ok((invitation, token))
+}
+
+// 分页查询邀请，返回当前页数据和总数
+pub async fn search(filter: &InvitationFilter, page: i64, page_size: i64, state: &web::Data<AppState>) -> Result<(Vec<Invitation>, i64), error::Error> {
+    let offset = (page - 1) * page_size;
+    let items = model::search(filter, offset, page_size, &state.db, &state.log).await?;
+    let total = model::count(filter, &state.db, &state.log).await?;
+
+    Ok((items, total))
+}
+
+pub async fn revoke(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<bool, error::Error> {
+    let result = model::revoke(id, tenant_id, &state.db, &state.log).await?;
+
+    Ok(result)
+}
+
+// 接受邀请：在同一个事务中创建用户并标记邀请已使用，然后为新用户登录
+pub async fn accept(invitation: &Invitation, username: String, password: &str, name: Option<String>, client: &ClientInfo, state: &web::Data<AppState>) -> Result<auth::Auth, error::Error> {
+    let salt = auth::salt();
+
+    let mut user = user::User::new();
+    user.username = Some(username);
+    user.password = Some(auth::crypt_password(password, &salt));
+    user.salt = Some(salt);
+    user.mobile = invitation.mobile.clone();
+    user.name = name;
+    user.user_type = Some(invitation.user_type);
+    user.tenant_id = Some(invitation.tenant_id);
+
+    let mut tx = match lib::db::begin(&state.db, &state.metrics).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!(state.log, "{}", e);
+            return Err(error::err500());
+        }
+    };
+
+    let user = user::model::insert(&user, &mut tx, &state.log).await?;
+    let user_id = user.id.unwrap_or_default();
+
+    // 并发接受同一个邀请时只有一个能成功，其余回滚
+    if !model::accept(invitation.id, user_id, &mut *tx, &state.log).await? {
+        return Err(error::new(400020, "邀请无效或已过期", 422));
+    }
+
+    if let Err(e) = tx.commit().await {
+        error!(state.log, "{}", e);
+        return Err(error::err500());
+    }
+
+    authorizations::service::insert_log(authorizations::LogType::AcceptInvitation, &format!("invitation={}", invitation.id), user_id, 0, invitation.tenant_id, client, state).await?;
+
+    let mut tx = authorizations::service::begin(state).await?;
+    let auth = auth::create_auth(user_id, invitation.user_type, invitation.tenant_id, client, tx.as_mut(), state).await?;
+    authorizations::service::insert_log_tx(authorizations::LogType::Login, "", user_id, auth.auth_id, invitation.tenant_id, client, tx.as_mut(), state).await?;
+    authorizations::service::commit(tx, state).await?;
+
+    Ok(auth)
+}
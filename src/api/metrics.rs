@@ -0,0 +1,15 @@
+use actix_web::{web, get, HttpResponse};
+use crate::AppState;
+use crate::lib::error;
+
+// prometheus采集接口
+#[get("/metrics")]
+pub async fn metrics(state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    if !state.config.get().metrics.enabled {
+        return Err(error::new(404, "Not Found", 404));
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(state.metrics.render(&state).await))
+}
\ No newline at end of file
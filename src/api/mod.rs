@@ -1,3 +1,11 @@
 pub mod hello;
 pub mod authorizations;
-pub mod user;
\ No newline at end of file
+pub mod user;
+pub mod files;
+pub mod tenant;
+pub mod invitation;
+pub mod preferences;
+pub mod settings;
+pub mod health;
+pub mod metrics;
+pub mod repository;
\ No newline at end of file
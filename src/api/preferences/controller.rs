@@ -0,0 +1,44 @@
+use actix_web::{web, get, put, patch, HttpResponse, HttpRequest};
+use serde_json::{Map, Value};
+use crate::AppState;
+use crate::lib::{error, auth};
+use super::service;
+
+fn to_object(v: Value) -> Result<Map<String, Value>, error::Error> {
+    match v {
+        Value::Object(v) => Ok(v),
+        _ => Err(error::new(400002, "偏好设置必须是JSON对象", 422)),
+    }
+}
+
+// 获取偏好设置
+#[get("/user/preferences")]
+pub async fn get(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
+
+    let data = service::get(auth_info.id, &state).await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+// 替换全部偏好设置
+#[put("/user/preferences")]
+pub async fn replace(req_info: web::Json<Value>, req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
+
+    let data = to_object(req_info.into_inner())?;
+    let data = service::replace(auth_info.id, data, &state).await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+// 合并更新偏好设置，值为null时删除该项
+#[patch("/user/preferences")]
+pub async fn merge(req_info: web::Json<Value>, req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
+
+    let patch = to_object(req_info.into_inner())?;
+    let data = service::merge(auth_info.id, patch, &state).await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
@@ -0,0 +1,43 @@
+pub mod controller;
+pub mod model;
+pub mod service;
+
+// 偏好设置值的类型约束
+#[derive(Debug)]
+pub enum PreferenceType {
+    Bool,
+    Int { min: i64, max: i64 },
+    Str { max_len: usize },
+    Enum(&'static [&'static str]),
+}
+
+#[derive(Debug)]
+pub struct PreferenceSchema {
+    pub key: &'static str,
+    pub kind: PreferenceType,
+}
+
+// 已知的偏好设置项，写入时按类型校验；未登记的key由 preferences.allow_unknown_keys 决定是否允许
+pub const SCHEMAS: &[PreferenceSchema] = &[
+    PreferenceSchema { key: "theme", kind: PreferenceType::Enum(&["light", "dark", "system"]) },
+    PreferenceSchema { key: "language", kind: PreferenceType::Enum(&["zh-CN", "en-US"]) },
+    PreferenceSchema { key: "timezone", kind: PreferenceType::Str { max_len: 64 } },
+    PreferenceSchema { key: "page_size", kind: PreferenceType::Int { min: 10, max: 100 } },
+    PreferenceSchema { key: "notify_email", kind: PreferenceType::Bool },
+    PreferenceSchema { key: "notify_sms", kind: PreferenceType::Bool },
+];
+
+impl PreferenceType {
+    pub fn is_valid(&self, v: &serde_json::Value) -> bool {
+        match self {
+            PreferenceType::Bool => v.is_boolean(),
+            PreferenceType::Int { min, max } => v.as_i64().map(|v| v >= *min && v <= *max).unwrap_or(false),
+            PreferenceType::Str { max_len } => v.as_str().map(|v| v.chars().count() <= *max_len).unwrap_or(false),
+            PreferenceType::Enum(values) => v.as_str().map(|v| values.contains(&v)).unwrap_or(false),
+        }
+    }
+}
+
+pub fn get_schema(key: &str) -> Option<&'static PreferenceSchema> {
+    SCHEMAS.iter().find(|v| v.key == key)
+}
@@ -0,0 +1,65 @@
+use crate::lib::{db, error, trace::Traced};
+use chrono::prelude::*;
+
+pub async fn get_by_user_id<'e, E: sqlx::Executor<'e, Database = db::Db>>(user_id: i32, for_update: bool, db: E, log: &slog::Logger) -> Result<Option<serde_json::Value>, error::Error> {
+    let sql = if for_update {
+        "SELECT data FROM user_preferences WHERE user_id=$1 FOR UPDATE"
+    } else {
+        "SELECT data FROM user_preferences WHERE user_id=$1"
+    };
+    let r = sqlx::query_scalar::<_, serde_json::Value>(&db::sql(sql))
+        .bind(user_id)
+        .fetch_optional(db)
+        .traced("preferences.get_by_user_id")
+        .await;
+
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+#[cfg(feature = "postgres")]
+const SAVE_SQL: &str = r#"
+    INSERT INTO user_preferences (user_id, data, update_time) VALUES ($1, $2, $3)
+    ON CONFLICT (user_id) DO UPDATE SET data = EXCLUDED.data, update_time = EXCLUDED.update_time"#;
+#[cfg(feature = "mysql")]
+const SAVE_SQL: &str = r#"
+    INSERT INTO user_preferences (user_id, data, update_time) VALUES ($1, $2, $3)
+    ON DUPLICATE KEY UPDATE data = VALUES(data), update_time = VALUES(update_time)"#;
+
+// 保存用户全部偏好设置，不存在时插入
+pub async fn save<'e, E: sqlx::Executor<'e, Database = db::Db>>(user_id: i32, data: &serde_json::Value, db: E, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql(SAVE_SQL))
+        .bind(user_id)
+        .bind(data)
+        .bind(Utc::now())
+        .execute(db)
+        .traced("preferences.save")
+        .await;
+
+    if let Err(err) = r {
+        error!(log, "{}", err);
+        return Err(error::err500());
+    }
+
+    Ok(())
+}
+
+pub async fn delete(user_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql("DELETE FROM user_preferences WHERE user_id=$1"))
+        .bind(user_id)
+        .execute(db)
+        .traced("preferences.delete")
+        .await;
+
+    if let Err(err) = r {
+        error!(log, "{}", err);
+        return Err(error::err500());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use actix_web::web;
+use serde_json::{Map, Value};
+use crate::AppState;
+use crate::lib::{self, error};
+use super::{model, get_schema};
+
+fn cache_key(user_id: i32) -> String {
+    format!("user_preferences_{}", user_id)
+}
+
+// 校验偏好设置：key格式、已知key的类型、key数量和总大小
+fn validate(data: &Map<String, Value>, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    let config = state.config.get();
+    let allow_unknown_keys = config.preferences.allow_unknown_keys;
+    let max_keys = config.preferences.max_keys;
+    let max_size = config.preferences.max_size;
+
+    if data.len() > max_keys {
+        return Err(error::new(400023, &format!("偏好设置不能超过{}项", max_keys)[..], 422));
+    }
+
+    for (key, value) in data {
+        if key.is_empty() || key.len() > 64 || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-') {
+            return Err(error::new(400002, &format!("偏好设置项{}名称格式不正确", key)[..], 422));
+        }
+
+        match get_schema(key) {
+            Some(schema) => {
+                if !schema.kind.is_valid(value) {
+                    return Err(error::new(400002, &format!("偏好设置项{}的值不正确", key)[..], 422));
+                }
+            },
+            None => {
+                if !allow_unknown_keys {
+                    return Err(error::new(400002, &format!("不支持的偏好设置项{}", key)[..], 422));
+                }
+            },
+        }
+    }
+
+    let size = serde_json::to_vec(data).map(|v| v.len()).unwrap_or_default();
+    if size > max_size {
+        return Err(error::new(400023, &format!("偏好设置不能超过{}字节", max_size)[..], 422));
+    }
+
+    Ok(())
+}
+
+// 获取用户偏好设置，优先读取缓存
+// 内存存储时偏好设置的路由不注册，导出数据时返回空的设置
+pub async fn get(user_id: i32, state: &web::Data<AppState>) -> Result<Map<String, Value>, error::Error> {
+    if state.config.get().repository.is_memory() {
+        return Ok(Map::new());
+    }
+    let ttl = state.config.get().preferences.cache_ttl;
+
+    state.cache.get_or_set(&cache_key(user_id), ttl, &state.log, || async {
+        match model::get_by_user_id(user_id, false, &state.db, &state.log).await? {
+            Some(Value::Object(v)) => Ok(v),
+            _ => Ok(Map::new()),
+        }
+    }).await
+}
+
+// 替换用户全部偏好设置
+pub async fn replace(user_id: i32, data: Map<String, Value>, state: &web::Data<AppState>) -> Result<Map<String, Value>, error::Error> {
+    validate(&data, state)?;
+
+    let data = Value::Object(data);
+    model::save(user_id, &data, &state.db, &state.log).await?;
+    // 保存后直接写入新值，避免并发读取时把旧值重新写回缓存
+    state.cache.set(&cache_key(user_id), &data, state.config.get().preferences.cache_ttl, &state.log).await?;
+
+    match data {
+        Value::Object(v) => Ok(v),
+        _ => Ok(Map::new()),
+    }
+}
+
+// 合并更新用户偏好设置，值为null的key会被删除
+pub async fn merge(user_id: i32, patch: Map<String, Value>, state: &web::Data<AppState>) -> Result<Map<String, Value>, error::Error> {
+    let mut tx = match lib::db::begin(&state.db, &state.metrics).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!(state.log, "{}", e);
+            return Err(error::err500());
+        }
+    };
+
+    let mut data = match model::get_by_user_id(user_id, true, &mut *tx, &state.log).await? {
+        Some(Value::Object(v)) => v,
+        _ => Map::new(),
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            data.remove(&key);
+        } else {
+            data.insert(key, value);
+        }
+    }
+
+    validate(&data, state)?;
+
+    let data = Value::Object(data);
+    model::save(user_id, &data, &mut *tx, &state.log).await?;
+
+    if let Err(e) = tx.commit().await {
+        error!(state.log, "{}", e);
+        return Err(error::err500());
+    }
+
+    state.cache.set(&cache_key(user_id), &data, state.config.get().preferences.cache_ttl, &state.log).await?;
+
+    match data {
+        Value::Object(v) => Ok(v),
+        _ => Ok(Map::new()),
+    }
+}
+
+// 删除用户偏好设置，用于注销账号后的匿名化
+pub async fn delete(user_id: i32, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    if state.config.get().repository.is_memory() {
+        return Ok(());
+    }
+    model::delete(user_id, &state.db, &state.log).await?;
+    state.cache.del(&cache_key(user_id), &state.log).await?;
+
+    Ok(())
+}
@@ -0,0 +1,742 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use async_trait::async_trait;
+use crate::lib::{client::ClientInfo, error};
+use crate::api::user::{User, UserInfo, UserAdminInfo, UserFilter};
+use crate::api::tenant::Tenant;
+use crate::api::authorizations::{AuthBlacklist, Authorization, AuthorizationLog, LogFilter};
+use super::{UserRepository, TenantRepository, AuthorizationRepository, AuthorizationLogRepository, BlacklistRepository, Transaction, TransactionRepository, AfterCommit};
+
+#[derive(Default)]
+struct Tables {
+    tenants: Vec<Tenant>,
+    users: Vec<User>,
+    // users表的anonymize_time，User中没有该字段
+    anonymize_time: HashMap<i32, DateTime<Utc>>,
+    authorizations: Vec<Authorization>,
+    logs: Vec<AuthorizationLog>,
+    black_list: Vec<AuthBlacklist>,
+}
+
+// 数据保存在内存中，用于测试和没有postgres、redis的环境，重启后数据丢失
+// 唯一约束和默认值与 sql/migrations/postgres 中的表结构一致
+#[derive(Clone)]
+pub struct MemoryRepository {
+    tables: Arc<Mutex<Tables>>,
+}
+
+impl MemoryRepository {
+    // 与数据库迁移一样创建id为1的默认租户
+    pub fn new(default_tenant: &str) -> Self {
+        let tables = Tables {
+            tenants: vec![Tenant {
+                id: 1,
+                code: default_tenant.to_string(),
+                name: Some(String::from("Default")),
+                is_enabled: 1,
+                create_time: Utc::now(),
+            }],
+            ..Default::default()
+        };
+
+        Self {
+            tables: Arc::new(Mutex::new(tables)),
+        }
+    }
+}
+
+fn not_found(what: &str, id: i32, log: &slog::Logger) -> error::Error {
+    error!(log, "{} {} not found", what, id);
+    error::err500()
+}
+
+// 检查 users 表的唯一约束：uuid，租户内的username，租户内未删除用户的mobile
+// 用户名和手机号冲突时与 user::model 一样返回业务错误
+fn check_user_unique(tables: &Tables, user: &User, log: &slog::Logger) -> Result<(), error::Error> {
+    for v in tables.users.iter().filter(|v| v.id != user.id) {
+        if v.tenant_id == user.tenant_id && user.username.is_some() && v.username == user.username {
+            return Err(error::new(400009, "用户名已存在", 422));
+        }
+        if v.tenant_id == user.tenant_id && user.mobile.is_some() && v.mobile == user.mobile && v.is_del == Some(0) && user.is_del == Some(0) {
+            return Err(error::new(400013, "手机号已被使用", 422));
+        }
+        if v.uuid == user.uuid {
+            error!(log, "duplicate user uuid {:?}", user.uuid);
+            return Err(error::err500());
+        }
+    }
+
+    Ok(())
+}
+
+// 事务回滚会删除行，id不能使用记录数，返回插入的id
+fn insert_log(tables: &mut Tables, log_type: i16, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, log_time: DateTime<Utc>) -> i32 {
+    let id = tables.logs.iter().map(|v| v.id).max().unwrap_or_default() + 1;
+    tables.logs.push(AuthorizationLog {
+        id,
+        user_id: Some(user_id),
+        log_type,
+        ip: Some(client.ip.clone()),
+        log_time,
+        client_type: 10,
+        auth_id: Some(auth_id),
+        log: Some(msg.to_string()),
+        user_agent: Some(client.user_agent.clone()),
+        tenant_id,
+    });
+
+    id
+}
+
+fn insert_user(tables: &mut Tables, user: &User, log: &slog::Logger) -> Result<User, error::Error> {
+    let tenant_id = user.tenant_id.unwrap_or_default();
+    if tenant_id <= 0 {
+        error!(log, "insert tenant_id error: {}", tenant_id);
+        return Err(error::err500());
+    }
+
+    let row = User {
+        id: Some(tables.users.len() as i32 + 1),
+        uuid: Some(user.uuid.unwrap_or_else(uuid::Uuid::new_v4)),
+        create_time: Some(user.create_time.unwrap_or_else(Utc::now)),
+        is_del: Some(user.is_del.unwrap_or(0)),
+        is_enabled: Some(user.is_enabled.unwrap_or(1)),
+        user_type: Some(user.user_type.unwrap_or(0)),
+        last_login_time: None,
+        last_login_ip: None,
+        avatar: None,
+        ..user.clone()
+    };
+    check_user_unique(tables, &row, log)?;
+    tables.users.push(row.clone());
+
+    Ok(row)
+}
+
+fn admin_info(user: &User) -> UserAdminInfo {
+    UserAdminInfo {
+        id: user.id.unwrap_or_default(),
+        uuid: user.uuid.unwrap_or_default(),
+        username: user.username.clone(),
+        name: user.name.clone(),
+        mobile: user.mobile.clone(),
+        user_type: user.user_type.unwrap_or_default(),
+        is_enabled: user.is_enabled.unwrap_or_default(),
+        is_del: user.is_del.unwrap_or_default(),
+        create_time: user.create_time,
+        update_time: user.update_time,
+        last_login_time: user.last_login_time,
+        last_login_ip: user.last_login_ip.clone(),
+    }
+}
+
+fn contains(value: &Option<String>, pattern: &str) -> bool {
+    value.as_deref().is_some_and(|v| v.contains(pattern))
+}
+
+fn match_user(user: &User, filter: &UserFilter) -> bool {
+    user.tenant_id == Some(filter.tenant_id)
+        && filter.username.as_ref().is_none_or(|v| contains(&user.username, v))
+        && filter.mobile.as_ref().is_none_or(|v| contains(&user.mobile, v))
+        && filter.user_type.is_none_or(|v| user.user_type == Some(v))
+        && filter.is_enabled.is_none_or(|v| user.is_enabled == Some(v))
+        && filter.is_del.is_none_or(|v| user.is_del == Some(v))
+}
+
+// 与 ORDER BY ... NULLS LAST 一致，空值总是排在最后
+fn compare<T: Ord>(a: &Option<T>, b: &Option<T>, desc: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if desc => b.cmp(a),
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn match_log(v: &AuthorizationLog, filter: &LogFilter) -> bool {
+    v.tenant_id == filter.tenant_id
+        && filter.user_id.is_none_or(|id| v.user_id == Some(id))
+        && filter.log_type.is_none_or(|t| v.log_type == t)
+        && filter.ip.as_ref().is_none_or(|ip| v.ip.as_ref() == Some(ip))
+        && filter.start_time.is_none_or(|t| v.log_time >= t)
+        && filter.end_time.is_none_or(|t| v.log_time < t)
+}
+
+fn page<T>(items: impl Iterator<Item = T>, offset: i64, limit: i64) -> Vec<T> {
+    items.skip(offset.max(0) as usize).take(limit.max(0) as usize).collect()
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn get_by_id(&self, id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<Option<User>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.users.iter().find(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id)).cloned())
+    }
+
+    async fn get_by_username(&self, username: &str, tenant_id: i32, _log: &slog::Logger) -> Result<Option<User>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.users.iter().find(|v| v.username.as_deref() == Some(username) && v.tenant_id == Some(tenant_id)).cloned())
+    }
+
+    async fn get_by_mobile(&self, mobile: &str, tenant_id: i32, _log: &slog::Logger) -> Result<Option<User>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.users.iter().find(|v| v.mobile.as_deref() == Some(mobile) && v.tenant_id == Some(tenant_id) && v.is_del == Some(0)).cloned())
+    }
+
+    async fn insert(&self, user: &User, log: &slog::Logger) -> Result<User, error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+
+        insert_user(&mut tables, user, log)
+    }
+
+    async fn insert_many(&self, users: &[User], log: &slog::Logger) -> Result<Vec<User>, error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        let len = tables.users.len();
+
+        let mut result = Vec::with_capacity(users.len());
+        for user in users {
+            match insert_user(&mut tables, user, log) {
+                Ok(v) => result.push(v),
+                Err(e) => {
+                    tables.users.truncate(len);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn update(&self, user: &User, tenant_id: i32, log: &slog::Logger) -> Result<User, error::Error> {
+        let id = user.id.unwrap_or_default();
+        if id <= 0 {
+            error!(log, "update id error: {}", id);
+            return Err(error::err500());
+        }
+
+        let mut tables = self.tables.lock().unwrap();
+        let index = match tables.users.iter().position(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id)) {
+            Some(v) => v,
+            None => return Err(not_found("user", id, log)),
+        };
+
+        let mut row = tables.users[index].clone();
+        row.update_time = Some(Utc::now());
+        if user.username.is_some() {
+            row.username = user.username.clone();
+        }
+        if user.password.is_some() {
+            row.password = user.password.clone();
+        }
+        if user.salt.is_some() {
+            row.salt = user.salt;
+        }
+        if user.mobile.is_some() {
+            row.mobile = user.mobile.clone().filter(|v| !v.is_empty());
+        }
+        if user.name.is_some() {
+            row.name = user.name.clone();
+        }
+        if user.avatar.is_some() {
+            row.avatar = user.avatar.clone();
+        }
+        if user.is_enabled.is_some() {
+            row.is_enabled = user.is_enabled;
+        }
+        if user.last_login_time.is_some() {
+            row.last_login_time = user.last_login_time;
+        }
+        if user.last_login_ip.is_some() {
+            row.last_login_ip = user.last_login_ip.clone();
+        }
+        if user.user_type.is_some() {
+            row.user_type = user.user_type;
+        }
+
+        check_user_unique(&tables, &row, log)?;
+        tables.users[index] = row.clone();
+
+        Ok(row)
+    }
+
+    async fn update_last_login(&self, login_time: DateTime<Utc>, ip: &str, user_id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        for v in tables.users.iter_mut().filter(|v| v.id == Some(user_id) && v.tenant_id == Some(tenant_id)) {
+            v.last_login_time = Some(login_time);
+            v.last_login_ip = Some(ip.to_string());
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        for v in tables.users.iter_mut().filter(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id)) {
+            v.is_del = Some(1);
+        }
+
+        Ok(())
+    }
+
+    async fn restore(&self, id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<bool, error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        let mut restored = false;
+        for v in tables.users.iter_mut().filter(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id) && v.password.is_some()) {
+            v.is_del = Some(0);
+            v.update_time = Some(Utc::now());
+            restored = true;
+        }
+        if restored {
+            tables.anonymize_time.remove(&id);
+        }
+
+        Ok(restored)
+    }
+
+    async fn get_user_info_by_id(&self, id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<Option<UserInfo>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+        let user = tables.users.iter().find(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id) && v.is_del == Some(0) && v.is_enabled == Some(1));
+
+        Ok(user.map(|v| UserInfo {
+            id,
+            username: v.username.clone(),
+            name: v.name.clone(),
+            uuid: v.uuid.unwrap_or_default(),
+            mobile: v.mobile.clone(),
+            last_login_time: v.last_login_time,
+            last_login_ip: v.last_login_ip.clone(),
+            user_type: v.user_type.unwrap_or_default(),
+            avatar: v.avatar.clone(),
+            avatar_thumb: None,
+        }))
+    }
+
+    async fn get_admin_info_by_id(&self, id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<Option<UserAdminInfo>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.users.iter().find(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id)).map(admin_info))
+    }
+
+    async fn search(&self, filter: &UserFilter, offset: i64, limit: i64, _log: &slog::Logger) -> Result<Vec<UserAdminInfo>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+        let mut users: Vec<&User> = tables.users.iter().filter(|v| match_user(v, filter)).collect();
+
+        let desc = filter.order.as_deref() != Some("asc");
+        users.sort_by(|a, b| {
+            let ord = match filter.sort.as_deref() {
+                Some("username") => compare(&a.username, &b.username, desc),
+                Some("create_time") => compare(&a.create_time, &b.create_time, desc),
+                Some("last_login_time") => compare(&a.last_login_time, &b.last_login_time, desc),
+                _ => Ordering::Equal,
+            };
+            ord.then_with(|| compare(&a.id, &b.id, desc))
+        });
+
+        Ok(page(users.into_iter().map(admin_info), offset, limit))
+    }
+
+    async fn count(&self, filter: &UserFilter, _log: &slog::Logger) -> Result<i64, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.users.iter().filter(|v| match_user(v, filter)).count() as i64)
+    }
+
+    async fn schedule_anonymize(&self, id: i32, anonymize_time: DateTime<Utc>, tenant_id: i32, _log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        let mut found = false;
+        for v in tables.users.iter_mut().filter(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id)) {
+            v.update_time = Some(Utc::now());
+            found = true;
+        }
+        if found {
+            tables.anonymize_time.insert(id, anonymize_time);
+        }
+
+        Ok(())
+    }
+
+    async fn get_to_anonymize(&self, now: DateTime<Utc>, _log: &slog::Logger) -> Result<Vec<User>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.users.iter()
+            .filter(|v| v.is_del == Some(1) && v.id.and_then(|id| tables.anonymize_time.get(&id)).is_some_and(|t| *t <= now))
+            .cloned()
+            .collect())
+    }
+
+    async fn anonymize(&self, id: i32, _log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        for v in tables.users.iter_mut().filter(|v| v.id == Some(id)) {
+            v.username = Some(format!("deleted_{}", id));
+            v.password = None;
+            v.salt = None;
+            v.mobile = None;
+            v.name = None;
+            v.avatar = None;
+            v.last_login_ip = None;
+            v.update_time = Some(Utc::now());
+        }
+        tables.anonymize_time.remove(&id);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TenantRepository for MemoryRepository {
+    async fn get_by_code(&self, code: &str, _log: &slog::Logger) -> Result<Option<Tenant>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.tenants.iter().find(|v| v.code == code).cloned())
+    }
+}
+
+#[async_trait]
+impl AuthorizationRepository for MemoryRepository {
+    async fn insert(&self, authorization: &Authorization, log: &slog::Logger) -> Result<Authorization, error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        if tables.authorizations.iter().any(|v| v.uuid == authorization.uuid) {
+            error!(log, "duplicate authorization uuid {:?}", authorization.uuid);
+            return Err(error::err500());
+        }
+
+        // 事务回滚会删除行，id不能使用记录数
+        let id = tables.authorizations.iter().filter_map(|v| v.id).max().unwrap_or_default() + 1;
+        let row = Authorization {
+            id: Some(id),
+            update_time: None,
+            last_refresh_time: None,
+            ..authorization.clone()
+        };
+        tables.authorizations.push(row.clone());
+
+        Ok(row)
+    }
+
+    async fn disable(&self, id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        for v in tables.authorizations.iter_mut().filter(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id)) {
+            v.is_enabled = Some(0);
+            v.update_time = Some(Utc::now());
+        }
+
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.authorizations.iter().find(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id)).cloned())
+    }
+
+    async fn get_by_uuid(&self, uuid: uuid::Uuid, tenant_id: i32, _log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.authorizations.iter().find(|v| v.uuid == Some(uuid) && v.tenant_id == Some(tenant_id)).cloned())
+    }
+
+    async fn update(&self, authorization: &Authorization, old_refresh_token: uuid::Uuid, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+        let id = authorization.id.unwrap_or_default();
+        if id <= 0 {
+            error!(log, "update id error: {}", id);
+            return Err(error::err500());
+        }
+
+        let mut tables = self.tables.lock().unwrap();
+        // 与sql的 UPDATE ... WHERE 一致，没有匹配的行时返回 None
+        let row = match tables.authorizations.iter_mut().find(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id) && v.refresh_token == Some(old_refresh_token)) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        row.update_time = Some(Utc::now());
+        if authorization.refresh_token.is_some() {
+            row.refresh_token = authorization.refresh_token;
+        }
+        if authorization.last_refresh_time.is_some() {
+            row.last_refresh_time = authorization.last_refresh_time;
+        }
+        if authorization.access_token_id.is_some() {
+            row.access_token_id = authorization.access_token_id;
+        }
+        if authorization.access_token_exp.is_some() {
+            row.access_token_exp = authorization.access_token_exp;
+        }
+        if authorization.access_token_iat.is_some() {
+            row.access_token_iat = authorization.access_token_iat;
+        }
+
+        Ok(Some(row.clone()))
+    }
+
+    async fn get_by_user_id(&self, user_id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<Vec<Authorization>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.authorizations.iter().filter(|v| v.user_id == Some(user_id) && v.tenant_id == Some(tenant_id)).cloned().collect())
+    }
+
+    async fn disable_by_user_id(&self, user_id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        for v in tables.authorizations.iter_mut().filter(|v| v.user_id == Some(user_id) && v.tenant_id == Some(tenant_id) && v.is_enabled == Some(1)) {
+            v.is_enabled = Some(0);
+            v.update_time = Some(Utc::now());
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AuthorizationLogRepository for MemoryRepository {
+    async fn insert(&self, log_type: i16, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, log_time: DateTime<Utc>, _log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        insert_log(&mut tables, log_type, msg, user_id, auth_id, tenant_id, client, log_time);
+
+        Ok(())
+    }
+
+    async fn get_by_user_id(&self, user_id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.logs.iter().filter(|v| v.user_id == Some(user_id) && v.tenant_id == tenant_id).cloned().collect())
+    }
+
+    async fn anonymize(&self, user_id: i32, tenant_id: i32, _log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        for v in tables.logs.iter_mut().filter(|v| v.user_id == Some(user_id) && v.tenant_id == tenant_id) {
+            v.ip = None;
+            v.user_agent = None;
+            v.log = None;
+        }
+
+        Ok(())
+    }
+
+    async fn search(&self, filter: &LogFilter, offset: i64, limit: i64, _log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(page(tables.logs.iter().rev().filter(|v| match_log(v, filter)).cloned(), offset, limit))
+    }
+
+    async fn count(&self, filter: &LogFilter, _log: &slog::Logger) -> Result<i64, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(tables.logs.iter().filter(|v| match_log(v, filter)).count() as i64)
+    }
+
+    async fn get_after(&self, filter: &LogFilter, after_id: i32, limit: i64, _log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+        let tables = self.tables.lock().unwrap();
+
+        Ok(page(tables.logs.iter().filter(|v| v.id > after_id && match_log(v, filter)).cloned(), 0, limit))
+    }
+}
+
+#[async_trait]
+impl BlacklistRepository for MemoryRepository {
+    async fn insert(&self, auth_black_list: &AuthBlacklist, log: &slog::Logger) -> Result<(), error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        if tables.black_list.iter().any(|v| v.access_token_id == auth_black_list.access_token_id) {
+            error!(log, "duplicate blacklist access_token_id {}", auth_black_list.access_token_id);
+            return Err(error::err500());
+        }
+
+        // 过期记录会被删除，id不能使用记录数
+        let id = tables.black_list.iter().filter_map(|v| v.id).max().unwrap_or_default() + 1;
+        tables.black_list.push(AuthBlacklist {
+            id: Some(id),
+            ..auth_black_list.clone()
+        });
+
+        Ok(())
+    }
+
+    async fn contains(&self, access_token_id: &str, _log: &slog::Logger) -> Result<bool, error::Error> {
+        let tables = self.tables.lock().unwrap();
+        let now = Utc::now();
+
+        Ok(tables.black_list.iter().any(|v| v.access_token_id.to_string() == access_token_id && v.access_token_exp > now))
+    }
+
+    async fn purge(&self, now: DateTime<Utc>, _log: &slog::Logger) -> Result<u64, error::Error> {
+        let mut tables = self.tables.lock().unwrap();
+        let len = tables.black_list.len();
+        tables.black_list.retain(|v| v.access_token_exp >= now);
+
+        Ok((len - tables.black_list.len()) as u64)
+    }
+}
+#[async_trait]
+impl TransactionRepository for MemoryRepository {
+    async fn begin(&self, _log: &slog::Logger) -> Result<Box<dyn Transaction>, error::Error> {
+        Ok(Box::new(MemoryTransaction {
+            repo: self.clone(),
+            undo: Vec::new(),
+            after_commit: Vec::new(),
+        }))
+    }
+}
+
+// 事务中写入前的数据，回滚时按相反顺序恢复
+enum Undo {
+    InsertAuth(i32),
+    UpdateAuth(Authorization),
+    UpdateLastLogin(i32, Option<DateTime<Utc>>, Option<String>),
+    InsertLog(i32),
+    InsertBlackList(uuid::Uuid),
+    // 用户的删除标记和匿名化时间
+    DeleteUser(i32, Option<i16>, Option<DateTime<Utc>>),
+}
+
+// 写入立即生效，未提交时在drop中撤销，没有隔离，其他请求可以读到未提交的写入
+pub struct MemoryTransaction {
+    repo: MemoryRepository,
+    undo: Vec<Undo>,
+    after_commit: Vec<AfterCommit>,
+}
+
+impl MemoryTransaction {
+    // 记录用户当前的删除标记和匿名化时间，回滚时恢复
+    fn save_user(&mut self, id: i32, tenant_id: i32) {
+        let tables = self.repo.tables.lock().unwrap();
+        if let Some(v) = tables.users.iter().find(|v| v.id == Some(id) && v.tenant_id == Some(tenant_id)) {
+            let undo = Undo::DeleteUser(id, v.is_del, tables.anonymize_time.get(&id).cloned());
+            drop(tables);
+            self.undo.push(undo);
+        }
+    }
+}
+
+#[async_trait]
+impl Transaction for MemoryTransaction {
+    async fn insert_auth(&mut self, authorization: &Authorization, log: &slog::Logger) -> Result<Authorization, error::Error> {
+        let result = AuthorizationRepository::insert(&self.repo, authorization, log).await?;
+        self.undo.push(Undo::InsertAuth(result.id.unwrap_or_default()));
+
+        Ok(result)
+    }
+
+    async fn update_auth(&mut self, authorization: &Authorization, old_refresh_token: uuid::Uuid, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+        let old = self.repo.tables.lock().unwrap().authorizations.iter().find(|v| v.id == authorization.id).cloned();
+        let result = AuthorizationRepository::update(&self.repo, authorization, old_refresh_token, tenant_id, log).await?;
+        if let (Some(v), Some(_)) = (old, &result) {
+            self.undo.push(Undo::UpdateAuth(v));
+        }
+
+        Ok(result)
+    }
+
+    async fn disable_auth(&mut self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        let old = self.repo.tables.lock().unwrap().authorizations.iter().find(|v| v.id == Some(id)).cloned();
+        AuthorizationRepository::disable(&self.repo, id, tenant_id, log).await?;
+        if let Some(v) = old {
+            self.undo.push(Undo::UpdateAuth(v));
+        }
+
+        Ok(())
+    }
+
+    async fn disable_auth_by_user_id(&mut self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        let old: Vec<Authorization> = self.repo.tables.lock().unwrap().authorizations.iter()
+            .filter(|v| v.user_id == Some(user_id) && v.tenant_id == Some(tenant_id) && v.is_enabled == Some(1))
+            .cloned()
+            .collect();
+        AuthorizationRepository::disable_by_user_id(&self.repo, user_id, tenant_id, log).await?;
+        self.undo.extend(old.into_iter().map(Undo::UpdateAuth));
+
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        self.save_user(id, tenant_id);
+
+        UserRepository::delete(&self.repo, id, tenant_id, log).await
+    }
+
+    async fn schedule_anonymize(&mut self, id: i32, anonymize_time: DateTime<Utc>, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        self.save_user(id, tenant_id);
+
+        UserRepository::schedule_anonymize(&self.repo, id, anonymize_time, tenant_id, log).await
+    }
+
+    async fn update_last_login(&mut self, login_time: DateTime<Utc>, ip: &str, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        let old = self.repo.tables.lock().unwrap().users.iter()
+            .find(|v| v.id == Some(user_id) && v.tenant_id == Some(tenant_id))
+            .map(|v| (v.last_login_time, v.last_login_ip.clone()));
+        UserRepository::update_last_login(&self.repo, login_time, ip, user_id, tenant_id, log).await?;
+        if let Some((time, ip)) = old {
+            self.undo.push(Undo::UpdateLastLogin(user_id, time, ip));
+        }
+
+        Ok(())
+    }
+
+    async fn insert_log(&mut self, log_type: i16, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, log_time: DateTime<Utc>, _log: &slog::Logger) -> Result<(), error::Error> {
+        let id = insert_log(&mut self.repo.tables.lock().unwrap(), log_type, msg, user_id, auth_id, tenant_id, client, log_time);
+        self.undo.push(Undo::InsertLog(id));
+
+        Ok(())
+    }
+
+    async fn insert_black_list(&mut self, auth_black_list: &AuthBlacklist, log: &slog::Logger) -> Result<(), error::Error> {
+        BlacklistRepository::insert(&self.repo, auth_black_list, log).await?;
+        self.undo.push(Undo::InsertBlackList(auth_black_list.access_token_id));
+
+        Ok(())
+    }
+
+    fn after_commit(&mut self, f: AfterCommit) {
+        self.after_commit.push(f);
+    }
+
+    async fn commit(mut self: Box<Self>, _log: &slog::Logger) -> Result<(), error::Error> {
+        self.undo.clear();
+        for f in self.after_commit.drain(..) {
+            f();
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for MemoryTransaction {
+    fn drop(&mut self) {
+        if self.undo.is_empty() {
+            return;
+        }
+
+        let mut tables = self.repo.tables.lock().unwrap();
+        for v in self.undo.drain(..).rev() {
+            match v {
+                Undo::InsertAuth(id) => tables.authorizations.retain(|v| v.id != Some(id)),
+                Undo::UpdateAuth(old) => {
+                    if let Some(row) = tables.authorizations.iter_mut().find(|v| v.id == old.id) {
+                        *row = old;
+                    }
+                },
+                Undo::UpdateLastLogin(user_id, time, ip) => {
+                    if let Some(row) = tables.users.iter_mut().find(|v| v.id == Some(user_id)) {
+                        row.last_login_time = time;
+                        row.last_login_ip = ip;
+                    }
+                },
+                Undo::InsertLog(id) => tables.logs.retain(|v| v.id != id),
+                Undo::InsertBlackList(access_token_id) => tables.black_list.retain(|v| v.access_token_id != access_token_id),
+                Undo::DeleteUser(id, is_del, anonymize_time) => {
+                    if let Some(row) = tables.users.iter_mut().find(|v| v.id == Some(id)) {
+                        row.is_del = is_del;
+                    }
+                    match anonymize_time {
+                        Some(v) => tables.anonymize_time.insert(id, v),
+                        None => tables.anonymize_time.remove(&id),
+                    };
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+pub mod sql;
+pub mod memory;
+
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use async_trait::async_trait;
+use crate::lib::{cache::Cache, client::ClientInfo, db, error, metrics::Metrics};
+use crate::lib::settings::Settings;
+use crate::api::user::{User, UserInfo, UserAdminInfo, UserFilter};
+use crate::api::tenant::Tenant;
+use crate::api::authorizations::{AuthBlacklist, Authorization, AuthorizationLog, LogFilter};
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn get_by_id(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Option<User>, error::Error>;
+    async fn get_by_username(&self, username: &str, tenant_id: i32, log: &slog::Logger) -> Result<Option<User>, error::Error>;
+    async fn get_by_mobile(&self, mobile: &str, tenant_id: i32, log: &slog::Logger) -> Result<Option<User>, error::Error>;
+    async fn insert(&self, user: &User, log: &slog::Logger) -> Result<User, error::Error>;
+    // 批量插入，全部成功或全部失败
+    async fn insert_many(&self, users: &[User], log: &slog::Logger) -> Result<Vec<User>, error::Error>;
+    async fn update(&self, user: &User, tenant_id: i32, log: &slog::Logger) -> Result<User, error::Error>;
+    async fn update_last_login(&self, login_time: DateTime<Utc>, ip: &str, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn delete(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    // 已匿名化的用户不能恢复，返回是否恢复成功
+    async fn restore(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<bool, error::Error>;
+    async fn get_user_info_by_id(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Option<UserInfo>, error::Error>;
+    async fn get_admin_info_by_id(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Option<UserAdminInfo>, error::Error>;
+    async fn search(&self, filter: &UserFilter, offset: i64, limit: i64, log: &slog::Logger) -> Result<Vec<UserAdminInfo>, error::Error>;
+    async fn count(&self, filter: &UserFilter, log: &slog::Logger) -> Result<i64, error::Error>;
+    async fn schedule_anonymize(&self, id: i32, anonymize_time: DateTime<Utc>, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn get_to_anonymize(&self, now: DateTime<Utc>, log: &slog::Logger) -> Result<Vec<User>, error::Error>;
+    async fn anonymize(&self, id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+}
+
+#[async_trait]
+pub trait TenantRepository: Send + Sync {
+    async fn get_by_code(&self, code: &str, log: &slog::Logger) -> Result<Option<Tenant>, error::Error>;
+}
+
+#[async_trait]
+pub trait AuthorizationRepository: Send + Sync {
+    async fn insert(&self, authorization: &Authorization, log: &slog::Logger) -> Result<Authorization, error::Error>;
+    async fn disable(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    // 不检查用户状态，由调用方检查并记录对应的日志类型
+    async fn get_by_id(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error>;
+    async fn get_by_uuid(&self, uuid: uuid::Uuid, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error>;
+    // refresh_token 已经不是 old_refresh_token 时返回 None
+    async fn update(&self, authorization: &Authorization, old_refresh_token: uuid::Uuid, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error>;
+    async fn get_by_user_id(&self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Vec<Authorization>, error::Error>;
+    async fn disable_by_user_id(&self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+}
+
+#[async_trait]
+pub trait AuthorizationLogRepository: Send + Sync {
+    async fn insert(&self, log_type: i16, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, log_time: DateTime<Utc>, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn get_by_user_id(&self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error>;
+    async fn anonymize(&self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    // 按id倒序分页
+    async fn search(&self, filter: &LogFilter, offset: i64, limit: i64, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error>;
+    async fn count(&self, filter: &LogFilter, log: &slog::Logger) -> Result<i64, error::Error>;
+    // 按id正序获取after_id之后的日志
+    async fn get_after(&self, filter: &LogFilter, after_id: i32, limit: i64, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error>;
+}
+
+#[async_trait]
+pub trait BlacklistRepository: Send + Sync {
+    async fn insert(&self, auth_black_list: &AuthBlacklist, log: &slog::Logger) -> Result<(), error::Error>;
+    // access token是否在黑名单中且未过期
+    async fn contains(&self, access_token_id: &str, log: &slog::Logger) -> Result<bool, error::Error>;
+    // 删除已过期的记录，返回删除数量
+    async fn purge(&self, now: DateTime<Utc>, log: &slog::Logger) -> Result<u64, error::Error>;
+}
+
+pub type AfterCommit = Box<dyn FnOnce() + Send>;
+
+// 需要一起提交的写入，commit 之前出错返回时 drop 自动回滚
+#[async_trait]
+pub trait Transaction: Send {
+    async fn insert_auth(&mut self, authorization: &Authorization, log: &slog::Logger) -> Result<Authorization, error::Error>;
+    async fn update_auth(&mut self, authorization: &Authorization, old_refresh_token: uuid::Uuid, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error>;
+    async fn disable_auth(&mut self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn disable_auth_by_user_id(&mut self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn delete_user(&mut self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn schedule_anonymize(&mut self, id: i32, anonymize_time: DateTime<Utc>, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn update_last_login(&mut self, login_time: DateTime<Utc>, ip: &str, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn insert_log(&mut self, log_type: i16, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, log_time: DateTime<Utc>, log: &slog::Logger) -> Result<(), error::Error>;
+    async fn insert_black_list(&mut self, auth_black_list: &AuthBlacklist, log: &slog::Logger) -> Result<(), error::Error>;
+    // 提交成功后执行，回滚时不执行
+    fn after_commit(&mut self, f: AfterCommit);
+    async fn commit(self: Box<Self>, log: &slog::Logger) -> Result<(), error::Error>;
+}
+
+#[async_trait]
+pub trait TransactionRepository: Send + Sync {
+    async fn begin(&self, log: &slog::Logger) -> Result<Box<dyn Transaction>, error::Error>;
+}
+
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub tenants: Arc<dyn TenantRepository>,
+    pub authorizations: Arc<dyn AuthorizationRepository>,
+    pub logs: Arc<dyn AuthorizationLogRepository>,
+    pub black_list: Arc<dyn BlacklistRepository>,
+    pub transactions: Arc<dyn TransactionRepository>,
+}
+
+impl Repositories {
+    fn from<T>(repository: Arc<T>) -> Self
+    where
+        T: UserRepository + TenantRepository + AuthorizationRepository + AuthorizationLogRepository + BlacklistRepository + TransactionRepository + 'static,
+    {
+        Self {
+            users: repository.clone(),
+            tenants: repository.clone(),
+            authorizations: repository.clone(),
+            logs: repository.clone(),
+            black_list: repository.clone(),
+            transactions: repository,
+        }
+    }
+}
+
+// replicas 为只读副本的连接池，为空时读写都使用主库
+pub fn conn(settings: &Settings, db: &db::Pool, replicas: Vec<db::Pool>, redis: &Cache, metrics: &Arc<Metrics>) -> Repositories {
+    match &settings.repository.backend[..] {
+        "memory" => Repositories::from(Arc::new(memory::MemoryRepository::new(&settings.tenant.default))),
+        _ => {
+            let replicas = db::replica::Replicas::new(db.clone(), replicas, settings.db().read_your_writes_ms);
+            let tenant_cache = std::time::Duration::from_secs(settings.tenant.cache_seconds);
+            Repositories::from(Arc::new(sql::SqlRepository::new(db.clone(), replicas, redis.clone(), metrics.clone(), tenant_cache)))
+        }
+    }
+}
\ No newline at end of file
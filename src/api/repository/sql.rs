@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use async_trait::async_trait;
+use crate::lib::{self, cache::Cache, client::ClientInfo, db::{self, replica::Replicas}, error, metrics::Metrics};
+use crate::api::user::{self, User, UserInfo, UserAdminInfo, UserFilter};
+use crate::api::tenant::{self, Tenant};
+use crate::api::authorizations::{self, AuthBlacklist, Authorization, AuthorizationLog, LogFilter};
+use super::{UserRepository, TenantRepository, AuthorizationRepository, AuthorizationLogRepository, BlacklistRepository, Transaction, TransactionRepository, AfterCommit};
+
+// 数据保存在编译时启用的数据库(postgres或mysql)中，token黑名单同时写入redis用于快速检查
+// 按id读取用户信息的查询使用只读副本，安全检查、其他查询和写入使用主库
+pub struct SqlRepository {
+    db: db::Pool,
+    replicas: Arc<Replicas>,
+    redis: Cache,
+    metrics: Arc<Metrics>,
+    tenant_cache: Duration,
+    // 按代码缓存查询到的租户和查询时间，查不到的代码不缓存
+    tenants: Mutex<HashMap<String, (Tenant, Instant)>>,
+}
+
+impl SqlRepository {
+    pub fn new(db: db::Pool, replicas: Replicas, redis: Cache, metrics: Arc<Metrics>, tenant_cache: Duration) -> Self {
+        Self { db, replicas: Arc::new(replicas), redis, metrics, tenant_cache, tenants: Mutex::new(HashMap::new()) }
+    }
+
+    async fn acquire(&self, log: &slog::Logger) -> Result<sqlx::pool::PoolConnection<db::Db>, error::Error> {
+        match db::acquire(&self.db, &self.metrics).await {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                error!(log, "{}", e);
+                Err(error::err500())
+            }
+        }
+    }
+}
+
+// 黑名单写入redis用于快速检查，记录在token过期时自动删除
+async fn cache_black_list(auth_black_list: &AuthBlacklist, redis: &Cache, log: &slog::Logger) -> Result<(), error::Error> {
+    let diff = auth_black_list
+        .access_token_exp
+        .signed_duration_since(Utc::now())
+        .num_seconds();
+    if diff <= 0 {
+        return Ok(());
+    }
+
+    lib::redis::set_with_expire(
+        format!("auth_black_list_{}", auth_black_list.access_token_id),
+        auth_black_list.user_id,
+        diff,
+        redis,
+        log
+    ).await
+}
+
+#[async_trait]
+impl UserRepository for SqlRepository {
+    async fn get_by_id(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Option<User>, error::Error> {
+        // 刷新授权、修改密码等安全检查使用的数据读取主库，不受复制延迟影响
+        user::model::get_by_id(id, tenant_id, &self.db, log).await
+    }
+
+    async fn get_by_username(&self, username: &str, tenant_id: i32, log: &slog::Logger) -> Result<Option<User>, error::Error> {
+        user::model::get_by_username(username, tenant_id, &self.db, log).await
+    }
+
+    async fn get_by_mobile(&self, mobile: &str, tenant_id: i32, log: &slog::Logger) -> Result<Option<User>, error::Error> {
+        user::model::get_by_mobile(mobile, tenant_id, &self.db, log).await
+    }
+
+    async fn insert(&self, user: &User, log: &slog::Logger) -> Result<User, error::Error> {
+        let mut conn = self.acquire(log).await?;
+        let result = user::model::insert(user, &mut conn, log).await?;
+        if let Some(id) = result.id {
+            self.replicas.written("users", id);
+        }
+
+        Ok(result)
+    }
+
+    async fn insert_many(&self, users: &[User], log: &slog::Logger) -> Result<Vec<User>, error::Error> {
+        let mut tx = match db::begin(&self.db, &self.metrics).await {
+            Ok(v) => v,
+            Err(e) => {
+                error!(log, "{}", e);
+                return Err(error::err500());
+            }
+        };
+
+        let mut result = Vec::with_capacity(users.len());
+        for user in users {
+            result.push(user::model::insert(user, &mut tx, log).await?);
+        }
+
+        if let Err(e) = tx.commit().await {
+            error!(log, "{}", e);
+            return Err(error::err500());
+        }
+        for id in result.iter().filter_map(|v| v.id) {
+            self.replicas.written("users", id);
+        }
+
+        Ok(result)
+    }
+
+    async fn update(&self, user: &User, tenant_id: i32, log: &slog::Logger) -> Result<User, error::Error> {
+        let result = user::model::update(user, tenant_id, &self.db, log).await?;
+        if let Some(id) = result.id {
+            self.replicas.written("users", id);
+        }
+
+        Ok(result)
+    }
+
+    async fn update_last_login(&self, login_time: DateTime<Utc>, ip: &str, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        user::model::update_last_login(login_time, ip, user_id, tenant_id, &self.db, log).await?;
+        self.replicas.written("users", user_id);
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        user::model::delete(id, tenant_id, &self.db, log).await?;
+        self.replicas.written("users", id);
+
+        Ok(())
+    }
+
+    async fn restore(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<bool, error::Error> {
+        let result = user::model::restore(id, tenant_id, &self.db, log).await?;
+        self.replicas.written("users", id);
+
+        Ok(result)
+    }
+
+    async fn get_user_info_by_id(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Option<UserInfo>, error::Error> {
+        user::model::get_user_info_by_id(id, tenant_id, self.replicas.read(&[("users", id)]), log).await
+    }
+
+    async fn get_admin_info_by_id(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Option<UserAdminInfo>, error::Error> {
+        user::model::get_admin_info_by_id(id, tenant_id, &self.db, log).await
+    }
+
+    async fn search(&self, filter: &UserFilter, offset: i64, limit: i64, log: &slog::Logger) -> Result<Vec<UserAdminInfo>, error::Error> {
+        user::model::search(filter, offset, limit, &self.db, log).await
+    }
+
+    async fn count(&self, filter: &UserFilter, log: &slog::Logger) -> Result<i64, error::Error> {
+        user::model::count(filter, &self.db, log).await
+    }
+
+    async fn schedule_anonymize(&self, id: i32, anonymize_time: DateTime<Utc>, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        user::model::schedule_anonymize(id, anonymize_time, tenant_id, &self.db, log).await?;
+        self.replicas.written("users", id);
+
+        Ok(())
+    }
+
+    async fn get_to_anonymize(&self, now: DateTime<Utc>, log: &slog::Logger) -> Result<Vec<User>, error::Error> {
+        user::model::get_to_anonymize(now, &self.db, log).await
+    }
+
+    async fn anonymize(&self, id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        user::model::anonymize(id, &self.db, log).await?;
+        self.replicas.written("users", id);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TenantRepository for SqlRepository {
+    // 每个需要登录的请求都会解析租户，使用短时间的进程内缓存
+    async fn get_by_code(&self, code: &str, log: &slog::Logger) -> Result<Option<Tenant>, error::Error> {
+        if let Some((v, t)) = self.tenants.lock().unwrap().get(code) {
+            if t.elapsed() < self.tenant_cache {
+                return Ok(Some(v.clone()));
+            }
+        }
+
+        let result = tenant::model::get_by_code(code, &self.db, log).await?;
+        if let Some(v) = &result {
+            if !self.tenant_cache.is_zero() {
+                let mut tenants = self.tenants.lock().unwrap();
+                tenants.retain(|_, (_, t)| t.elapsed() < self.tenant_cache);
+                tenants.insert(code.to_string(), (v.clone(), Instant::now()));
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl AuthorizationRepository for SqlRepository {
+    async fn insert(&self, authorization: &Authorization, log: &slog::Logger) -> Result<Authorization, error::Error> {
+        let mut conn = self.acquire(log).await?;
+        authorizations::model::insert_auth(authorization, &mut conn, log).await
+    }
+
+    async fn disable(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        authorizations::model::disable_auth(id, tenant_id, &self.db, log).await
+    }
+
+    async fn get_by_id(&self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+        // 授权状态用于安全检查，读取主库
+        authorizations::model::get_by_id(id, tenant_id, &self.db, log).await
+    }
+
+    async fn get_by_uuid(&self, uuid: uuid::Uuid, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+        authorizations::model::get_by_uuid(uuid, tenant_id, &self.db, log).await
+    }
+
+    async fn update(&self, authorization: &Authorization, old_refresh_token: uuid::Uuid, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+        let mut conn = self.acquire(log).await?;
+        authorizations::model::update_auth(authorization, old_refresh_token, tenant_id, &mut conn, log).await
+    }
+
+    async fn get_by_user_id(&self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Vec<Authorization>, error::Error> {
+        authorizations::model::get_by_user_id(user_id, tenant_id, &self.db, log).await
+    }
+
+    async fn disable_by_user_id(&self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        authorizations::model::disable_by_user_id(user_id, tenant_id, &self.db, log).await
+    }
+}
+
+#[async_trait]
+impl AuthorizationLogRepository for SqlRepository {
+    async fn insert(&self, log_type: i16, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, log_time: DateTime<Utc>, log: &slog::Logger) -> Result<(), error::Error> {
+        authorizations::model::insert_log(log_type, msg, user_id, auth_id, tenant_id, client, log_time, &self.db, log).await
+    }
+
+    async fn get_by_user_id(&self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+        authorizations::model::get_logs_by_user_id(user_id, tenant_id, &self.db, log).await
+    }
+
+    async fn anonymize(&self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        authorizations::model::anonymize_logs(user_id, tenant_id, &self.db, log).await
+    }
+
+    async fn search(&self, filter: &LogFilter, offset: i64, limit: i64, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+        authorizations::model::search_logs(filter, offset, limit, &self.db, log).await
+    }
+
+    async fn count(&self, filter: &LogFilter, log: &slog::Logger) -> Result<i64, error::Error> {
+        authorizations::model::count_logs(filter, &self.db, log).await
+    }
+
+    async fn get_after(&self, filter: &LogFilter, after_id: i32, limit: i64, log: &slog::Logger) -> Result<Vec<AuthorizationLog>, error::Error> {
+        authorizations::model::get_logs_after(filter, after_id, limit, &self.db, log).await
+    }
+}
+
+#[async_trait]
+impl BlacklistRepository for SqlRepository {
+    // 同时写入数据库和redis
+    async fn insert(&self, auth_black_list: &AuthBlacklist, log: &slog::Logger) -> Result<(), error::Error> {
+        let (r1, r2) = futures::join!(
+            authorizations::model::insert_auth_black_list(auth_black_list, &self.db, log),
+            cache_black_list(auth_black_list, &self.redis, log),
+        );
+        r1?;
+        r2?;
+
+        Ok(())
+    }
+
+    async fn contains(&self, access_token_id: &str, log: &slog::Logger) -> Result<bool, error::Error> {
+        lib::redis::has_key(format!("auth_black_list_{}", access_token_id), &self.redis, log).await
+    }
+
+    async fn purge(&self, now: DateTime<Utc>, log: &slog::Logger) -> Result<u64, error::Error> {
+        authorizations::model::purge_auth_black_list(now, &self.db, log).await
+    }
+}
+#[async_trait]
+impl TransactionRepository for SqlRepository {
+    async fn begin(&self, log: &slog::Logger) -> Result<Box<dyn Transaction>, error::Error> {
+        let tx = match db::begin(&self.db, &self.metrics).await {
+            Ok(v) => v,
+            Err(e) => {
+                error!(log, "{}", e);
+                return Err(error::err500());
+            }
+        };
+
+        Ok(Box::new(SqlTransaction {
+            tx,
+            replicas: self.replicas.clone(),
+            redis: self.redis.clone(),
+            written: Vec::new(),
+            black_list: Vec::new(),
+            after_commit: Vec::new(),
+        }))
+    }
+}
+
+// 数据库事务，redis 不参与回滚，token黑名单在提交前写入redis，只读副本的写入记录在提交后更新
+pub struct SqlTransaction {
+    tx: db::Transaction,
+    replicas: Arc<Replicas>,
+    redis: Cache,
+    written: Vec<(&'static str, i32)>,
+    black_list: Vec<AuthBlacklist>,
+    after_commit: Vec<AfterCommit>,
+}
+
+#[async_trait]
+impl Transaction for SqlTransaction {
+    async fn insert_auth(&mut self, authorization: &Authorization, log: &slog::Logger) -> Result<Authorization, error::Error> {
+        authorizations::model::insert_auth(authorization, &mut self.tx, log).await
+    }
+
+    async fn update_auth(&mut self, authorization: &Authorization, old_refresh_token: uuid::Uuid, tenant_id: i32, log: &slog::Logger) -> Result<Option<Authorization>, error::Error> {
+        authorizations::model::update_auth(authorization, old_refresh_token, tenant_id, &mut self.tx, log).await
+    }
+
+    async fn disable_auth(&mut self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        authorizations::model::disable_auth(id, tenant_id, &mut *self.tx, log).await
+    }
+
+    async fn disable_auth_by_user_id(&mut self, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        authorizations::model::disable_by_user_id(user_id, tenant_id, &mut *self.tx, log).await
+    }
+
+    async fn delete_user(&mut self, id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        user::model::delete(id, tenant_id, &mut *self.tx, log).await?;
+        self.written.push(("users", id));
+
+        Ok(())
+    }
+
+    async fn schedule_anonymize(&mut self, id: i32, anonymize_time: DateTime<Utc>, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        user::model::schedule_anonymize(id, anonymize_time, tenant_id, &mut *self.tx, log).await?;
+        self.written.push(("users", id));
+
+        Ok(())
+    }
+
+    async fn update_last_login(&mut self, login_time: DateTime<Utc>, ip: &str, user_id: i32, tenant_id: i32, log: &slog::Logger) -> Result<(), error::Error> {
+        user::model::update_last_login(login_time, ip, user_id, tenant_id, &mut *self.tx, log).await?;
+        self.written.push(("users", user_id));
+
+        Ok(())
+    }
+
+    async fn insert_log(&mut self, log_type: i16, msg: &str, user_id: i32, auth_id: i32, tenant_id: i32, client: &ClientInfo, log_time: DateTime<Utc>, log: &slog::Logger) -> Result<(), error::Error> {
+        authorizations::model::insert_log(log_type, msg, user_id, auth_id, tenant_id, client, log_time, &mut *self.tx, log).await
+    }
+
+    async fn insert_black_list(&mut self, auth_black_list: &AuthBlacklist, log: &slog::Logger) -> Result<(), error::Error> {
+        authorizations::model::insert_auth_black_list(auth_black_list, &mut *self.tx, log).await?;
+        self.black_list.push(auth_black_list.clone());
+
+        Ok(())
+    }
+
+    fn after_commit(&mut self, f: AfterCommit) {
+        self.after_commit.push(f);
+    }
+
+    async fn commit(self: Box<Self>, log: &slog::Logger) -> Result<(), error::Error> {
+        let SqlTransaction { tx, replicas, redis, written, black_list, after_commit } = *self;
+
+        // 黑名单只从redis检查，先写入redis，避免提交后写入失败时token仍然可用
+        // 写入redis后提交失败只会多出一条会过期的记录
+        for v in &black_list {
+            cache_black_list(v, &redis, log).await?;
+        }
+
+        if let Err(e) = tx.commit().await {
+            error!(log, "{}", e);
+            return Err(error::err500());
+        }
+
+        for (table, id) in written {
+            replicas.written(table, id);
+        }
+        for f in after_commit {
+            f();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use actix_web::{web, post, HttpResponse, HttpRequest};
+use actix_web::dev::ConnectionInfo;
+use serde::Serialize;
+use crate::AppState;
+use crate::lib::{error, client, auth};
+use crate::api::tenant;
+use crate::api::authorizations::{self, LogType};
+
+#[derive(Serialize)]
+struct ResReloadJson {
+    changes: Vec<String>,
+}
+
+// 重新加载配置，只有默认租户的管理员可以操作，与SIGHUP效果相同
+#[post("/settings/reload")]
+pub async fn reload(req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let tenant = tenant::service::resolve(&req, &state).await?;
+    if tenant.code != state.config.get().tenant.default {
+        return Err(error::new(100404, "No permission", 403));
+    }
+
+    let changes = match state.config.reload(&state.log) {
+        Ok(v) => v,
+        Err(e) => return Err(error::new(400024, &format!("配置重新加载失败: {}", e)[..], 422)),
+    };
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminReloadSettings, &changes.join("; "), auth_info.id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().json(ResReloadJson {changes}))
+}
\ No newline at end of file
@@ -0,0 +1,13 @@
+pub mod model;
+pub mod service;
+
+use chrono::prelude::*;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Tenant {
+    pub id: i32,
+    pub code: String,
+    pub name: Option<String>,
+    pub is_enabled: i16,
+    pub create_time: DateTime<Utc>,
+}
\ No newline at end of file
@@ -0,0 +1,18 @@
+use crate::lib::{db, error, trace::Traced};
+use super::Tenant;
+
+pub async fn get_by_code(code: &str, db: &db::Pool, log: &slog::Logger) -> Result<Option<Tenant>, error::Error> {
+    let r = sqlx::query_as::<_, Tenant>(&db::sql("SELECT * FROM tenants WHERE code=$1"))
+        .bind(code)
+        .fetch_optional(db)
+        .traced("tenant.get_by_code")
+        .await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
\ No newline at end of file
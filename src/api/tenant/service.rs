@@ -0,0 +1,43 @@
+use actix_web::{web, HttpRequest};
+use crate::AppState;
+use crate::lib::error;
+use crate::lib::settings::Settings;
+use super::Tenant;
+
+pub async fn get_by_code(code: &str, state: &web::Data<AppState>) -> Result<Option<Tenant>, error::Error> {
+    let result = state.repo.tenants.get_by_code(code, &state.log).await?;
+
+    Ok(result)
+}
+
+// 从请求中获取租户代码：优先使用请求头，其次是 {code}.{base_domain} 形式的子域名，最后使用默认租户
+pub fn get_code(req: &HttpRequest, config: &Settings) -> String {
+    if let Some(v) = req.headers().get(&config.tenant.header[..]) {
+        let v = v.to_str().unwrap_or_default().trim();
+        if !v.is_empty() {
+            return v.to_string();
+        }
+    }
+
+    if let Some(base_domain) = &config.tenant.base_domain {
+        let host = req.connection_info().host().to_string();
+        let host = host.split(':').next().unwrap_or_default();
+        if let Some(sub) = host.strip_suffix(&format!(".{}", base_domain)[..]) {
+            if !sub.is_empty() && !sub.contains('.') {
+                return sub.to_string();
+            }
+        }
+    }
+
+    config.tenant.default.clone()
+}
+
+// 解析请求所属租户，租户不存在或已禁用时返回错误
+pub async fn resolve(req: &HttpRequest, state: &web::Data<AppState>) -> Result<Tenant, error::Error> {
+    let code = get_code(req, &state.config.get());
+
+    match get_by_code(&code, state).await? {
+        Some(v) if v.is_enabled == 1 => Ok(v),
+        _ => Err(error::new(100405, "租户不存在", 422)),
+    }
+}
\ No newline at end of file
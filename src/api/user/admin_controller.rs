@@ -0,0 +1,371 @@
+use actix_web::{web, get, post, put, delete, HttpResponse, HttpRequest};
+use actix_web::dev::ConnectionInfo;
+use serde::{Serialize, Deserialize};
+use crate::AppState;
+use crate::lib::{error, validator, client, auth};
+use crate::api::user::{service, User, UserAdminInfo, UserFilter};
+use crate::api::authorizations::{self, LogType};
+use chrono::prelude::*;
+use futures::StreamExt;
+
+const USER_TYPES: [i16; 2] = [0, 10];
+
+#[derive(Deserialize)]
+pub struct SearchUsersReqQuery {
+    username: Option<String>,
+    mobile: Option<String>,
+    user_type: Option<i16>,
+    is_enabled: Option<i16>,
+    is_del: Option<i16>,
+    sort: Option<String>,
+    order: Option<String>,
+    page: Option<i64>,
+    page_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ResUserListJson {
+    total: i64,
+    page: i64,
+    page_size: i64,
+    items: Vec<UserAdminInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateUserReqJson {
+    username: Option<String>,
+    password: Option<String>,
+    name: Option<String>,
+    mobile: Option<String>,
+    user_type: Option<i16>,
+    is_enabled: Option<i16>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateUserReqJson {
+    username: Option<String>,
+    name: Option<String>,
+    mobile: Option<String>,
+    user_type: Option<i16>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportUsersReqQuery {
+    dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordReqJson {
+    password: Option<String>,
+}
+
+fn non_empty(v: &Option<String>) -> Option<String> {
+    match v {
+        Some(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        _ => None,
+    }
+}
+
+pub fn check_user_type(user_type: i16) -> Result<(), error::Error> {
+    if !USER_TYPES.contains(&user_type) {
+        return Err(error::new(400002, "用户类型错误", 422));
+    }
+
+    Ok(())
+}
+
+async fn get_target(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<UserAdminInfo, error::Error> {
+    match service::get_admin_info_by_id(id, tenant_id, state).await? {
+        None => Err(error::new(400008, "用户不存在", 422)),
+        Some(v) => Ok(v),
+    }
+}
+
+// 用户列表
+#[get("/users")]
+pub async fn list(query: web::Query<SearchUsersReqQuery>, req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let filter = UserFilter {
+        tenant_id: auth_info.tenant_id,
+        username: non_empty(&query.username),
+        mobile: non_empty(&query.mobile),
+        user_type: query.user_type,
+        is_enabled: query.is_enabled,
+        is_del: query.is_del,
+        sort: query.sort.clone(),
+        order: query.order.clone(),
+    };
+
+    let (items, total) = service::search(&filter, page, page_size, &state).await?;
+
+    Ok(HttpResponse::Ok().json(ResUserListJson {
+        total,
+        page,
+        page_size,
+        items,
+    }))
+}
+
+// 用户详情
+#[get("/users/{id}")]
+pub async fn get_user(path: web::Path<i32>, req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let user_data = get_target(path.into_inner(), auth_info.tenant_id, &state).await?;
+
+    Ok(HttpResponse::Ok().json(user_data))
+}
+
+// 创建用户
+#[post("/users")]
+pub async fn create_user(req_info: web::Json<CreateUserReqJson>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let username = validator::required_str(&req_info.username, "用户名")?;
+    let password = validator::required_str(&req_info.password, "密码")?;
+
+    let mobile = non_empty(&req_info.mobile);
+    if let Some(v) = &mobile {
+        validator::mobile(v, "手机号")?;
+    }
+
+    let name = non_empty(&req_info.name);
+    if let Some(v) = &name {
+        validator::max_len(v, 50, "姓名")?;
+    }
+
+    let user_type = req_info.user_type.unwrap_or(0);
+    check_user_type(user_type)?;
+
+    let is_enabled = req_info.is_enabled.unwrap_or(1);
+    if is_enabled != 0 && is_enabled != 1 {
+        return Err(error::new(400002, "启用状态错误", 422));
+    }
+
+    if service::get_by_username(&username, auth_info.tenant_id, &state).await?.is_some() {
+        return Err(error::new(400009, "用户名已存在", 422));
+    }
+
+    let salt = auth::salt();
+    let pwd = auth::crypt_password(&password, &salt);
+
+    let mut user = User::new();
+    user.username = Some(username);
+    user.password = Some(pwd);
+    user.salt = Some(salt);
+    user.mobile = mobile;
+    user.name = name;
+    user.user_type = Some(user_type);
+    user.is_enabled = Some(is_enabled);
+    user.tenant_id = Some(auth_info.tenant_id);
+
+    let user = service::insert(&user, &state).await?;
+    let user_id = user.id.unwrap_or_default();
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminCreateUser, &format!("operator={}", auth_info.id), user_id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    let user_data = get_target(user_id, auth_info.tenant_id, &state).await?;
+
+    Ok(HttpResponse::Ok().json(user_data))
+}
+
+// 编辑用户
+#[put("/users/{id}")]
+pub async fn update_user(path: web::Path<i32>, req_info: web::Json<UpdateUserReqJson>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let id = path.into_inner();
+    let target = get_target(id, auth_info.tenant_id, &state).await?;
+
+    let mut user = User::new();
+    user.id = Some(id);
+
+    if let Some(username) = non_empty(&req_info.username) {
+        if target.username.as_deref() != Some(&username[..]) {
+            if service::get_by_username(&username, auth_info.tenant_id, &state).await?.is_some() {
+                return Err(error::new(400009, "用户名已存在", 422));
+            }
+            user.username = Some(username);
+        }
+    }
+
+    if let Some(name) = &req_info.name {
+        let name = name.trim();
+        validator::max_len(name, 50, "姓名")?;
+        user.name = Some(name.to_string());
+    }
+
+    // 空字符串清空手机号，保存为NULL
+    if let Some(mobile) = &req_info.mobile {
+        if !mobile.is_empty() {
+            validator::mobile(mobile, "手机号")?;
+        }
+        user.mobile = Some(mobile.to_string());
+    }
+
+    if let Some(user_type) = req_info.user_type {
+        check_user_type(user_type)?;
+        if id == auth_info.id && user_type != target.user_type {
+            return Err(error::new(400010, "不能修改当前登录用户的类型", 422));
+        }
+        user.user_type = Some(user_type);
+    }
+
+    user.update_time = Some(Utc::now());
+    service::update(&user, auth_info.tenant_id, &state).await?;
+    // 取消管理员后已签发的token仍带有 ROLE_ADMIN，需要重新登录
+    if target.user_type == 10 && user.user_type.is_some_and(|v| v != 10) {
+        authorizations::service::revoke_all_by_user(id, auth_info.tenant_id, &state).await?;
+    }
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminUpdateUser, &format!("operator={}", auth_info.id), id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    let user_data = get_target(id, auth_info.tenant_id, &state).await?;
+
+    Ok(HttpResponse::Ok().json(user_data))
+}
+
+// 启用用户
+#[put("/users/{id}/enable")]
+pub async fn enable_user(path: web::Path<i32>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let id = path.into_inner();
+    get_target(id, auth_info.tenant_id, &state).await?;
+
+    let mut user = User::new();
+    user.id = Some(id);
+    user.is_enabled = Some(1);
+    service::update(&user, auth_info.tenant_id, &state).await?;
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminEnableUser, &format!("operator={}", auth_info.id), id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().body(""))
+}
+
+// 禁用用户
+#[put("/users/{id}/disable")]
+pub async fn disable_user(path: web::Path<i32>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let id = path.into_inner();
+    if id == auth_info.id {
+        return Err(error::new(400010, "不能禁用当前登录用户", 422));
+    }
+    get_target(id, auth_info.tenant_id, &state).await?;
+
+    let mut user = User::new();
+    user.id = Some(id);
+    user.is_enabled = Some(0);
+    service::update(&user, auth_info.tenant_id, &state).await?;
+    authorizations::service::revoke_all_by_user(id, auth_info.tenant_id, &state).await?;
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminDisableUser, &format!("operator={}", auth_info.id), id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().body(""))
+}
+
+// 删除用户（软删除）
+#[delete("/users/{id}")]
+pub async fn delete_user(path: web::Path<i32>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let id = path.into_inner();
+    if id == auth_info.id {
+        return Err(error::new(400010, "不能删除当前登录用户", 422));
+    }
+    let target = get_target(id, auth_info.tenant_id, &state).await?;
+    if target.is_del != 0 {
+        return Err(error::new(400008, "用户不存在", 422));
+    }
+
+    service::delete(id, auth_info.tenant_id, &state).await?;
+    authorizations::service::revoke_all_by_user(id, auth_info.tenant_id, &state).await?;
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminDeleteUser, &format!("operator={}", auth_info.id), id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().body(""))
+}
+
+// 恢复已删除用户
+#[put("/users/{id}/restore")]
+pub async fn restore_user(path: web::Path<i32>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let id = path.into_inner();
+    let target = get_target(id, auth_info.tenant_id, &state).await?;
+    if target.is_del == 0 {
+        return Err(error::new(400011, "用户未被删除", 422));
+    }
+
+    if !service::restore(id, auth_info.tenant_id, &state).await? {
+        return Err(error::new(400025, "用户已匿名化，无法恢复", 422));
+    }
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminRestoreUser, &format!("operator={}", auth_info.id), id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().body(""))
+}
+
+// 重置用户密码
+#[put("/users/{id}/password")]
+pub async fn reset_password(path: web::Path<i32>, req_info: web::Json<ResetPasswordReqJson>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let password = validator::required_str(&req_info.password, "密码")?;
+
+    let id = path.into_inner();
+    get_target(id, auth_info.tenant_id, &state).await?;
+
+    let salt = auth::salt();
+    let pwd = auth::crypt_password(&password, &salt);
+
+    let mut user = User::new();
+    user.id = Some(id);
+    user.password = Some(pwd);
+    user.salt = Some(salt);
+    user.update_time = Some(Utc::now());
+    service::update(&user, auth_info.tenant_id, &state).await?;
+    // 重置密码后已登录的设备需要使用新密码重新登录
+    authorizations::service::revoke_all_by_user(id, auth_info.tenant_id, &state).await?;
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::AdminResetPassword, &format!("operator={}", auth_info.id), id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().body(""))
+}
+
+// 从CSV批量导入用户，请求体为CSV内容，dry_run=true时只校验不写入
+#[post("/users/import")]
+pub async fn import_users(query: web::Query<ImportUsersReqQuery>, mut payload: web::Payload, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_ADMIN", &req, &state).await?;
+
+    let max_size = state.config.get().user.import_max_size;
+
+    let mut data = Vec::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(v) => v,
+            Err(_) => return Err(error::new(400018, "CSV格式错误", 422)),
+        };
+        if data.len() + chunk.len() > max_size {
+            return Err(error::new(400017, &format!("文件不能超过{}KB", max_size / 1024)[..], 422));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    let client = client::get_client_info(&state, &req, &conn);
+    let report = service::import_users(&data, query.dry_run.unwrap_or(false), auth_info.tenant_id, auth_info.id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
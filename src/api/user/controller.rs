@@ -1,17 +1,20 @@
-use actix_web::{web, put, get, HttpResponse, HttpRequest};
+use actix_web::{web, put, get, patch, post, delete, HttpResponse, HttpRequest};
 use actix_web::dev::ConnectionInfo;
-use serde::Deserialize;
+use actix_multipart::Multipart;
+use futures::StreamExt;
+use serde::{Serialize, Deserialize};
 use crate::AppState;
 use crate::lib::{error, validator, client, auth};
-use crate::api::user::{service, User};
-use crate::api::authorizations;
+use crate::api::user::{service, User, UserAdminInfo};
+use crate::api::authorizations::{self, LogType};
+use crate::api::preferences;
 use chrono::prelude::*;
 
 #[get("/user")]
 pub async fn get_info(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
     let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
 
-    let user_data = match service::get_user_info_by_id(auth_info.id, &state).await? {
+    let user_data = match service::get_user_info_by_id(auth_info.id, auth_info.tenant_id, &state).await? {
         None => return Err(error::new(400007, "无法获得用户信息", 422)),
         Some(v) => v
     };
@@ -38,7 +41,7 @@ pub async fn change_password(req_info: web::Json<ChangePasswordReqJson>, req: Ht
         return Err(error::new(100301, "新密码和确认密码不一致", 422));
     }
 
-    let user_data = match service::get_by_id(auth_info.id, &state).await? {
+    let user_data = match service::get_by_id(auth_info.id, auth_info.tenant_id, &state).await? {
         None => return Err(error::new(400007, "无法获得用户信息", 422)),
         Some(v) => v
     };
@@ -89,8 +92,222 @@ pub async fn change_password(req_info: web::Json<ChangePasswordReqJson>, req: Ht
 
     let client = client::get_client_info(&state, &req, &conn);
 
-    service::update(&user, &state).await?;
-    authorizations::service::insert_log(5, "", auth_info.id, 0, &client, &state).await?;
+    service::update(&user, auth_info.tenant_id, &state).await?;
+    authorizations::service::insert_log(LogType::ChangePassword, "", auth_info.id, 0, auth_info.tenant_id, &client, &state).await?;
 
     Ok(HttpResponse::Ok().body(""))
 }
+
+
+#[derive(Deserialize)]
+pub struct UpdateProfileReqJson {
+    name: Option<String>,
+    mobile: Option<String>,
+    mobile_code: Option<String>,
+}
+
+#[patch("/user")]
+pub async fn update_profile(req_info: web::Json<UpdateProfileReqJson>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
+
+    let mut user = User::new();
+    user.id = Some(auth_info.id);
+
+    if let Some(name) = &req_info.name {
+        let name = name.trim();
+        validator::max_len(name, 50, "姓名")?;
+        user.name = Some(name.to_string());
+    }
+
+    // 修改手机号需要先通过 POST /user/mobile/code 获取新手机号的验证码
+    if let Some(mobile) = &req_info.mobile {
+        validator::mobile(mobile, "手机号")?;
+        let code = validator::required_str(&req_info.mobile_code, "验证码")?;
+
+        if let Some(v) = service::get_by_mobile(mobile, auth_info.tenant_id, &state).await? {
+            if v.id != Some(auth_info.id) {
+                return Err(error::new(400013, "手机号已被使用", 422));
+            }
+        }
+
+        if !service::verify_mobile_code(auth_info.id, mobile, &code, &state).await? {
+            return Err(error::new(400012, "验证码错误或已过期", 422));
+        }
+        user.mobile = Some(mobile.to_string());
+    }
+
+    if user.name.is_none() && user.mobile.is_none() {
+        return Err(error::new(400002, "没有需要修改的内容", 422));
+    }
+
+    user.update_time = Some(Utc::now());
+    service::update(&user, auth_info.tenant_id, &state).await?;
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::UpdateProfile, "", auth_info.id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    let user_data = match service::get_user_info_by_id(auth_info.id, auth_info.tenant_id, &state).await? {
+        None => return Err(error::new(400007, "无法获得用户信息", 422)),
+        Some(v) => v
+    };
+
+    Ok(HttpResponse::Ok().json(user_data))
+}
+
+#[derive(Deserialize)]
+pub struct SendMobileCodeReqJson {
+    mobile: Option<String>,
+}
+
+#[post("/user/mobile/code")]
+pub async fn send_mobile_code(req_info: web::Json<SendMobileCodeReqJson>, req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
+
+    let mobile = validator::required_str(&req_info.mobile, "手机号")?;
+    validator::mobile(&mobile, "手机号")?;
+
+    if service::get_by_mobile(&mobile, auth_info.tenant_id, &state).await?.is_some() {
+        return Err(error::new(400013, "手机号已被使用", 422));
+    }
+
+    service::send_mobile_code(auth_info.id, &mobile, &state).await?;
+
+    Ok(HttpResponse::Ok().body(""))
+}
+
+
+#[derive(Serialize)]
+struct ResExportJson {
+    export_time: DateTime<Utc>,
+    user: UserAdminInfo,
+    authorizations: Vec<authorizations::Authorization>,
+    logs: Vec<authorizations::AuthorizationLog>,
+    preferences: serde_json::Map<String, serde_json::Value>,
+}
+
+// 导出个人数据
+#[get("/user/export")]
+pub async fn export(req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
+
+    let user_data = match service::get_admin_info_by_id(auth_info.id, auth_info.tenant_id, &state).await? {
+        None => return Err(error::new(400007, "无法获得用户信息", 422)),
+        Some(v) => v
+    };
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::ExportData, "", auth_info.id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    let filename = format!("user-{}-export.json", user_data.uuid);
+    let data = ResExportJson {
+        export_time: Utc::now(),
+        user: user_data,
+        authorizations: authorizations::service::get_by_user_id(auth_info.id, auth_info.tenant_id, &state).await?,
+        logs: authorizations::service::get_logs_by_user_id(auth_info.id, auth_info.tenant_id, &state).await?,
+        preferences: preferences::service::get(auth_info.id, &state).await?,
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+        .json(data))
+}
+
+#[derive(Deserialize)]
+pub struct CloseAccountReqJson {
+    password: Option<String>,
+}
+
+// 注销账号
+#[delete("/user")]
+pub async fn close_account(req_info: web::Json<CloseAccountReqJson>, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
+
+    let password = validator::required_str(&req_info.password, "密码")?;
+
+    let user_data = match service::get_by_id(auth_info.id, auth_info.tenant_id, &state).await? {
+        None => return Err(error::new(400007, "无法获得用户信息", 422)),
+        Some(v) => v
+    };
+
+    if user_data.is_del != Some(0) {
+        return Err(error::new(100403, "Authentication failure", 401));
+    }
+
+    let (salt, password_store) = match (user_data.salt, user_data.password) {
+        (Some(salt), Some(password_store)) => (salt, password_store),
+        _ => return Err(error::new(100403, "Authentication failure", 401)),
+    };
+
+    if auth::crypt_password(&password, &salt) != password_store {
+        return Err(error::new(100407, "密码错误", 422));
+    }
+
+    service::close_account(auth_info.id, auth_info.tenant_id, &state).await?;
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::CloseAccount, "", auth_info.id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    Ok(HttpResponse::Ok().body(""))
+}
+
+const AVATAR_CONTENT_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+// 上传头像，multipart表单字段名为file
+#[post("/user/avatar")]
+pub async fn upload_avatar(mut payload: Multipart, req: HttpRequest, state: web::Data<AppState>, conn: ConnectionInfo) -> Result<HttpResponse, error::Error> {
+    let auth_info = auth::verify("ROLE_MEMBER", &req, &state).await?;
+
+    let max_size = state.config.get().avatar.max_size;
+
+    let mut file: Option<(Vec<u8>, String)> = None;
+    while let Some(item) = payload.next().await {
+        let mut field = match item {
+            Ok(v) => v,
+            Err(_) => return Err(error::new(400002, "上传数据格式错误", 422)),
+        };
+
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let content_type = match field.content_type() {
+            Some(v) => v.essence_str().to_string(),
+            None => String::new(),
+        };
+        if !AVATAR_CONTENT_TYPES.contains(&&content_type[..]) {
+            return Err(error::new(400015, "不支持的图片格式", 422));
+        }
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = match chunk {
+                Ok(v) => v,
+                Err(_) => return Err(error::new(400002, "上传数据格式错误", 422)),
+            };
+            if data.len() + chunk.len() > max_size {
+                return Err(error::new(400017, &format!("图片不能超过{}KB", max_size / 1024)[..], 422));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        file = Some((data, content_type));
+        break;
+    }
+
+    let (data, content_type) = match file {
+        None => return Err(error::new(400002, "文件不能为空", 422)),
+        Some(v) => v,
+    };
+
+    service::update_avatar(auth_info.id, auth_info.tenant_id, data, content_type, &state).await?;
+
+    let client = client::get_client_info(&state, &req, &conn);
+    authorizations::service::insert_log(LogType::UpdateAvatar, "", auth_info.id, 0, auth_info.tenant_id, &client, &state).await?;
+
+    let user_data = match service::get_user_info_by_id(auth_info.id, auth_info.tenant_id, &state).await? {
+        None => return Err(error::new(400007, "无法获得用户信息", 422)),
+        Some(v) => v
+    };
+
+    Ok(HttpResponse::Ok().json(user_data))
+}
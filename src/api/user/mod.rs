@@ -1,10 +1,11 @@
 pub mod controller;
+pub mod admin_controller;
 pub mod model;
 pub mod service;
 use serde::{Serialize};
 use chrono::prelude::*;
 
-#[derive(Debug, sqlx::FromRow)]
+#[derive(Debug, Clone, sqlx::FromRow)]
 pub struct User {
     pub id: Option<i32>,
     pub uuid: Option<uuid::Uuid>,
@@ -19,6 +20,15 @@ pub struct User {
     pub last_login_time: Option<DateTime<Utc>>,
     pub last_login_ip: Option<String>,
     pub user_type: Option<i16>,
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+    pub tenant_id: Option<i32>,
+}
+
+impl Default for User {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl User {
@@ -37,6 +47,9 @@ impl User {
             last_login_time: None,
             last_login_ip: None,
             user_type: None,
+            name: None,
+            avatar: None,
+            tenant_id: None,
         }
     }
 }
@@ -45,9 +58,62 @@ impl User {
 pub struct UserInfo {
     pub id: i32,
     pub username: Option<String>,
+    pub name: Option<String>,
     pub uuid: uuid::Uuid,
     pub mobile: Option<String>,
     pub last_login_time: Option<DateTime<Utc>>,
     pub last_login_ip: Option<String>,
     pub user_type: i16,
+    pub avatar: Option<String>,
+    #[sqlx(default)]
+    pub avatar_thumb: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow, Serialize)]
+pub struct UserAdminInfo {
+    pub id: i32,
+    pub uuid: uuid::Uuid,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    pub mobile: Option<String>,
+    pub user_type: i16,
+    pub is_enabled: i16,
+    pub is_del: i16,
+    pub create_time: Option<DateTime<Utc>>,
+    pub update_time: Option<DateTime<Utc>>,
+    pub last_login_time: Option<DateTime<Utc>>,
+    pub last_login_ip: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct UserFilter {
+    pub tenant_id: i32,
+    pub username: Option<String>,
+    pub mobile: Option<String>,
+    pub user_type: Option<i16>,
+    pub is_enabled: Option<i16>,
+    pub is_del: Option<i16>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+}
+
+
+#[derive(Debug, Serialize)]
+pub struct ImportRowResult {
+    pub row: usize,
+    pub username: String,
+    // valid: 校验通过(dry run)，created: 已创建，invalid: 校验失败
+    pub status: &'static str,
+    pub errors: Vec<String>,
+    pub id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub total: usize,
+    pub valid: usize,
+    pub invalid: usize,
+    pub created: usize,
+    pub rows: Vec<ImportRowResult>,
 }
\ No newline at end of file
@@ -1,11 +1,13 @@
 use chrono::prelude::*;
-use crate::lib::error;
-use super::{User, UserInfo};
+use crate::lib::{db, error, trace::Traced};
+use super::{User, UserInfo, UserAdminInfo, UserFilter};
 
-pub async fn get_by_id(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<Option<User>, error::Error> {
-    let r = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id=$1")
+pub async fn get_by_id(id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<User>, error::Error> {
+    let r = sqlx::query_as::<_, User>(&db::sql("SELECT * FROM users WHERE id=$1 AND tenant_id=$2"))
         .bind(id)
+        .bind(tenant_id)
         .fetch_optional(db)
+        .traced("user.get_by_id")
         .await;
     
     match r {
@@ -17,10 +19,12 @@ pub async fn get_by_id(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Log
     }
 }
 
-pub async fn get_by_username(username: &str, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<Option<User>, error::Error> {
-    let r = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username=$1")
+pub async fn get_by_username(username: &str, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<User>, error::Error> {
+    let r = sqlx::query_as::<_, User>(&db::sql("SELECT * FROM users WHERE username=$1 AND tenant_id=$2"))
         .bind(username)
+        .bind(tenant_id)
         .fetch_optional(db)
+        .traced("user.get_by_username")
         .await;
     
     match r {
@@ -32,40 +36,52 @@ pub async fn get_by_username(username: &str, db: &sqlx::Pool<sqlx::Postgres>, lo
     }
 }
 
-pub async fn insert(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<User, error::Error> {
-    let mut sql1 = vec![String::from("uuid")];
-    let mut sql2 = vec![String::from("$1")];
-    let mut sql_index = 2;
+pub async fn insert(user: &User, db: &mut db::Connection, log: &slog::Logger) -> Result<User, error::Error> {
+    let tenant_id = user.tenant_id.unwrap_or_default();
+    if tenant_id <= 0 {
+        error!(log, "insert tenant_id error: {}", tenant_id);
+        return Err(error::err500());
+    }
 
-    if let Some(_) = &user.username {
+    let mut sql1 = vec![String::from("uuid"), String::from("tenant_id")];
+    let mut sql2 = vec![String::from("$1"), String::from("$2")];
+    let mut sql_index = 3;
+
+    if user.username.is_some() {
         sql1.push(String::from("username"));
         sql2.push(format!("${}", sql_index));
         sql_index += 1;
     }
 
-    if let Some(_) = &user.password {
+    if user.password.is_some() {
         sql1.push(String::from("password"));
         sql2.push(format!("${}", sql_index));
         sql_index += 1;
     }
 
-    if let Some(_) = &user.salt {
+    if user.salt.is_some() {
         sql1.push(String::from("salt"));
         sql2.push(format!("${}", sql_index));
         sql_index += 1;
     }
 
-    if let Some(_) = &user.mobile {
+    if user.mobile.is_some() {
         sql1.push(String::from("mobile"));
         sql2.push(format!("${}", sql_index));
         sql_index += 1;
     }
 
+    if user.name.is_some() {
+        sql1.push(String::from("name"));
+        sql2.push(format!("${}", sql_index));
+        sql_index += 1;
+    }
+
     sql1.push(String::from("create_time"));
     sql2.push(format!("${}", sql_index));
     sql_index += 1;
 
-    if let Some(_) = &user.update_time {
+    if user.update_time.is_some() {
         sql1.push(String::from("update_time"));
         sql2.push(format!("${}", sql_index));
         sql_index += 1;
@@ -82,9 +98,9 @@ pub async fn insert(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Lo
     sql1.push(String::from("user_type"));
     sql2.push(format!("${}", sql_index));
 
-    let sql = format!("INSERT INTO users ({}) VALUES ({}) RETURNING *", sql1.join(","), sql2.join(","));
+    let sql = db::returning(&format!("INSERT INTO users ({}) VALUES ({})", sql1.join(","), sql2.join(",")));
     
-    let mut q = sqlx::query_as::<_, User>(&sql);
+    let mut q = sqlx::query(&sql);
 
     if let Some(uuid) = &user.uuid {
         q = q.bind(uuid);
@@ -92,6 +108,8 @@ pub async fn insert(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Lo
         q = q.bind(uuid::Uuid::new_v4());
     }
 
+    q = q.bind(tenant_id);
+
     if let Some(username) = &user.username {
         q = q.bind(username);
     }
@@ -108,6 +126,10 @@ pub async fn insert(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Lo
         q = q.bind(mobile);
     }
 
+    if let Some(name) = &user.name {
+        q = q.bind(name);
+    }
+
     if let Some(create_time) = &user.create_time {
         q = q.bind(create_time);
     } else {
@@ -136,22 +158,34 @@ pub async fn insert(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Lo
         q = q.bind(0);
     }
 
-    let r = q.fetch_one(db).await;
+    let r = db::fetch_returning::<User>(q, "users", None, db).traced("user.insert").await;
     
     match r {
         Ok(v) => Ok(v),
         Err(e) => {
+            if let Some(v) = unique_error(&e) {
+                return Err(v);
+            }
             error!(log, "{}", e);
             Err(error::err500())
         }
     }
 }
 
-pub async fn update(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<User, error::Error> {
-    let id = match user.id {
-        Some(v) => v,
-        None => 0,
-    };
+// 并发写入时用户名或手机号可能在检查之后被占用，违反唯一索引时返回与检查相同的错误
+fn unique_error(e: &sqlx::Error) -> Option<error::Error> {
+    if db::is_unique_violation(e, "users_tenant_id_username_key") {
+        return Some(error::new(400009, "用户名已存在", 422));
+    }
+    if db::is_unique_violation(e, "users_tenant_id_mobile_key") {
+        return Some(error::new(400013, "手机号已被使用", 422));
+    }
+
+    None
+}
+
+pub async fn update(user: &User, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<User, error::Error> {
+    let id = user.id.unwrap_or_default();
 
     if id <= 0 {
         error!(log, "update id error: {}", id);
@@ -161,42 +195,51 @@ pub async fn update(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Lo
     let mut sql1 = vec![format!("update_time = $1")];
     let mut sql_index = 2;
 
-    if let Some(_) = &user.username {
+    if user.username.is_some() {
         sql1.push(format!("username = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = &user.password {
+    if user.password.is_some() {
         sql1.push(format!("password = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = &user.salt {
+    if user.salt.is_some() {
         sql1.push(format!("salt = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = &user.mobile {
-        sql1.push(format!("mobile = ${}", sql_index));
+    // 手机号为空字符串时清空为NULL，手机号的唯一索引只排除NULL
+    if user.mobile.is_some() {
+        sql1.push(format!("mobile = NULLIF(${}, '')", sql_index));
+        sql_index += 1;
+    }
+    if user.name.is_some() {
+        sql1.push(format!("name = ${}", sql_index));
+        sql_index += 1;
+    }
+    if user.avatar.is_some() {
+        sql1.push(format!("avatar = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = &user.is_enabled {
+    if user.is_enabled.is_some() {
         sql1.push(format!("is_enabled = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = &user.last_login_time {
+    if user.last_login_time.is_some() {
         sql1.push(format!("last_login_time = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = &user.last_login_ip {
+    if user.last_login_ip.is_some() {
         sql1.push(format!("last_login_ip = ${}", sql_index));
         sql_index += 1;
     }
-    if let Some(_) = &user.user_type {
+    if user.user_type.is_some() {
         sql1.push(format!("user_type = ${}", sql_index));
         sql_index += 1;
     }
 
-    let sql = format!("UPDATE users SET {} WHERE id = ${} RETURNING *", sql1.join(","), sql_index);
+    let sql = db::returning(&format!("UPDATE users SET {} WHERE id = ${} AND tenant_id = ${}", sql1.join(","), sql_index, sql_index + 1));
 
-    let mut q = sqlx::query_as::<_, User>(&sql);
+    let mut q = sqlx::query(&sql);
 
     q = q.bind(Utc::now());
     if let Some(username) = &user.username {
@@ -211,6 +254,12 @@ pub async fn update(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Lo
     if let Some(mobile) = &user.mobile {
         q = q.bind(mobile);
     }
+    if let Some(name) = &user.name {
+        q = q.bind(name);
+    }
+    if let Some(avatar) = &user.avatar {
+        q = q.bind(avatar);
+    }
     if let Some(is_enabled) = &user.is_enabled {
         q = q.bind(is_enabled);
     }
@@ -223,25 +272,33 @@ pub async fn update(user: &User, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Lo
     if let Some(user_type) = &user.user_type {
         q = q.bind(user_type);
     }
-    q = q.bind(id);
+    q = q.bind(id).bind(tenant_id);
 
-    let r = q.fetch_one(db).await;
+    let r = async {
+        let mut conn = db.acquire().await?;
+        db::fetch_returning::<User>(q, "users", Some(id), &mut conn).await
+    }.traced("user.update").await;
     
     match r {
         Ok(v) => Ok(v),
         Err(e) => {
+            if let Some(v) = unique_error(&e) {
+                return Err(v);
+            }
             error!(log, "{}", e);
             Err(error::err500())
         }
     }
 }
 
-pub async fn update_last_login(login_time: DateTime<Utc>, ip: &str, user_id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<(), error::Error> {
-    let r = sqlx::query(r#"UPDATE users SET last_login_time=$1, last_login_ip=$2 WHERE id=$3"#)
+pub async fn update_last_login<'e, E: sqlx::Executor<'e, Database = db::Db>>(login_time: DateTime<Utc>, ip: &str, user_id: i32, tenant_id: i32, db: E, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql(r#"UPDATE users SET last_login_time=$1, last_login_ip=$2 WHERE id=$3 AND tenant_id=$4"#))
         .bind(login_time)
         .bind(ip)
         .bind(user_id)
+        .bind(tenant_id)
         .execute(db)
+        .traced("user.update_last_login")
         .await;
     
     if let Err(err) = r {
@@ -252,10 +309,12 @@ pub async fn update_last_login(login_time: DateTime<Utc>, ip: &str, user_id: i32
     Ok(())
 }
 
-pub async fn delete(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<(), error::Error> {
-    let r = sqlx::query("UPDATE users SET is_del = 1 WHERE id=$1")
+pub async fn delete<'e, E: sqlx::Executor<'e, Database = db::Db>>(id: i32, tenant_id: i32, db: E, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql("UPDATE users SET is_del = 1 WHERE id=$1 AND tenant_id=$2"))
         .bind(id)
+        .bind(tenant_id)
         .execute(db)
+        .traced("user.delete")
         .await;
     
     if let Err(err) = r {
@@ -266,12 +325,52 @@ pub async fn delete(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger
     Ok(())
 }
 
-pub async fn get_user_info_by_id(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log: &slog::Logger) -> Result<Option<UserInfo>, error::Error> {
-    let r = sqlx::query_as::<_, UserInfo>(r#"
-        SELECT id, username, uuid, mobile, last_login_time, last_login_ip, user_type FROM users
-        WHERE id = $1 AND is_del=0 AND is_enabled=1"#)
+pub async fn get_user_info_by_id(id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<UserInfo>, error::Error> {
+    let r = sqlx::query_as::<_, UserInfo>(&db::sql(r#"
+        SELECT id, username, name, uuid, mobile, last_login_time, last_login_ip, user_type, avatar FROM users
+        WHERE id = $1 AND tenant_id = $2 AND is_del=0 AND is_enabled=1"#))
+        .bind(id)
+        .bind(tenant_id)
+        .fetch_optional(db)
+        .traced("user.get_user_info_by_id")
+        .await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 已匿名化的用户(密码已清空)不能恢复，返回是否恢复成功
+pub async fn restore(id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<bool, error::Error> {
+    let r = sqlx::query(&db::sql("UPDATE users SET is_del = 0, anonymize_time = NULL, update_time = $1 WHERE id=$2 AND tenant_id=$3 AND password IS NOT NULL"))
+        .bind(Utc::now())
+        .bind(id)
+        .bind(tenant_id)
+        .execute(db)
+        .traced("user.restore")
+        .await;
+    
+    match r {
+        Ok(v) => Ok(v.rows_affected() > 0),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+pub async fn get_admin_info_by_id(id: i32, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<UserAdminInfo>, error::Error> {
+    let r = sqlx::query_as::<_, UserAdminInfo>(&db::sql(r#"
+        SELECT id, uuid, username, name, mobile, user_type, is_enabled, is_del, create_time, update_time, last_login_time, last_login_ip
+        FROM users WHERE id = $1 AND tenant_id = $2"#))
         .bind(id)
+        .bind(tenant_id)
         .fetch_optional(db)
+        .traced("user.get_admin_info_by_id")
         .await;
     
     match r {
@@ -282,3 +381,195 @@ pub async fn get_user_info_by_id(id: i32, db: &sqlx::Pool<sqlx::Postgres>, log:
         }
     }
 }
+
+// 拼接查询条件，返回 WHERE 子句和下一个参数序号
+fn search_where(filter: &UserFilter) -> (String, i32) {
+    let mut sql1 = vec![String::from("tenant_id = $1")];
+    let mut sql_index = 2;
+
+    if filter.username.is_some() {
+        sql1.push(format!("username LIKE ${} {}", sql_index, db::LIKE_ESCAPE));
+        sql_index += 1;
+    }
+    if filter.mobile.is_some() {
+        sql1.push(format!("mobile LIKE ${} {}", sql_index, db::LIKE_ESCAPE));
+        sql_index += 1;
+    }
+    if filter.user_type.is_some() {
+        sql1.push(format!("user_type = ${}", sql_index));
+        sql_index += 1;
+    }
+    if filter.is_enabled.is_some() {
+        sql1.push(format!("is_enabled = ${}", sql_index));
+        sql_index += 1;
+    }
+    if filter.is_del.is_some() {
+        sql1.push(format!("is_del = ${}", sql_index));
+        sql_index += 1;
+    }
+
+    (sql1.join(" AND "), sql_index)
+}
+
+// 排序字段只允许白名单中的列
+fn search_order(filter: &UserFilter) -> String {
+    let column = match filter.sort.as_deref() {
+        Some("username") => "username",
+        Some("create_time") => "create_time",
+        Some("last_login_time") => "last_login_time",
+        _ => "id",
+    };
+    let order = match filter.order.as_deref() {
+        Some("asc") => "ASC",
+        _ => "DESC",
+    };
+
+    format!("{} IS NULL, {} {}, id {}", column, column, order, order)
+}
+
+pub async fn search(filter: &UserFilter, offset: i64, limit: i64, db: &db::Pool, log: &slog::Logger) -> Result<Vec<UserAdminInfo>, error::Error> {
+    let (sql_where, sql_index) = search_where(filter);
+    let sql = format!(r#"
+        SELECT id, uuid, username, name, mobile, user_type, is_enabled, is_del, create_time, update_time, last_login_time, last_login_ip
+        FROM users WHERE {} ORDER BY {} LIMIT ${} OFFSET ${}"#, sql_where, search_order(filter), sql_index, sql_index + 1);
+    let sql = db::sql(&sql);
+
+    let mut q = sqlx::query_as::<_, UserAdminInfo>(&sql);
+
+    q = q.bind(filter.tenant_id);
+    if let Some(username) = &filter.username {
+        q = q.bind(db::like(username));
+    }
+    if let Some(mobile) = &filter.mobile {
+        q = q.bind(db::like(mobile));
+    }
+    if let Some(user_type) = &filter.user_type {
+        q = q.bind(user_type);
+    }
+    if let Some(is_enabled) = &filter.is_enabled {
+        q = q.bind(is_enabled);
+    }
+    if let Some(is_del) = &filter.is_del {
+        q = q.bind(is_del);
+    }
+    q = q.bind(limit).bind(offset);
+
+    let r = q.fetch_all(db).traced("user.search").await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+pub async fn count(filter: &UserFilter, db: &db::Pool, log: &slog::Logger) -> Result<i64, error::Error> {
+    let (sql_where, _) = search_where(filter);
+    let sql = format!("SELECT COUNT(*) FROM users WHERE {}", sql_where);
+    let sql = db::sql(&sql);
+
+    let mut q = sqlx::query_scalar::<_, i64>(&sql);
+
+    q = q.bind(filter.tenant_id);
+    if let Some(username) = &filter.username {
+        q = q.bind(db::like(username));
+    }
+    if let Some(mobile) = &filter.mobile {
+        q = q.bind(db::like(mobile));
+    }
+    if let Some(user_type) = &filter.user_type {
+        q = q.bind(user_type);
+    }
+    if let Some(is_enabled) = &filter.is_enabled {
+        q = q.bind(is_enabled);
+    }
+    if let Some(is_del) = &filter.is_del {
+        q = q.bind(is_del);
+    }
+
+    let r = q.fetch_one(db).traced("user.count").await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+pub async fn get_by_mobile(mobile: &str, tenant_id: i32, db: &db::Pool, log: &slog::Logger) -> Result<Option<User>, error::Error> {
+    let r = sqlx::query_as::<_, User>(&db::sql("SELECT * FROM users WHERE mobile=$1 AND tenant_id=$2 AND is_del=0"))
+        .bind(mobile)
+        .bind(tenant_id)
+        .fetch_optional(db)
+        .traced("user.get_by_mobile")
+        .await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 设置注销用户的匿名化时间
+pub async fn schedule_anonymize<'e, E: sqlx::Executor<'e, Database = db::Db>>(id: i32, anonymize_time: DateTime<Utc>, tenant_id: i32, db: E, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql("UPDATE users SET anonymize_time = $1, update_time = $2 WHERE id=$3 AND tenant_id=$4"))
+        .bind(anonymize_time)
+        .bind(Utc::now())
+        .bind(id)
+        .bind(tenant_id)
+        .execute(db)
+        .traced("user.schedule_anonymize")
+        .await;
+    
+    if let Err(err) = r {
+        error!(log, "{}", err);
+        return Err(error::err500());
+    }
+
+    Ok(())
+}
+
+// 获取已到匿名化时间的注销用户，由后台任务调用，不区分租户
+pub async fn get_to_anonymize(now: DateTime<Utc>, db: &db::Pool, log: &slog::Logger) -> Result<Vec<User>, error::Error> {
+    let r = sqlx::query_as::<_, User>(&db::sql("SELECT * FROM users WHERE is_del = 1 AND anonymize_time <= $1"))
+        .bind(now)
+        .fetch_all(db)
+        .traced("user.get_to_anonymize")
+        .await;
+    
+    match r {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            error!(log, "{}", e);
+            Err(error::err500())
+        }
+    }
+}
+
+// 清除用户个人信息，保留id和uuid，由后台任务调用，不区分租户
+pub async fn anonymize(id: i32, db: &db::Pool, log: &slog::Logger) -> Result<(), error::Error> {
+    let r = sqlx::query(&db::sql(r#"
+        UPDATE users SET username = $1, password = NULL, salt = NULL, mobile = NULL, name = NULL, avatar = NULL,
+            last_login_ip = NULL, anonymize_time = NULL, update_time = $2
+        WHERE id = $3"#))
+        .bind(format!("deleted_{}", id))
+        .bind(Utc::now())
+        .bind(id)
+        .execute(db)
+        .traced("user.anonymize")
+        .await;
+    
+    if let Err(err) = r {
+        error!(log, "{}", err);
+        return Err(error::err500());
+    }
+
+    Ok(())
+}
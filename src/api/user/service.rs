@@ -1,47 +1,405 @@
 use actix_web::web;
 use crate::AppState;
-use crate::lib::{client::ClientInfo, error};
-use super::{model, User, UserInfo};
+use crate::lib::{self, client::ClientInfo, error, validator, auth};
+use rand::Rng;
+use crate::api::{authorizations, preferences, repository::Transaction};
+use super::{User, UserInfo, UserAdminInfo, UserFilter, ImportRowResult, ImportReport};
+use super::admin_controller::check_user_type;
+use std::collections::HashSet;
 use chrono::prelude::*;
+use chrono::Duration;
 
-pub async fn get_by_id(id: i32, state: &web::Data<AppState>) -> Result<Option<User>, error::Error> {
-    let result = model::get_by_id(id, &state.db, &state.log).await?;
+pub async fn get_by_id(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<User>, error::Error> {
+    let result = state.repo.users.get_by_id(id, tenant_id, &state.log).await?;
 
     Ok(result)
 }
 
-pub async fn get_by_username(username: &str, state: &web::Data<AppState>) -> Result<Option<User>, error::Error> {
-    let result = model::get_by_username(&username, &state.db, &state.log).await?;
+pub async fn get_by_username(username: &str, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<User>, error::Error> {
+    let result = state.repo.users.get_by_username(username, tenant_id, &state.log).await?;
 
     Ok(result)
 }
 
 pub async fn insert(user: &User, state: &web::Data<AppState>) -> Result<User, error::Error> {
-    let result = model::insert(user, &state.db, &state.log).await?;
+    let result = state.repo.users.insert(user, &state.log).await?;
 
     Ok(result)
 }
 
-pub async fn update(user: &User, state: &web::Data<AppState>) -> Result<User, error::Error> {
-    let result = model::update(user, &state.db, &state.log).await?;
+pub async fn update(user: &User, tenant_id: i32, state: &web::Data<AppState>) -> Result<User, error::Error> {
+    let result = state.repo.users.update(user, tenant_id, &state.log).await?;
 
     Ok(result)
 }
 
-pub async fn delete(id: i32, state: &web::Data<AppState>) -> Result<(), error::Error> {
-    model::delete(id, &state.db, &state.log).await?;
+pub async fn delete(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    state.repo.users.delete(id, tenant_id, &state.log).await?;
 
     Ok(())
 }
 
-pub async fn update_last_login(login_time: DateTime<Utc>, user_id: i32, client: &ClientInfo, state: &web::Data<AppState>) -> Result<(), error::Error> {
-    model::update_last_login(login_time, &client.ip, user_id, &state.db, &state.log).await?;
+pub async fn update_last_login(login_time: DateTime<Utc>, user_id: i32, tenant_id: i32, client: &ClientInfo, tx: &mut dyn Transaction, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    tx.update_last_login(login_time, &client.ip, user_id, tenant_id, &state.log).await?;
 
     Ok(())
 }
 
-pub async fn get_user_info_by_id(id: i32, state: &web::Data<AppState>) -> Result<Option<UserInfo>, error::Error> {
-    let result = model::get_user_info_by_id(id, &state.db, &state.log).await?;
+pub async fn get_user_info_by_id(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<UserInfo>, error::Error> {
+    let mut result = state.repo.users.get_user_info_by_id(id, tenant_id, &state.log).await?;
+
+    // 数据库中保存的是存储key，返回给前端时转换为url
+    if let Some(v) = result.as_mut() {
+        if let Some(key) = v.avatar.take() {
+            v.avatar_thumb = Some(state.storage.url(&avatar_thumb_key(&key)));
+            v.avatar = Some(state.storage.url(&key));
+        }
+    }
+
+    Ok(result)
+}
+
+// 恢复已删除用户，已匿名化的用户返回false
+pub async fn restore(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<bool, error::Error> {
+    let result = state.repo.users.restore(id, tenant_id, &state.log).await?;
+
+    Ok(result)
+}
+
+pub async fn get_admin_info_by_id(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<UserAdminInfo>, error::Error> {
+    let result = state.repo.users.get_admin_info_by_id(id, tenant_id, &state.log).await?;
+
+    Ok(result)
+}
+
+// 分页查询用户，返回当前页数据和总数
+pub async fn search(filter: &UserFilter, page: i64, page_size: i64, state: &web::Data<AppState>) -> Result<(Vec<UserAdminInfo>, i64), error::Error> {
+    let offset = (page - 1) * page_size;
+    let items = state.repo.users.search(filter, offset, page_size, &state.log).await?;
+    let total = state.repo.users.count(filter, &state.log).await?;
+
+    Ok((items, total))
+}
+
+pub async fn get_by_mobile(mobile: &str, tenant_id: i32, state: &web::Data<AppState>) -> Result<Option<User>, error::Error> {
+    let result = state.repo.users.get_by_mobile(mobile, tenant_id, &state.log).await?;
 
     Ok(result)
 }
+
+// 向新手机号发送验证码，60秒内只能发送一次，验证码5分钟有效
+pub async fn send_mobile_code(user_id: i32, mobile: &str, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    let lock_key = format!("user_mobile_code_lock_{}", user_id);
+    if lib::redis::has_key(lock_key.clone(), &state.cache, &state.log).await? {
+        return Err(error::new(400014, "验证码发送过于频繁", 422));
+    }
+
+    let code = format!("{:06}", rand::rng().random_range(0..1000000));
+    lib::redis::set_with_expire(format!("user_mobile_code_{}", user_id), format!("{}:{}", mobile, code), 300, &state.cache, &state.log).await?;
+    lib::redis::set_with_expire(lock_key, 1, 60, &state.cache, &state.log).await?;
+
+    lib::sms::send(mobile, lib::sms::TEMPLATE_MOBILE_CODE, &[&code], &state.log).await?;
+
+    Ok(())
+}
+
+// 校验手机验证码，验证码只能使用一次，校验失败也会作废
+// 内存存储时不能发送验证码，校验总是失败
+pub async fn verify_mobile_code(user_id: i32, mobile: &str, code: &str, state: &web::Data<AppState>) -> Result<bool, error::Error> {
+    if state.config.get().repository.is_memory() {
+        return Ok(false);
+    }
+
+    let key = format!("user_mobile_code_{}", user_id);
+    let stored = lib::redis::get::<Option<String>>(key.clone(), &state.cache, &state.log).await?;
+    let stored = match stored {
+        None => return Ok(false),
+        Some(v) => v,
+    };
+
+    lib::redis::del(key, &state.cache, &state.log).await?;
+
+    Ok(stored == format!("{}:{}", mobile, code))
+}
+
+// 注销账号：软删除，撤销所有授权，并在宽限期后匿名化个人信息，在同一个事务中执行
+pub async fn close_account(id: i32, tenant_id: i32, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    let grace_days = state.config.get().user.delete_grace_days;
+
+    let mut tx = authorizations::service::begin(state).await?;
+    tx.delete_user(id, tenant_id, &state.log).await?;
+    tx.schedule_anonymize(id, Utc::now() + Duration::days(grace_days), tenant_id, &state.log).await?;
+    authorizations::service::revoke_all_by_user_tx(id, tenant_id, tx.as_mut(), state).await?;
+
+    authorizations::service::commit(tx, state).await
+}
+
+// 匿名化已过宽限期的注销用户
+pub async fn anonymize_expired(state: &web::Data<AppState>) -> Result<usize, error::Error> {
+    let users = state.repo.users.get_to_anonymize(Utc::now(), &state.log).await?;
+
+    for user in &users {
+        let id = user.id.unwrap_or_default();
+        if let Some(avatar) = &user.avatar {
+            state.storage.delete(avatar).await?;
+            state.storage.delete(&avatar_thumb_key(avatar)).await?;
+        }
+        state.repo.users.anonymize(id, &state.log).await?;
+        state.repo.logs.anonymize(id, user.tenant_id.unwrap_or_default(), &state.log).await?;
+        preferences::service::delete(id, state).await?;
+        info!(state.log, "user {} anonymized", id);
+    }
+
+    Ok(users.len())
+}
+
+// 缩略图key，如 avatars/a/b.jpg 对应 avatars/a/b_thumb.png
+pub fn avatar_thumb_key(key: &str) -> String {
+    let stem = match key.rfind('.') {
+        Some(i) => &key[..i],
+        None => key,
+    };
+
+    format!("{}_thumb.png", stem)
+}
+
+// 校验头像图片并生成缩略图，返回扩展名和缩略图数据
+fn process_avatar(data: &[u8], content_type: &str, min_dimension: u32, max_dimension: u32, thumb_size: u32) -> Result<(&'static str, Vec<u8>), error::Error> {
+    let format = match image::guess_format(data) {
+        Ok(v) => v,
+        Err(_) => return Err(error::new(400015, "不支持的图片格式", 422)),
+    };
+
+    let ext = match (format, content_type) {
+        (image::ImageFormat::Png, "image/png") => "png",
+        (image::ImageFormat::Jpeg, "image/jpeg") => "jpg",
+        (image::ImageFormat::Gif, "image/gif") => "gif",
+        (image::ImageFormat::WebP, "image/webp") => "webp",
+        _ => return Err(error::new(400015, "不支持的图片格式", 422)),
+    };
+
+    // 先读取尺寸，避免解码超大图片
+    let reader = image::ImageReader::with_format(std::io::Cursor::new(data), format);
+    let (width, height) = match reader.into_dimensions() {
+        Ok(v) => v,
+        Err(_) => return Err(error::new(400015, "图片无法识别", 422)),
+    };
+    if width < min_dimension || height < min_dimension || width > max_dimension || height > max_dimension {
+        return Err(error::new(400016, &format!("图片尺寸需在{}到{}像素之间", min_dimension, max_dimension)[..], 422));
+    }
+
+    let img = match image::load_from_memory_with_format(data, format) {
+        Ok(v) => v,
+        Err(_) => return Err(error::new(400015, "图片无法识别", 422)),
+    };
+
+    let mut thumb = Vec::new();
+    if img.thumbnail(thumb_size, thumb_size).write_to(&mut std::io::Cursor::new(&mut thumb), image::ImageFormat::Png).is_err() {
+        return Err(error::err500());
+    }
+
+    Ok((ext, thumb))
+}
+
+// 保存头像和缩略图，并删除旧头像
+pub async fn update_avatar(user_id: i32, tenant_id: i32, data: Vec<u8>, content_type: String, state: &web::Data<AppState>) -> Result<(), error::Error> {
+    let config = state.config.get();
+    let min_dimension = config.avatar.min_dimension;
+    let max_dimension = config.avatar.max_dimension;
+    let thumb_size = config.avatar.thumb_size;
+
+    let user_data = match state.repo.users.get_by_id(user_id, tenant_id, &state.log).await? {
+        None => return Err(error::new(400007, "无法获得用户信息", 422)),
+        Some(v) => v,
+    };
+    let user_uuid = user_data.uuid.unwrap_or_default();
+
+    let (data, ext, thumb) = match web::block(move || {
+        process_avatar(&data, &content_type, min_dimension, max_dimension, thumb_size).map(|(ext, thumb)| (data, ext, thumb))
+    }).await {
+        Ok(v) => v?,
+        Err(e) => {
+            error!(state.log, "{}", e);
+            return Err(error::err500());
+        }
+    };
+
+    let key = format!("avatars/{}/{}.{}", user_uuid, uuid::Uuid::new_v4().simple(), ext);
+    state.storage.put(&key, data).await?;
+    state.storage.put(&avatar_thumb_key(&key), thumb).await?;
+
+    let mut user = User::new();
+    user.id = Some(user_id);
+    user.avatar = Some(key);
+    user.update_time = Some(Utc::now());
+    state.repo.users.update(&user, tenant_id, &state.log).await?;
+
+    if let Some(old) = user_data.avatar {
+        state.storage.delete(&old).await?;
+        state.storage.delete(&avatar_thumb_key(&old)).await?;
+    }
+
+    Ok(())
+}
+
+const IMPORT_COLUMNS: [&str; 6] = ["username", "password", "mobile", "name", "user_type", "is_enabled"];
+
+fn collect(errors: &mut Vec<String>, result: Result<(), error::Error>) {
+    if let Err(e) = result {
+        errors.push(e.errmsg);
+    }
+}
+
+// 校验一行导入数据，返回所有错误而不是遇到第一个错误就停止
+// 返回的用户不含密码，密码原文单独返回，只在实际插入时计算哈希
+fn validate_import_row(record: &csv::StringRecord, headers: &csv::StringRecord, errors: &mut Vec<String>) -> (User, String) {
+    let get = |name: &str| -> Option<String> {
+        headers.iter().position(|v| v == name)
+            .and_then(|i| record.get(i))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let mut user = User::new();
+
+    match validator::required_str(&get("username"), "用户名") {
+        Ok(v) => {
+            collect(errors, validator::max_len(&v, 50, "用户名"));
+            user.username = Some(v);
+        },
+        Err(e) => errors.push(e.errmsg),
+    }
+
+    let password = match validator::required_str(&get("password"), "密码") {
+        Ok(v) => v,
+        Err(e) => {
+            errors.push(e.errmsg);
+            String::new()
+        }
+    };
+
+    if let Some(v) = get("mobile") {
+        collect(errors, validator::mobile(&v, "手机号"));
+        user.mobile = Some(v);
+    }
+
+    if let Some(v) = get("name") {
+        collect(errors, validator::max_len(&v, 50, "姓名"));
+        user.name = Some(v);
+    }
+
+    match get("user_type").map(|v| v.parse::<i16>()) {
+        None => user.user_type = Some(0),
+        Some(Ok(v)) => {
+            collect(errors, check_user_type(v));
+            user.user_type = Some(v);
+        },
+        Some(Err(_)) => errors.push(String::from("用户类型错误")),
+    }
+
+    match get("is_enabled").map(|v| v.parse::<i16>()) {
+        None => user.is_enabled = Some(1),
+        Some(Ok(v)) if v == 0 || v == 1 => user.is_enabled = Some(v),
+        Some(_) => errors.push(String::from("启用状态错误")),
+    }
+
+    (user, password)
+}
+
+// 从CSV批量导入用户，第一行为表头，校验通过的行在同一个事务中插入
+pub async fn import_users(data: &[u8], dry_run: bool, tenant_id: i32, operator_id: i32, client: &ClientInfo, state: &web::Data<AppState>) -> Result<ImportReport, error::Error> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+
+    let headers = match rdr.headers() {
+        Ok(v) => v.clone(),
+        Err(_) => return Err(error::new(400018, "CSV格式错误", 422)),
+    };
+    for name in ["username", "password"] {
+        if !headers.iter().any(|v| v == name) {
+            return Err(error::new(400018, &format!("CSV缺少{}列，支持的列：{}", name, IMPORT_COLUMNS.join(","))[..], 422));
+        }
+    }
+
+    let mut rows = Vec::new();
+    let mut users = Vec::new();
+    // users中每一项对应rows中的序号
+    let mut indexes = Vec::new();
+    let mut usernames = HashSet::new();
+    let mut mobiles = HashSet::new();
+
+    for (i, record) in rdr.records().enumerate() {
+        // 表头占第1行
+        let row = i + 2;
+        let record = match record {
+            Ok(v) => v,
+            Err(_) => {
+                rows.push(ImportRowResult { row, username: String::new(), status: "invalid", errors: vec![String::from("CSV格式错误")], id: None });
+                continue;
+            }
+        };
+
+        let mut errors = Vec::new();
+        let (mut user, password) = validate_import_row(&record, &headers, &mut errors);
+        user.tenant_id = Some(tenant_id);
+        let username = user.username.clone().unwrap_or_default();
+
+        if !username.is_empty() {
+            if !usernames.insert(username.clone()) {
+                errors.push(String::from("用户名在文件中重复"));
+            } else if state.repo.users.get_by_username(&username, tenant_id, &state.log).await?.is_some() {
+                errors.push(String::from("用户名已存在"));
+            }
+        }
+
+        // 未删除用户的手机号在租户内唯一，插入时违反唯一约束会使整个事务失败，这里逐行检查
+        if let Some(mobile) = user.mobile.clone() {
+            if !mobiles.insert(mobile.clone()) {
+                errors.push(String::from("手机号在文件中重复"));
+            } else if state.repo.users.get_by_mobile(&mobile, tenant_id, &state.log).await?.is_some() {
+                errors.push(String::from("手机号已被使用"));
+            }
+        }
+
+        if errors.is_empty() {
+            // 只在实际导入时计算密码哈希
+            if !dry_run {
+                let salt = auth::salt();
+                user.password = Some(auth::crypt_password(&password, &salt));
+                user.salt = Some(salt);
+            }
+            rows.push(ImportRowResult { row, username, status: "valid", errors, id: None });
+            indexes.push(rows.len() - 1);
+            users.push(user);
+        } else {
+            rows.push(ImportRowResult { row, username, status: "invalid", errors, id: None });
+        }
+    }
+
+    let valid = users.len();
+    let mut created = 0;
+
+    if !dry_run && !users.is_empty() {
+        let inserted = state.repo.users.insert_many(&users, &state.log).await?;
+        for (index, user) in indexes.iter().zip(inserted) {
+            rows[*index].id = user.id;
+            rows[*index].status = "created";
+        }
+        created = users.len();
+
+        let msg = format!("operator={},import", operator_id);
+        for index in &indexes {
+            if let Some(id) = rows[*index].id {
+                authorizations::service::insert_log(authorizations::LogType::AdminCreateUser, &msg, id, 0, tenant_id, client, state).await?;
+            }
+        }
+    }
+
+    Ok(ImportReport {
+        dry_run,
+        total: rows.len(),
+        valid,
+        invalid: rows.len() - valid,
+        created,
+        rows,
+    })
+}
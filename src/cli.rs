@@ -0,0 +1,345 @@
+use actix_web::web;
+use std::io::{Error, ErrorKind};
+use chrono::prelude::*;
+use rand::Rng;
+use crate::AppState;
+use crate::api::{user, tenant, authorizations};
+use crate::api::authorizations::LogType;
+use crate::lib::{self, auth, client::ClientInfo, settings::Settings};
+
+const USAGE: &str = r#"usage: rust-actix-rest-api-boilerplate [--config <file>] [command]
+
+commands:
+  serve                                   启动HTTP服务(默认)
+  migrate                                 执行数据库迁移
+  check-config                            检查配置文件
+  encrypt-secret [value]                  使用主密钥加密敏感配置，未指定value时从标准输入读取
+  create-admin <username> [--password-stdin] [--tenant <code>]
+  reset-password <username> [--password-stdin] [--tenant <code>]
+  revoke-sessions <username> [--tenant <code>]
+  purge-blacklist                         清理已过期的token黑名单
+  import-users <file.csv> [--dry-run] [--tenant <code>]"#;
+
+// 带值的选项，解析位置参数时跳过其值
+const VALUE_OPTIONS: [&str; 1] = ["--tenant"];
+
+fn usage_error(msg: &str) -> Error {
+    eprintln!("{}", USAGE);
+    Error::new(ErrorKind::InvalidInput, msg.to_string())
+}
+
+fn option<'a>(args: &'a [String], name: &str) -> Result<Option<&'a String>, Error> {
+    match args.iter().position(|v| v == name) {
+        None => Ok(None),
+        Some(i) => match args.get(i + 1) {
+            Some(v) => Ok(Some(v)),
+            None => Err(usage_error(&format!("missing value for {}", name))),
+        },
+    }
+}
+
+// 取出选项及其值并从参数中移除，用于在解析命令前处理全局选项
+pub fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, Error> {
+    match args.iter().position(|v| v == name) {
+        None => Ok(None),
+        Some(i) if i + 1 < args.len() => {
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(value))
+        },
+        Some(_) => Err(usage_error(&format!("missing value for {}", name))),
+    }
+}
+
+fn flag(args: &[String], name: &str) -> bool {
+    args.iter().any(|v| v == name)
+}
+
+fn positional(args: &[String]) -> Vec<&String> {
+    let mut result = Vec::new();
+    let mut iter = args.iter();
+    while let Some(v) = iter.next() {
+        if VALUE_OPTIONS.contains(&v.as_str()) {
+            iter.next();
+        } else if !v.starts_with("--") {
+            result.push(v);
+        }
+    }
+
+    result
+}
+
+fn cli_client() -> ClientInfo {
+    ClientInfo {
+        ip: String::new(),
+        user_agent: String::from("cli"),
+    }
+}
+
+fn random_password() -> String {
+    const CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnpqrstuvwxyz23456789";
+    let mut rng = rand::rng();
+    (0..16).map(|_| CHARS[rng.random_range(0..CHARS.len())] as char).collect()
+}
+
+// 从标准输入读取一行，去掉行尾换行符
+fn read_line() -> std::io::Result<String> {
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// 指定 --password-stdin 时从标准输入读取密码，避免出现在命令行参数和shell历史中，否则生成随机密码
+// 返回密码和是否为生成的密码
+fn read_password(args: &[String]) -> std::io::Result<(String, bool)> {
+    if !flag(args, "--password-stdin") {
+        return Ok((random_password(), true));
+    }
+
+    let password = read_line()?;
+    if password.is_empty() {
+        return Err(usage_error("missing password"));
+    }
+
+    Ok((password, false))
+}
+
+async fn get_tenant_id(args: &[String], state: &web::Data<AppState>) -> Result<i32, Error> {
+    let tenant_code = match option(args, "--tenant")? {
+        Some(v) => v.to_string(),
+        None => state.config.get().tenant.default.clone(),
+    };
+
+    match tenant::service::get_by_code(&tenant_code, state).await {
+        Ok(Some(v)) => Ok(v.id),
+        Ok(None) => Err(Error::new(ErrorKind::InvalidInput, format!("unknown tenant: {}", tenant_code))),
+        Err(e) => Err(Error::other(e.errmsg)),
+    }
+}
+
+async fn get_user(username: &str, tenant_id: i32, state: &web::Data<AppState>) -> Result<user::User, Error> {
+    match user::service::get_by_username(username, tenant_id, state).await {
+        Ok(Some(v)) if v.is_del == Some(0) => Ok(v),
+        Ok(_) => Err(Error::new(ErrorKind::NotFound, format!("user not found: {}", username))),
+        Err(e) => Err(Error::other(e.errmsg)),
+    }
+}
+
+pub async fn run(args: &[String], state: &web::Data<AppState>) -> std::io::Result<()> {
+    let result = match args[0].as_str() {
+        "migrate" => migrate(state).await,
+        "create-admin" => create_admin(&args[1..], state).await,
+        "reset-password" => reset_password(&args[1..], state).await,
+        "revoke-sessions" => revoke_sessions(&args[1..], state).await,
+        "purge-blacklist" => purge_blacklist(state).await,
+        "import-users" => import_users(&args[1..], state).await,
+        _ => Err(usage_error(&format!("unknown command: {}", args[0]))),
+    };
+
+    if let Err(e) = &result {
+        error!(state.log, "command {} failed: {}", args[0], e);
+    }
+
+    result
+}
+
+// 检查配置文件，只加载并校验配置不连接数据库和redis，校验失败时在加载阶段已输出错误
+pub fn check_config(settings: &Settings) -> std::io::Result<()> {
+    println!("config ok, profile {}", settings.profile);
+
+    Ok(())
+}
+
+// 使用主密钥加密敏感配置，输出结果填入配置文件的 [secrets] 中
+pub fn encrypt_secret(args: &[String]) -> std::io::Result<()> {
+    let master_key = match lib::settings::master_key() {
+        Ok(Some(v)) => v,
+        Ok(None) => return Err(Error::new(ErrorKind::InvalidInput, format!("missing {}", lib::settings::MASTER_KEY_ENV))),
+        Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e)),
+    };
+
+    let value = match positional(args).first() {
+        Some(v) => v.to_string(),
+        None => read_line()?,
+    };
+    if value.is_empty() {
+        return Err(usage_error("missing value"));
+    }
+
+    println!("{}", lib::aes::encrypt_secret(&value, &master_key));
+
+    Ok(())
+}
+
+// 执行数据库迁移
+async fn migrate(state: &web::Data<AppState>) -> std::io::Result<()> {
+    if let Err(e) = lib::db::migrate(&state.db, &state.log).await {
+        return Err(Error::other(e));
+    }
+
+    println!("database migrated");
+
+    Ok(())
+}
+
+// 创建管理员，未指定密码时生成随机密码
+async fn create_admin(args: &[String], state: &web::Data<AppState>) -> std::io::Result<()> {
+    let username = match positional(args).first() {
+        Some(v) => v.to_string(),
+        None => return Err(usage_error("missing username")),
+    };
+    let tenant_id = get_tenant_id(args, state).await?;
+
+    match user::service::get_by_username(&username, tenant_id, state).await {
+        Ok(None) => {},
+        Ok(Some(_)) => return Err(Error::new(ErrorKind::AlreadyExists, format!("user already exists: {}", username))),
+        Err(e) => return Err(Error::other(e.errmsg)),
+    }
+
+    let (password, generated) = read_password(args)?;
+
+    let salt = auth::salt();
+    let mut user = user::User::new();
+    user.username = Some(username.clone());
+    user.password = Some(auth::crypt_password(&password, &salt));
+    user.salt = Some(salt);
+    user.user_type = Some(10);
+    user.tenant_id = Some(tenant_id);
+
+    let user = match user::service::insert(&user, state).await {
+        Ok(v) => v,
+        Err(e) => return Err(Error::other(e.errmsg)),
+    };
+    let user_id = user.id.unwrap_or_default();
+
+    if let Err(e) = authorizations::service::insert_log(LogType::AdminCreateUser, "operator=0,cli", user_id, 0, tenant_id, &cli_client(), state).await {
+        return Err(Error::other(e.errmsg));
+    }
+
+    println!("admin {} created, id {}", username, user_id);
+    if generated {
+        println!("password: {}", password);
+    }
+
+    Ok(())
+}
+
+// 内存存储启动时没有用户，创建默认租户的管理员admin并在日志中输出随机密码
+pub async fn seed_admin(state: &web::Data<AppState>) -> std::io::Result<()> {
+    let tenant_id = get_tenant_id(&[], state).await?;
+    let password = random_password();
+
+    let salt = auth::salt();
+    let mut user = user::User::new();
+    user.username = Some(String::from("admin"));
+    user.password = Some(auth::crypt_password(&password, &salt));
+    user.salt = Some(salt);
+    user.user_type = Some(10);
+    user.tenant_id = Some(tenant_id);
+
+    if let Err(e) = user::service::insert(&user, state).await {
+        return Err(Error::other(e.errmsg));
+    }
+
+    warn!(state.log, "memory repository, admin created with password {}", password);
+
+    Ok(())
+}
+
+// 重置用户密码并撤销所有授权，未指定密码时生成随机密码
+async fn reset_password(args: &[String], state: &web::Data<AppState>) -> std::io::Result<()> {
+    let username = match positional(args).first() {
+        Some(v) => v.to_string(),
+        None => return Err(usage_error("missing username")),
+    };
+    let tenant_id = get_tenant_id(args, state).await?;
+    let target = get_user(&username, tenant_id, state).await?;
+    let user_id = target.id.unwrap_or_default();
+
+    let (password, generated) = read_password(args)?;
+
+    let salt = auth::salt();
+    let mut user = user::User::new();
+    user.id = Some(user_id);
+    user.password = Some(auth::crypt_password(&password, &salt));
+    user.salt = Some(salt);
+    user.update_time = Some(Utc::now());
+
+    if let Err(e) = user::service::update(&user, tenant_id, state).await {
+        return Err(Error::other(e.errmsg));
+    }
+    // 与管理后台重置密码一致，已签发的token全部失效
+    if let Err(e) = authorizations::service::revoke_all_by_user(user_id, tenant_id, state).await {
+        return Err(Error::other(e.errmsg));
+    }
+    if let Err(e) = authorizations::service::insert_log(LogType::AdminResetPassword, "operator=0,cli", user_id, 0, tenant_id, &cli_client(), state).await {
+        return Err(Error::other(e.errmsg));
+    }
+
+    println!("password of {} reset", username);
+    if generated {
+        println!("password: {}", password);
+    }
+
+    Ok(())
+}
+
+// 撤销用户所有授权
+async fn revoke_sessions(args: &[String], state: &web::Data<AppState>) -> std::io::Result<()> {
+    let username = match positional(args).first() {
+        Some(v) => v.to_string(),
+        None => return Err(usage_error("missing username")),
+    };
+    let tenant_id = get_tenant_id(args, state).await?;
+    let target = get_user(&username, tenant_id, state).await?;
+    let user_id = target.id.unwrap_or_default();
+
+    if let Err(e) = authorizations::service::revoke_all_by_user(user_id, tenant_id, state).await {
+        return Err(Error::other(e.errmsg));
+    }
+    if let Err(e) = authorizations::service::insert_log(LogType::AdminRevokeSessions, "operator=0,cli", user_id, 0, tenant_id, &cli_client(), state).await {
+        return Err(Error::other(e.errmsg));
+    }
+
+    println!("sessions of {} revoked", username);
+
+    Ok(())
+}
+
+// 清理数据库中已过期的token黑名单，redis中的记录会自动过期
+async fn purge_blacklist(state: &web::Data<AppState>) -> std::io::Result<()> {
+    let count = match authorizations::service::purge_black_list(state).await {
+        Ok(v) => v,
+        Err(e) => return Err(Error::other(e.errmsg)),
+    };
+
+    println!("{} expired blacklist entries purged", count);
+
+    Ok(())
+}
+
+// 从CSV批量导入用户，输出JSON格式的导入报告
+async fn import_users(args: &[String], state: &web::Data<AppState>) -> std::io::Result<()> {
+    let path = match positional(args).first() {
+        Some(v) => v.to_string(),
+        None => return Err(usage_error("missing csv file")),
+    };
+    let dry_run = flag(args, "--dry-run");
+    let tenant_id = get_tenant_id(args, state).await?;
+
+    let data = std::fs::read(path)?;
+
+    let report = match user::service::import_users(&data, dry_run, tenant_id, 0, &cli_client(), state).await {
+        Ok(v) => v,
+        Err(e) => return Err(Error::other(e.errmsg)),
+    };
+
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+    if report.invalid > 0 {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{} invalid rows", report.invalid)));
+    }
+
+    Ok(())
+}
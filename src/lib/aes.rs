@@ -1,9 +1,11 @@
 use aes_gcm::{
     aead::{Aead, KeyInit},
     Aes128Gcm,
+    Aes256Gcm,
     Nonce,
 };
 use rand::Rng;
+use sha2::{Digest, Sha256};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
 
 const BASE_STR: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
@@ -58,4 +60,27 @@ pub fn decrypt(data: &str, key: &str) -> Option<String> {
     
     let plaintext = cipher.decrypt(nonce, &bytes[12..]).ok()?;
     String::from_utf8(plaintext).ok()
-}
\ No newline at end of file
+}
+
+// 加密配置中的敏感信息，密钥由主密钥经 SHA-256 得到，主密钥的每一位都参与加密
+pub fn encrypt_secret(data: &str, master_key: &str) -> String {
+    let cipher = Aes256Gcm::new(&Sha256::digest(master_key.as_bytes()));
+    let mut nonce = [0u8; 12];
+    rand::rng().fill(&mut nonce);
+
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), data.as_bytes()).unwrap();
+    let mut result = nonce.to_vec();
+    result.extend_from_slice(&ciphertext);
+    STANDARD.encode(result)
+}
+
+pub fn decrypt_secret(data: &str, master_key: &str) -> Option<String> {
+    let bytes = STANDARD.decode(data).ok()?;
+    if bytes.len() <= 12 {
+        return None;
+    }
+
+    let cipher = Aes256Gcm::new(&Sha256::digest(master_key.as_bytes()));
+    let plaintext = cipher.decrypt(Nonce::from_slice(&bytes[0..12]), &bytes[12..]).ok()?;
+    String::from_utf8(plaintext).ok()
+}
@@ -4,11 +4,12 @@ use jsonwebtoken::{encode, decode, Header, Algorithm, Validation, EncodingKey, D
 use chrono::{Duration, Utc};
 use super::aes;
 use chrono::prelude::*;
-use crate::api::authorizations;
+use crate::api::{authorizations, repository::Transaction, tenant};
 use actix_web::{web, HttpRequest};
 use crate::AppState;
 use crate::lib::client::ClientInfo;
 use crate::lib::error;
+use crate::lib::settings::Settings;
 use crate::api::authorizations::AuthorizationInfo;
 
 const AES_KEY: &str = "e3Ui2PBkyFl5vUaO";
@@ -19,9 +20,9 @@ pub fn salt() -> uuid::Uuid {
 }
 
 pub fn crypt_password(password: &str, salt: &uuid::Uuid) -> String {
-    let pwd = format!("{}{}", password, salt.to_string());
+    let pwd = format!("{}{}", password, salt);
     let pwd = md5::compute(pwd);
-    let pwd = format!("{:?}{}{}", pwd, password, salt.to_string());
+    let pwd = format!("{:?}{}{}", pwd, password, salt);
     let pwd = Sha256::new().chain_update(pwd).finalize();
     format!("{:x}", pwd)
 }
@@ -33,6 +34,9 @@ pub struct Claims {
     pub exp: usize,
     pub jti: String,
     pub scopes: Vec<String>,
+    // 签发token的租户id
+    #[serde(default)]
+    pub tenant: i32,
 }
 
 #[derive(Debug)]
@@ -52,8 +56,8 @@ pub struct Auth {
     pub auth_id: i32,
 }
 
-pub async fn create_auth(user_id: i32, user_type: i16, client: &ClientInfo, state: &web::Data<AppState>) -> Result<Auth, error::Error> {
-    let access_token = create_access_token(user_id, user_type, &state.config);
+pub async fn create_auth(user_id: i32, user_type: i16, tenant_id: i32, client: &ClientInfo, tx: &mut dyn Transaction, state: &web::Data<AppState>) -> Result<Auth, error::Error> {
+    let access_token = create_access_token(user_id, user_type, tenant_id, &state.config.get());
 
     let refresh_token_id = uuid::Uuid::new_v4();
     let refresh_token_jti = uuid::Uuid::new_v4();
@@ -71,11 +75,12 @@ pub async fn create_auth(user_id: i32, user_type: i16, client: &ClientInfo, stat
         access_token_exp: Some(access_token.expire_time),
         access_token_iat: Some(access_token.create_time),
         is_enabled: Some(1),
+        tenant_id: Some(tenant_id),
     };
 
-    let authorization_id = authorizations::service::create_auth(&authorization, client, state).await?;
+    let authorization_id = authorizations::service::create_auth(&authorization, client, tx, state).await?;
 
-    let refresh_token = create_refresh_token(authorization_id, refresh_token_jti, &state.config);
+    let refresh_token = create_refresh_token(authorization_id, refresh_token_jti, tenant_id, &state.config.get());
     
     let auth = Auth {
         access_token,
@@ -87,8 +92,8 @@ pub async fn create_auth(user_id: i32, user_type: i16, client: &ClientInfo, stat
     Ok(auth)
 }
 
-pub fn create_access_token(user_id: i32, user_type: i16, config: &config::Config) -> Token {
-    let expire = config.get::<i64>("auth.access_token_expire").unwrap();
+pub fn create_access_token(user_id: i32, user_type: i16, tenant_id: i32, config: &Settings) -> Token {
+    let expire = config.auth.access_token_expire;
 
     let mut scopes = vec![String::from("ROLE_MEMBER")];
     if user_type == 10 {
@@ -106,6 +111,7 @@ pub fn create_access_token(user_id: i32, user_type: i16, config: &config::Config
         exp: expire_time.timestamp() as usize,
         jti: jti.to_string(),
         scopes,
+        tenant: tenant_id,
     };
 
     let token = encode(&Header::new(Algorithm::HS256), &claim, &EncodingKey::from_secret(JWT_KEY.as_ref())).unwrap();
@@ -119,8 +125,8 @@ pub fn create_access_token(user_id: i32, user_type: i16, config: &config::Config
     }
 }
 
-pub fn create_refresh_token(authorization_id: i32, refresh_token_jti: uuid::Uuid, config: &config::Config) -> Token {
-    let expire = config.get::<i64>("auth.refresh_token_expire").unwrap();
+pub fn create_refresh_token(authorization_id: i32, refresh_token_jti: uuid::Uuid, tenant_id: i32, config: &Settings) -> Token {
+    let expire = config.auth.refresh_token_expire;
     let scopes = vec![String::from("ROLE_REFRESH_TOKEN")];
 
     let create_time = Utc::now();
@@ -134,6 +140,7 @@ pub fn create_refresh_token(authorization_id: i32, refresh_token_jti: uuid::Uuid
         exp: expire_time.timestamp() as usize,
         jti: jti.to_string(),
         scopes,
+        tenant: tenant_id,
     };
 
     let token = encode(&Header::new(Algorithm::HS256), &claim, &EncodingKey::from_secret(JWT_KEY.as_ref())).unwrap();
@@ -148,7 +155,7 @@ pub fn create_refresh_token(authorization_id: i32, refresh_token_jti: uuid::Uuid
 }
 
 pub fn parse_token(token: &str) -> Result<Claims, error::Error> {
-    let token = match decode::<Claims>(&token, &DecodingKey::from_secret(JWT_KEY.as_ref()), &Validation::default()) {
+    let token = match decode::<Claims>(token, &DecodingKey::from_secret(JWT_KEY.as_ref()), &Validation::default()) {
         Ok(v) => v,
         Err(_) => return Err(error::new(100403, "Authentication failure", 401))
     };
@@ -179,6 +186,13 @@ pub async fn verify(permission: &str, req: &HttpRequest, state: &web::Data<AppSt
     };
 
     let claims = parse_token(&token)?;
+
+    // token只能在签发它的租户下使用
+    let tenant = tenant::service::resolve(req, state).await?;
+    if claims.tenant != tenant.id {
+        return Err(error::new(100403, "Authentication failure", 401));
+    }
+
     let user_id = match claims.sub.parse::<i32>() {
         Err(_) => return Err(error::new(100403, "Authentication failure", 401)),
         Ok(v) => v
@@ -187,18 +201,18 @@ pub async fn verify(permission: &str, req: &HttpRequest, state: &web::Data<AppSt
     let mut scopes: Vec<String> = Vec::new();
     let mut have_permission = false;
     for v in claims.scopes {
-        if permission.len() > 0 && v == permission {
+        if !permission.is_empty() && v == permission {
             have_permission = true;
         }
 
         scopes.push(v);
     }
 
-    if permission.len() > 0 && !have_permission {
+    if !permission.is_empty() && !have_permission {
         return Err(error::new(100404, "No permission", 403));
     }
 
-    match authorizations::service::is_in_black_list(&claims.jti, &state).await {
+    match authorizations::service::is_in_black_list(&claims.jti, state).await {
         Err(_) => return Err(error::new(100403, "Authentication failure", 401)),
         Ok(v) => {
             if v {
@@ -207,8 +221,11 @@ pub async fn verify(permission: &str, req: &HttpRequest, state: &web::Data<AppSt
         }
     };
 
+    crate::lib::log::set_user_id(req, user_id);
+
     let authorization_info = AuthorizationInfo {
         id: user_id,
+        tenant_id: tenant.id,
         scopes,
     };
 
@@ -0,0 +1,160 @@
+use mobc::{Connection, Pool};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+use super::{error, trace};
+use super::redis::RedisConnectionManager;
+use super::settings::CacheSettings;
+
+// 基于redis的缓存，值序列化为JSON保存，key自动添加命名空间前缀
+#[derive(Clone)]
+pub struct Cache {
+    pool: Pool<RedisConnectionManager>,
+    namespace: String,
+    timeout: Duration,
+}
+
+impl Cache {
+    pub fn new(pool: Pool<RedisConnectionManager>, settings: &CacheSettings) -> Self {
+        Self {
+            pool,
+            namespace: settings.namespace.clone(),
+            timeout: Duration::from_millis(settings.timeout_ms),
+        }
+    }
+
+    // 单次调用使用其他超时时间：state.cache.timeout(Duration::from_millis(200)).get(...)
+    pub fn timeout(&self, timeout: Duration) -> Self {
+        Self {
+            timeout,
+            ..self.clone()
+        }
+    }
+
+    pub fn key(&self, key: &str) -> String {
+        if self.namespace.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}:{}", self.namespace, key)
+        }
+    }
+
+    // 获取连接并执行命令，连接池错误、redis错误和超时都记录日志并返回500，lib::redis 中的命令也通过这里执行
+    pub(super) async fn run<T, F, Fut>(&self, command: &'static str, log: &slog::Logger, f: F) -> Result<T, error::Error>
+    where
+        F: FnOnce(Connection<RedisConnectionManager>) -> Fut,
+        Fut: Future<Output = Result<T, redis::RedisError>>,
+    {
+        let result = tokio::time::timeout(self.timeout, async {
+            let con = self.pool.get().await.map_err(|e| format!("redis pool: {}", e))?;
+            trace::redis(command, f(con)).await.map_err(|e| e.to_string())
+        }).await;
+
+        match result {
+            Ok(Ok(v)) => Ok(v),
+            Ok(Err(e)) => {
+                error!(log, "cache {}: {}", command, e);
+                Err(error::err500())
+            },
+            Err(_) => {
+                error!(log, "cache {}: timed out after {}ms", command, self.timeout.as_millis());
+                Err(error::err500())
+            }
+        }
+    }
+
+    // 反序列化失败(如结构变更后的旧数据)时视为未命中
+    fn decode<T: DeserializeOwned>(&self, key: &str, value: Option<String>, log: &slog::Logger) -> Option<T> {
+        let value = value?;
+        match serde_json::from_str(&value) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                warn!(log, "cache {} decode failed: {}", key, e);
+                None
+            }
+        }
+    }
+
+    fn encode<T: Serialize>(&self, value: &T, log: &slog::Logger) -> Result<String, error::Error> {
+        serde_json::to_string(value).map_err(|e| {
+            error!(log, "cache encode failed: {}", e);
+            error::err500()
+        })
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, key: &str, log: &slog::Logger) -> Result<Option<T>, error::Error> {
+        let key = self.key(key);
+        let value = self.run("GET", log, |mut con| {
+            let cmd = redis::cmd("GET").arg(&key).to_owned();
+            async move { cmd.query_async::<Option<String>>(&mut *con).await }
+        }).await?;
+
+        Ok(self.decode(&key, value, log))
+    }
+
+    // ttl为过期时间(秒)，不大于0时不过期
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T, ttl: i64, log: &slog::Logger) -> Result<(), error::Error> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(self.key(key)).arg(self.encode(value, log)?);
+        if ttl > 0 {
+            cmd.arg("EX").arg(ttl);
+        }
+
+        self.run("SET", log, |mut con| async move { cmd.query_async::<()>(&mut *con).await }).await
+    }
+
+    pub async fn del(&self, key: &str, log: &slog::Logger) -> Result<(), error::Error> {
+        let cmd = redis::cmd("DEL").arg(self.key(key)).to_owned();
+
+        self.run("DEL", log, |mut con| async move { cmd.query_async::<()>(&mut *con).await }).await
+    }
+
+    // 缓存命中时直接返回，否则调用f计算并写入缓存
+    pub async fn get_or_set<T, F, Fut>(&self, key: &str, ttl: i64, log: &slog::Logger, f: F) -> Result<T, error::Error>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, error::Error>>,
+    {
+        if let Some(v) = self.get(key, log).await? {
+            return Ok(v);
+        }
+
+        let value = f().await?;
+        self.set(key, &value, ttl, log).await?;
+
+        Ok(value)
+    }
+
+    // 批量获取，结果与keys一一对应
+    pub async fn mget<T: DeserializeOwned>(&self, keys: &[String], log: &slog::Logger) -> Result<Vec<Option<T>>, error::Error> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys: Vec<String> = keys.iter().map(|v| self.key(v)).collect();
+        let cmd = redis::cmd("MGET").arg(&keys).to_owned();
+        let values = self.run("MGET", log, |mut con| async move { cmd.query_async::<Vec<Option<String>>>(&mut *con).await }).await?;
+
+        Ok(keys.iter().zip(values).map(|(key, value)| self.decode(key, value, log)).collect())
+    }
+
+    // 批量设置，使用pipeline一次发送
+    pub async fn mset<T: Serialize>(&self, items: &[(String, T)], ttl: i64, log: &slog::Logger) -> Result<(), error::Error> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        for (key, value) in items {
+            let cmd = pipe.cmd("SET").arg(self.key(key)).arg(self.encode(value, log)?);
+            if ttl > 0 {
+                cmd.arg("EX").arg(ttl);
+            }
+            cmd.ignore();
+        }
+
+        self.run("PIPELINE", log, |mut con| async move { pipe.query_async::<()>(&mut *con).await }).await
+    }
+}
\ No newline at end of file
@@ -12,12 +12,11 @@ pub fn get_client_info(state: &web::Data<AppState>, req: &HttpRequest, conn: &Co
     let mut user_agent = String::from("");
 
     
-    let is_behind_proxy = state.config.get::<bool>("app.behind_proxy").unwrap_or(false);
-    if is_behind_proxy {
+    if state.config.get().app.behind_proxy {
         if let Some(val) = conn.realip_remote_addr() {
             let split = val.split(":");
             let vec: Vec<&str> = split.collect();
-            if vec.len() >0 {
+            if !vec.is_empty() {
                 ip = vec[0].to_string();
             }
         }
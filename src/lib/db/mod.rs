@@ -1 +1,225 @@
-pub mod pg;
\ No newline at end of file
+// 数据库在编译时通过 feature 选择，默认 postgres，mysql 使用 --no-default-features --features mysql
+#[cfg(all(feature = "postgres", feature = "mysql"))]
+compile_error!("feature \"postgres\" 和 \"mysql\" 不能同时启用");
+#[cfg(not(any(feature = "postgres", feature = "mysql")))]
+compile_error!("需要启用 feature \"postgres\" 或 \"mysql\"");
+
+#[cfg(feature = "postgres")]
+pub mod pg;
+#[cfg(feature = "mysql")]
+pub mod mysql;
+pub mod replica;
+
+#[cfg(feature = "postgres")]
+pub use pg::{conn, conn_lazy, migrate};
+#[cfg(feature = "mysql")]
+pub use mysql::{conn, conn_lazy, migrate};
+
+use sqlx::FromRow;
+use std::borrow::Cow;
+use std::time::Instant;
+use super::metrics::Metrics;
+
+#[cfg(feature = "postgres")]
+pub type Db = sqlx::Postgres;
+#[cfg(feature = "mysql")]
+pub type Db = sqlx::MySql;
+
+pub type Pool = sqlx::Pool<Db>;
+// 事务，可以作为 &mut Connection 传给 model 函数，drop 时未提交自动回滚
+pub type Transaction = sqlx::Transaction<'static, Db>;
+pub type Connection = <Db as sqlx::Database>::Connection;
+pub type Row = <Db as sqlx::Database>::Row;
+pub type Query<'q> = sqlx::query::Query<'q, Db, <Db as sqlx::Database>::Arguments<'q>>;
+
+// 数据库名称和配置文件中的section，用于健康检查和监控指标
+#[cfg(feature = "postgres")]
+pub const NAME: &str = "postgres";
+#[cfg(feature = "postgres")]
+pub const SECTION: &str = "pg";
+#[cfg(feature = "mysql")]
+pub const NAME: &str = "mysql";
+#[cfg(feature = "mysql")]
+pub const SECTION: &str = "mysql";
+// 链路追踪中的 db.system.name，使用 OpenTelemetry 约定的名称
+#[cfg(feature = "postgres")]
+pub const SYSTEM_NAME: &str = "postgresql";
+#[cfg(feature = "mysql")]
+pub const SYSTEM_NAME: &str = NAME;
+
+// 从连接池获取连接，等待时间记录到监控指标
+pub async fn acquire(pool: &Pool, metrics: &Metrics) -> Result<sqlx::pool::PoolConnection<Db>, sqlx::Error> {
+    let start = Instant::now();
+    let result = pool.acquire().await;
+    metrics.db_acquire(start.elapsed());
+
+    result
+}
+
+// 开始事务，获取连接的等待时间记录到监控指标
+pub async fn begin(pool: &Pool, metrics: &Metrics) -> Result<Transaction, sqlx::Error> {
+    let start = Instant::now();
+    let result = pool.begin().await;
+    metrics.db_acquire(start.elapsed());
+
+    result
+}
+
+// 是否违反指定的唯一索引，postgres 从错误中获取索引名称，mysql 的错误信息为 Duplicate entry '...' for key 'table.index'
+pub fn is_unique_violation(e: &sqlx::Error, index: &str) -> bool {
+    match e.as_database_error() {
+        Some(v) if v.is_unique_violation() => v.constraint() == Some(index) || v.message().contains(index),
+        _ => false,
+    }
+}
+
+// model 中的sql统一使用 $1, $2 ... 作为参数占位符，mysql 替换为 ?
+// mysql 的参数按出现顺序绑定，因此每个占位符只能出现一次且按序号递增
+// debug 构建中检查占位符顺序，postgres 下不需要替换也同样检查，避免只在 mysql 下出错
+#[cfg(feature = "postgres")]
+pub fn sql(sql: &str) -> Cow<'_, str> {
+    debug_assert!(placeholders_in_order(sql), "sql 占位符需要从 $1 开始按顺序出现且不能重复: {}", sql);
+
+    Cow::Borrowed(sql)
+}
+
+#[cfg(feature = "mysql")]
+pub fn sql(sql: &str) -> Cow<'_, str> {
+    debug_assert!(placeholders_in_order(sql), "sql 占位符需要从 $1 开始按顺序出现且不能重复: {}", sql);
+    if !sql.contains('$') {
+        return Cow::Borrowed(sql);
+    }
+
+    let mut result = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' && chars.peek().is_some_and(|v| v.is_ascii_digit()) {
+            while chars.peek().is_some_and(|v| v.is_ascii_digit()) {
+                chars.next();
+            }
+            result.push('?');
+        } else {
+            result.push(c);
+        }
+    }
+
+    Cow::Owned(result)
+}
+
+// 占位符是否为 $1, $2 ... 按出现顺序依次递增
+fn placeholders_in_order(sql: &str) -> bool {
+    let mut expected = 1;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' || !chars.peek().is_some_and(|v| v.is_ascii_digit()) {
+            continue;
+        }
+
+        let mut n = 0u32;
+        while let Some(d) = chars.peek().and_then(|v| v.to_digit(10)) {
+            n = n * 10 + d;
+            chars.next();
+        }
+        if n != expected {
+            return false;
+        }
+        expected += 1;
+    }
+
+    true
+}
+
+// LIKE 的转义字符，放在 LIKE $n 之后，与 like() 配合使用
+// mysql 字符串中的反斜杠本身需要转义
+#[cfg(feature = "postgres")]
+pub const LIKE_ESCAPE: &str = r"ESCAPE '\'";
+#[cfg(feature = "mysql")]
+pub const LIKE_ESCAPE: &str = r"ESCAPE '\\'";
+
+// 包含匹配的 LIKE 参数，转义输入中的 %、_ 和 \，避免被当作通配符
+pub fn like(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('%');
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result.push('%');
+
+    result
+}
+
+// INSERT/UPDATE 语句返回写入后的行，postgres 添加 RETURNING *，mysql 不支持 RETURNING，与 fetch_returning 配合使用
+#[cfg(feature = "postgres")]
+pub fn returning(sql: &str) -> String {
+    format!("{} RETURNING *", sql)
+}
+
+#[cfg(feature = "mysql")]
+pub fn returning(sql: &str) -> String {
+    self::sql(sql).into_owned()
+}
+
+// 执行 returning() 生成的语句并返回写入后的行，使用同一个连接以便在事务中调用
+// mysql 执行后按id重新查询，id 为 None 时是 INSERT，使用自增id；UPDATE 未匹配到行时返回 RowNotFound，与 fetch_one 一致
+#[cfg(feature = "postgres")]
+pub async fn fetch_returning<T>(query: Query<'_>, _table: &str, _id: Option<i32>, conn: &mut Connection) -> Result<T, sqlx::Error>
+where
+    T: for<'r> FromRow<'r, Row>,
+{
+    let row = query.fetch_one(conn).await?;
+
+    T::from_row(&row)
+}
+
+#[cfg(feature = "mysql")]
+pub async fn fetch_returning<T>(query: Query<'_>, table: &str, id: Option<i32>, conn: &mut Connection) -> Result<T, sqlx::Error>
+where
+    T: for<'r> FromRow<'r, Row> + Send + Unpin,
+{
+    let r = query.execute(&mut *conn).await?;
+    let id = match id {
+        Some(_) if r.rows_affected() == 0 => return Err(sqlx::Error::RowNotFound),
+        Some(v) => v as u64,
+        None => r.last_insert_id(),
+    };
+
+    sqlx::query_as::<_, T>(&format!("SELECT * FROM {} WHERE id = ?", table))
+        .bind(id)
+        .fetch_one(conn)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders() {
+        assert!(placeholders_in_order("SELECT * FROM users"));
+        assert!(placeholders_in_order("UPDATE users SET a = $1, b = $2 WHERE id = $3 AND tenant_id = $4"));
+        assert!(placeholders_in_order("SELECT * FROM users WHERE id = $1 LIMIT $2 OFFSET $3"));
+        assert!(placeholders_in_order("SELECT * FROM t WHERE a = $1 AND b = $2 AND c = $3 AND d = $4 AND e = $5 AND f = $6 AND g = $7 AND h = $8 AND i = $9 AND j = $10"));
+
+        // 乱序、重复和不从1开始的占位符在 mysql 下会绑定到错误的参数
+        assert!(!placeholders_in_order("UPDATE users SET a = $2 WHERE id = $1"));
+        assert!(!placeholders_in_order("SELECT * FROM users WHERE id = $1 OR parent_id = $1"));
+        assert!(!placeholders_in_order("SELECT * FROM users WHERE id = $2"));
+    }
+
+    #[cfg(feature = "mysql")]
+    #[test]
+    fn sql_mysql() {
+        assert_eq!(sql("SELECT * FROM users WHERE id = $1 AND tenant_id = $2"), "SELECT * FROM users WHERE id = ? AND tenant_id = ?");
+        assert_eq!(sql("SELECT * FROM t WHERE a = $1 AND b = $2 AND c = $3 AND d = $4 AND e = $5 AND f = $6 AND g = $7 AND h = $8 AND i = $9 AND j = $10"),
+            "SELECT * FROM t WHERE a = ? AND b = ? AND c = ? AND d = ? AND e = ? AND f = ? AND g = ? AND h = ? AND i = ? AND j = ?");
+    }
+
+    #[test]
+    fn like_escape() {
+        assert_eq!(like("ab"), "%ab%");
+        assert_eq!(like("a%b_c\\"), "%a\\%b\\_c\\\\%");
+    }
+}
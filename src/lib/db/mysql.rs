@@ -0,0 +1,42 @@
+use sqlx::mysql::MySqlPoolOptions;
+use std::time::Duration;
+use crate::lib::settings::DbSettings;
+use super::Pool;
+
+fn options(settings: &DbSettings) -> MySqlPoolOptions {
+    MySqlPoolOptions::new()
+        .max_connections(settings.max)
+        .idle_timeout(Duration::new(settings.idle_timeout, 0))
+        .acquire_timeout(Duration::new(settings.connect_timeout, 0))
+}
+
+fn url(settings: &DbSettings) -> String {
+    format!("mysql://{}:{}@{}:{}/{}", settings.user, settings.password.expose(), settings.host, settings.port, settings.dbname)
+}
+
+pub async fn conn(settings: &DbSettings) -> Pool {
+    let pool = options(settings)
+        .connect(&url(settings)[..])
+        .await
+        .unwrap();
+    
+    pool
+}
+
+// 第一次使用时才建立连接，用于不需要数据库的内存存储模式
+pub fn conn_lazy(settings: &DbSettings) -> Pool {
+    options(settings).connect_lazy(&url(settings)[..]).unwrap()
+}
+
+// 执行 sql/migrations/mysql 下尚未执行的迁移，迁移文件在编译时嵌入程序
+pub async fn migrate(pool: &Pool, log: &slog::Logger) -> Result<(), sqlx::migrate::MigrateError> {
+    let migrator = sqlx::migrate!("./sql/migrations/mysql");
+    migrator.run(pool).await?;
+
+    for v in migrator.iter() {
+        debug!(log, "migration {} {}", v.version, v.description);
+    }
+    info!(log, "database migrated, latest version {}", migrator.iter().map(|v| v.version).max().unwrap_or_default());
+
+    Ok(())
+}
@@ -1,23 +1,42 @@
 use sqlx::postgres::PgPoolOptions;
 use std::time::Duration;
+use crate::lib::settings::DbSettings;
+use super::Pool;
 
-pub async fn conn(settings: &config::Config) -> sqlx::Pool<sqlx::Postgres> {
-    let user = settings.get::<String>("pg.user").unwrap();
-    let password = settings.get::<String>("pg.password").unwrap();
-    let host = settings.get::<String>("pg.host").unwrap();
-    let port = settings.get::<String>("pg.port").unwrap();
-    let dbname = settings.get::<String>("pg.dbname").unwrap();
-    let connect_timeout = settings.get::<u64>("pg.connect_timeout").unwrap();
-    let idle_timeout = settings.get::<u64>("pg.idle_timeout").unwrap();
-    let max = settings.get::<u32>("pg.max").unwrap();
+fn options(settings: &DbSettings) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(settings.max)
+        .idle_timeout(Duration::new(settings.idle_timeout, 0))
+        .acquire_timeout(Duration::new(settings.connect_timeout, 0))
+}
 
-    let pool = PgPoolOptions::new()
-        .max_connections(max)
-        .idle_timeout(Duration::new(idle_timeout, 0))
-        .acquire_timeout(Duration::new(connect_timeout, 0))
-        .connect(&format!("postgres://{}:{}@{}:{}/{}", user, password, host, port, dbname)[..])
+fn url(settings: &DbSettings) -> String {
+    format!("postgres://{}:{}@{}:{}/{}", settings.user, settings.password.expose(), settings.host, settings.port, settings.dbname)
+}
+
+pub async fn conn(settings: &DbSettings) -> Pool {
+    let pool = options(settings)
+        .connect(&url(settings)[..])
         .await
         .unwrap();
     
     pool
-}
\ No newline at end of file
+}
+
+// 第一次使用时才建立连接，用于不需要数据库的内存存储模式
+pub fn conn_lazy(settings: &DbSettings) -> Pool {
+    options(settings).connect_lazy(&url(settings)[..]).unwrap()
+}
+
+// 执行 sql/migrations/postgres 下尚未执行的迁移，迁移文件在编译时嵌入程序
+pub async fn migrate(pool: &Pool, log: &slog::Logger) -> Result<(), sqlx::migrate::MigrateError> {
+    let migrator = sqlx::migrate!("./sql/migrations/postgres");
+    migrator.run(pool).await?;
+
+    for v in migrator.iter() {
+        debug!(log, "migration {} {}", v.version, v.description);
+    }
+    info!(log, "database migrated, latest version {}", migrator.iter().map(|v| v.version).max().unwrap_or_default());
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use crate::lib::settings::DbSettings;
+use super::Pool;
+
+// 只读副本，读取时按顺序轮流使用，没有配置副本时读写都使用主库
+// read_your_writes_ms 大于0时记录写入的行，该时间内读取这些行使用主库
+// 行用 (表名, id) 表示
+pub struct Replicas {
+    primary: Pool,
+    pools: Vec<Pool>,
+    next: AtomicUsize,
+    window: Duration,
+    writes: Mutex<HashMap<(&'static str, i32), Instant>>,
+}
+
+impl Replicas {
+    pub fn new(primary: Pool, pools: Vec<Pool>, read_your_writes_ms: u64) -> Self {
+        Self {
+            primary,
+            pools,
+            next: AtomicUsize::new(0),
+            window: Duration::from_millis(read_your_writes_ms),
+            writes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // 读取使用的连接池，keys 中的行最近写入过时使用主库
+    pub fn read(&self, keys: &[(&'static str, i32)]) -> &Pool {
+        if self.pools.is_empty() || self.stale(keys) {
+            return &self.primary;
+        }
+
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.pools.len();
+        &self.pools[i]
+    }
+
+    // keys 中的行是否在 read_your_writes_ms 内写入过，副本上的数据可能还没有同步
+    fn stale(&self, keys: &[(&'static str, i32)]) -> bool {
+        if self.pools.is_empty() || self.window.is_zero() || keys.is_empty() {
+            return false;
+        }
+
+        let writes = self.writes.lock().unwrap();
+        keys.iter().any(|k| writes.get(k).is_some_and(|t| t.elapsed() < self.window))
+    }
+
+    // 记录写入的行，同时清理已过期的记录
+    pub fn written(&self, table: &'static str, id: i32) {
+        if self.pools.is_empty() || self.window.is_zero() {
+            return;
+        }
+
+        let mut writes = self.writes.lock().unwrap();
+        writes.retain(|_, t| t.elapsed() < self.window);
+        writes.insert((table, id), Instant::now());
+    }
+}
+
+// 连接只读副本，其他连接参数与主库相同
+pub async fn conn(settings: &DbSettings) -> Vec<Pool> {
+    let mut pools = Vec::with_capacity(settings.replicas.len());
+    for addr in &settings.replicas {
+        // 地址已在启动时校验
+        if let Some(v) = settings.replica(addr) {
+            pools.push(super::conn(&v).await);
+        }
+    }
+
+    pools
+}
@@ -19,6 +19,8 @@ impl Display for Error {
     }
 }
 
+impl std::error::Error for Error {}
+
 impl ResponseError for Error {
     fn error_response(&self) -> HttpResponse {
         let err_json = json!({ "errcode": self.errcode, "errmsg": self.errmsg });
@@ -0,0 +1,53 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use super::db;
+use super::redis::RedisConnectionManager;
+
+#[derive(Debug, Serialize)]
+pub struct DependencyStatus {
+    pub status: &'static str,
+    pub latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DependencyStatus {
+    pub fn is_up(&self) -> bool {
+        self.status == "up"
+    }
+
+    fn new(start: Instant, result: Result<(), String>) -> Self {
+        DependencyStatus {
+            status: if result.is_ok() { "up" } else { "down" },
+            latency_ms: start.elapsed().as_millis(),
+            error: result.err(),
+        }
+    }
+}
+
+// 检查数据库连接，超时视为不可用
+pub async fn check_db(db: &db::Pool, timeout: Duration) -> DependencyStatus {
+    let start = Instant::now();
+    let result = match actix_web::rt::time::timeout(timeout, sqlx::query("SELECT 1").execute(db)).await {
+        Err(_) => Err(String::from("timeout")),
+        Ok(Err(e)) => Err(e.to_string()),
+        Ok(Ok(_)) => Ok(()),
+    };
+
+    DependencyStatus::new(start, result)
+}
+
+// 检查redis连接，从连接池获取连接并执行PING
+pub async fn check_redis(pool: &mobc::Pool<RedisConnectionManager>, timeout: Duration) -> DependencyStatus {
+    let start = Instant::now();
+    let ping = async {
+        let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+        redis::cmd("PING").query_async::<()>(&mut *conn).await.map_err(|e| e.to_string())
+    };
+    let result = match actix_web::rt::time::timeout(timeout, ping).await {
+        Err(_) => Err(String::from("timeout")),
+        Ok(v) => v,
+    };
+
+    DependencyStatus::new(start, result)
+}
\ No newline at end of file
@@ -0,0 +1,185 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpMessage, HttpRequest};
+use slog::Logger;
+use slog::Drain;
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::time::Instant;
+use crate::AppState;
+use super::settings::{parse_level, LogSettings};
+
+mod rotate;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// 最低日志级别，可以在运行中修改，默认为 slog::Level::Info.as_usize()
+static LEVEL: AtomicUsize = AtomicUsize::new(4);
+
+pub fn set_level(level: slog::Level) {
+    LEVEL.store(level.as_usize(), Ordering::Relaxed);
+}
+
+fn level() -> slog::Level {
+    slog::Level::from_usize(LEVEL.load(Ordering::Relaxed)).unwrap_or(slog::Level::Info)
+}
+
+type BoxDrain = Box<dyn Drain<Ok = (), Err = slog::Never> + Send>;
+
+// 多个输出，每条日志依次写入
+struct Outputs(Vec<BoxDrain>);
+
+impl Drain for Outputs {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(&self, record: &slog::Record, values: &slog::OwnedKVList) -> Result<(), slog::Never> {
+        for v in &self.0 {
+            v.log(record, values)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn json_output<W: Write + Send + 'static>(w: W, level: slog::Level) -> BoxDrain {
+    let drain = slog_json::Json::new(w)
+        .set_pretty(false)
+        .set_newlines(true)
+        .build()
+        .fuse();
+
+    Box::new(drain.filter_level(level).ignore_res())
+}
+
+fn text_output<D: slog_term::Decorator + Send + 'static>(decorator: D, level: slog::Level) -> BoxDrain {
+    let drain = slog_term::FullFormat::new(decorator).build().fuse();
+
+    Box::new(drain.filter_level(level).ignore_res())
+}
+
+pub fn get_logger(settings: &LogSettings) -> Result<Logger, String> {
+    let mut outputs: Vec<BoxDrain> = Vec::new();
+
+    if settings.terminal.enabled {
+        let level = parse_level(&settings.terminal.level).unwrap_or(slog::Level::Trace);
+        if settings.terminal.format == "json" {
+            outputs.push(json_output(std::io::stdout(), level));
+        } else {
+            outputs.push(text_output(slog_term::TermDecorator::new().build(), level));
+        }
+    }
+
+    if settings.file.enabled {
+        let file = match rotate::RotatingFile::open(&settings.file) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("打开日志文件{}失败: {}", settings.file.path, e)),
+        };
+        let level = parse_level(&settings.file.level).unwrap_or(slog::Level::Trace);
+        if settings.file.format == "text" {
+            outputs.push(text_output(slog_term::PlainDecorator::new(file), level));
+        } else {
+            outputs.push(json_output(file, level));
+        }
+    }
+
+    let drain = slog_async::Async::new(Outputs(outputs)).build().fuse();
+    let drain = slog::Filter::new(drain, |record: &slog::Record| record.level().is_at_least(level())).fuse();
+
+    let logger = slog::Logger::root(
+        drain,
+        o!(
+            "msg" => slog::PushFnValue(move |record, ser| {
+                ser.emit(record.msg())
+            }),
+            "time" => slog::PushFnValue(move |_ : &slog::Record, ser| {
+                ser.emit(chrono::Utc::now().to_rfc3339())
+            }),
+            "level" => slog::FnValue(move |record| {
+                record.level().as_str()
+            }),
+            "file" => slog::FnValue(move |record| {
+                format!("{}:{}", record.file(), record.line())
+            }),
+        ),
+    );
+
+    Ok(logger)
+}
+
+// 请求上下文，保存在请求的 extensions 中
+#[derive(Clone)]
+pub struct RequestContext {
+    pub request_id: String,
+    user_id: Arc<AtomicI32>,
+}
+
+// 记录当前请求的用户，之后的日志都会带上 user_id
+pub fn set_user_id(req: &HttpRequest, user_id: i32) {
+    if let Some(ctx) = req.extensions().get::<RequestContext>() {
+        ctx.user_id.store(user_id, Ordering::Relaxed);
+    }
+}
+
+// 上游传入的请求id只接受字母数字和 - _ .，否则重新生成
+fn valid_request_id(v: &str) -> bool {
+    !v.is_empty() && v.len() <= 128 && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+// 为每个请求分配或沿用 X-Request-Id，并用带有 request_id, method, path, user_id 的子logger
+// 替换本次请求的 AppState.log，handler、service、model 中使用 state.log 记录的日志都会带上这些字段
+// 请求结束时输出一条访问日志
+pub async fn middleware(mut req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let start = Instant::now();
+    let request_id = match req.headers().get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(v) if valid_request_id(v) => v.to_string(),
+        _ => uuid::Uuid::new_v4().to_string(),
+    };
+
+    let ctx = RequestContext {
+        request_id: request_id.clone(),
+        user_id: Arc::new(AtomicI32::new(0)),
+    };
+    req.extensions_mut().insert(ctx.clone());
+
+    let state = match req.app_data::<web::Data<AppState>>() {
+        None => return next.call(req).await,
+        Some(v) => v.clone(),
+    };
+    let user_id = ctx.user_id.clone();
+    let logger = state.log.new(o!(
+        "request_id" => request_id.clone(),
+        "method" => req.method().to_string(),
+        "path" => req.path().to_string(),
+        "user_id" => slog::FnValue(move |_| user_id.load(Ordering::Relaxed)),
+    ));
+
+    let mut request_state = (**state).clone();
+    request_state.log = logger.clone();
+    let mut data = actix_web::dev::Extensions::new();
+    data.insert(web::Data::new(request_state));
+    req.add_data_container(Rc::new(data));
+
+    let ip = if state.config.get().app.behind_proxy {
+        req.connection_info().realip_remote_addr().unwrap_or_default().to_string()
+    } else {
+        req.peer_addr().map(|v| v.ip().to_string()).unwrap_or_default()
+    };
+    let mut res = next.call(req).await?;
+
+    if let Ok(v) = HeaderValue::from_str(&request_id) {
+        res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), v);
+    }
+    info!(logger, "access";
+        "type" => "access",
+        "status" => res.status().as_u16(),
+        "latency_ms" => start.elapsed().as_secs_f64() * 1000.0,
+        "ip" => ip,
+    );
+
+    Ok(res)
+}
\ No newline at end of file
@@ -0,0 +1,199 @@
+use chrono::prelude::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::time::SystemTime;
+use crate::lib::settings::LogFileSettings;
+
+// 按大小或时间切分的日志文件，历史文件按其中日志所属的时间段命名：
+// daily 为 app.log.20240101，hourly 为 app.log.2024010113，只按大小切分时为文件开始写入的时间 app.log.20240101-080000
+// 同一时间段切分出多个文件时依次添加 -1、-2 后缀，compress 开启时压缩为 .gz
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    rotate: String,
+    period: String,
+    // 当前文件开始写入的时间
+    start: DateTime<Local>,
+    // 切分出的历史文件交给后台线程依次压缩和清理
+    worker: Sender<PathBuf>,
+}
+
+impl RotatingFile {
+    pub fn open(settings: &LogFileSettings) -> io::Result<Self> {
+        let path = PathBuf::from(&settings.path);
+        if let Some(dir) = path.parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let modified: DateTime<Local> = match metadata.modified() {
+            Ok(v) => v.into(),
+            Err(_) => Local::now(),
+        };
+        let start: DateTime<Local> = match metadata.created() {
+            Ok(v) if metadata.len() > 0 => v.into(),
+            _ => Local::now(),
+        };
+
+        Ok(RotatingFile {
+            period: period(&settings.rotate, modified),
+            start,
+            worker: worker(path.clone(), settings.compress, settings.max_files)?,
+            path,
+            file,
+            size: metadata.len(),
+            max_size: settings.max_size_mb * 1024 * 1024,
+            rotate: settings.rotate.clone(),
+        })
+    }
+
+    fn should_rotate(&self, len: u64, now: DateTime<Local>) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        if self.max_size > 0 && self.size + len > self.max_size {
+            return true;
+        }
+
+        self.rotate != "none" && period(&self.rotate, now) != self.period
+    }
+
+    fn rotate(&mut self, now: DateTime<Local>) -> io::Result<()> {
+        self.file.flush()?;
+
+        // 使用文件中日志所属的时间段命名，而不是切分的时间
+        let name = if self.period.is_empty() {
+            self.start.format("%Y%m%d-%H%M%S").to_string()
+        } else {
+            self.period.clone()
+        };
+        let mut rotated = PathBuf::from(format!("{}.{}", self.path.display(), name));
+        let mut i = 1;
+        while rotated.exists() || gz_path(&rotated).exists() {
+            rotated = PathBuf::from(format!("{}.{}-{}", self.path.display(), name, i));
+            i += 1;
+        }
+        fs::rename(&self.path, &rotated)?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.period = period(&self.rotate, now);
+        self.start = now;
+
+        if self.worker.send(rotated).is_err() {
+            eprintln!("log rotate worker stopped");
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = Local::now();
+        if self.should_rotate(buf.len() as u64, now) {
+            if let Err(e) = self.rotate(now) {
+                eprintln!("rotate log {} failed: {}", self.path.display(), e);
+            }
+        }
+
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn period(rotate: &str, time: DateTime<Local>) -> String {
+    match rotate {
+        "hourly" => time.format("%Y%m%d%H").to_string(),
+        "daily" => time.format("%Y%m%d").to_string(),
+        _ => String::new(),
+    }
+}
+
+// 后台线程，按切分顺序依次压缩历史文件并清理，同一时间只处理一个文件
+// RotatingFile 释放后通道关闭，线程处理完剩余文件后退出
+fn worker(path: PathBuf, compress: bool, max_files: usize) -> io::Result<Sender<PathBuf>> {
+    let (sender, receiver) = mpsc::channel::<PathBuf>();
+    std::thread::Builder::new().name(String::from("log-rotate")).spawn(move || {
+        for rotated in receiver {
+            if compress {
+                if let Err(e) = gzip(&rotated) {
+                    eprintln!("compress log {} failed: {}", rotated.display(), e);
+                }
+            }
+            if let Err(e) = prune(&path, max_files) {
+                eprintln!("prune logs failed: {}", e);
+            }
+        }
+    })?;
+
+    Ok(sender)
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.gz", path.display()))
+}
+
+// 先写入临时文件，完成后再改名，压缩中的文件不会被当作历史文件
+fn gzip(path: &Path) -> io::Result<()> {
+    let gz = gz_path(path);
+    let tmp = PathBuf::from(format!("{}.tmp", gz.display()));
+
+    let mut input = File::open(path)?;
+    let output = File::create(&tmp)?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&tmp, &gz)?;
+
+    fs::remove_file(path)
+}
+
+// 只保留最新的 max_files 个历史文件
+fn prune(path: &Path, max_files: usize) -> io::Result<()> {
+    if max_files == 0 {
+        return Ok(());
+    }
+
+    let dir = match path.parent() {
+        Some(v) if !v.as_os_str().is_empty() => v.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let prefix = match path.file_name().and_then(|v| v.to_str()) {
+        Some(v) => format!("{}.", v),
+        None => return Ok(()),
+    };
+
+    // 跳过压缩中的临时文件
+    let mut files: Vec<(SystemTime, PathBuf)> = fs::read_dir(&dir)?
+        .filter_map(|v| v.ok())
+        .filter(|v| v.file_name().to_str().is_some_and(|name| name.starts_with(&prefix) && !name.ends_with(".tmp")))
+        .map(|v| (v.metadata().and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH), v.path()))
+        .collect();
+    if files.len() <= max_files {
+        return Ok(());
+    }
+
+    // 同一时间段的文件名带有序号后缀，不能按文件名排序，按最后写入时间排序
+    files.sort();
+    for (_, v) in &files[..files.len() - max_files] {
+        fs::remove_file(v)?;
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,133 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web;
+use prometheus::{Encoder, Gauge, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+use std::time::{Duration, Instant};
+use crate::AppState;
+use crate::api::authorizations::LogType;
+use super::db;
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    auth_events: IntCounterVec,
+    db_pool: IntGaugeVec,
+    db_acquire: Histogram,
+    redis_pool: IntGaugeVec,
+    redis_wait_count: IntGauge,
+    redis_wait_seconds: Gauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP请求数"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP请求耗时"),
+            &["method", "route"],
+        ).unwrap();
+        let auth_events = IntCounterVec::new(
+            Opts::new("auth_events_total", "授权日志数，按 authorizations_logs.log_type 统计"),
+            &["log_type", "outcome"],
+        ).unwrap();
+        let db_pool = IntGaugeVec::new(
+            Opts::new(format!("{}_pool_connections", db::SECTION), format!("{}连接池连接数", db::NAME)),
+            &["state"],
+        ).unwrap();
+        let db_acquire = Histogram::with_opts(
+            HistogramOpts::new(format!("{}_pool_acquire_duration_seconds", db::SECTION), format!("{}连接池获取连接的等待时间", db::NAME))
+                .buckets(vec![0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0]),
+        ).unwrap();
+        let redis_pool = IntGaugeVec::new(
+            Opts::new("redis_pool_connections", "redis连接池连接数"),
+            &["state"],
+        ).unwrap();
+        let redis_wait_count = IntGauge::new("redis_pool_wait_count", "redis连接池等待获取连接的累计次数").unwrap();
+        let redis_wait_seconds = Gauge::new("redis_pool_wait_seconds", "redis连接池等待获取连接的累计时间").unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_duration.clone())).unwrap();
+        registry.register(Box::new(auth_events.clone())).unwrap();
+        registry.register(Box::new(db_pool.clone())).unwrap();
+        registry.register(Box::new(db_acquire.clone())).unwrap();
+        registry.register(Box::new(redis_pool.clone())).unwrap();
+        registry.register(Box::new(redis_wait_count.clone())).unwrap();
+        registry.register(Box::new(redis_wait_seconds.clone())).unwrap();
+
+        Metrics {
+            registry,
+            http_requests,
+            http_duration,
+            auth_events,
+            db_pool,
+            db_acquire,
+            redis_pool,
+            redis_wait_count,
+            redis_wait_seconds,
+        }
+    }
+
+    pub fn auth_event(&self, log_type: LogType) {
+        let outcome = if log_type.code() < 1000 { "success" } else { "failure" };
+        self.auth_events.with_label_values(&[log_type.name(), outcome]).inc();
+    }
+
+    // 记录从数据库连接池获取连接的等待时间，由 db::acquire 和 db::begin 调用
+    pub fn db_acquire(&self, duration: Duration) {
+        self.db_acquire.observe(duration.as_secs_f64());
+    }
+
+    // 输出prometheus文本格式，连接池状态在采集时读取
+    pub async fn render(&self, state: &web::Data<AppState>) -> String {
+        let db_size = state.db.size() as i64;
+        let db_idle = state.db.num_idle() as i64;
+        self.db_pool.with_label_values(&["max"]).set(state.config.get().db().max as i64);
+        self.db_pool.with_label_values(&["open"]).set(db_size);
+        self.db_pool.with_label_values(&["idle"]).set(db_idle);
+        self.db_pool.with_label_values(&["in_use"]).set(db_size - db_idle);
+
+        let redis = state.redis.state().await;
+        self.redis_pool.with_label_values(&["max"]).set(redis.max_open as i64);
+        self.redis_pool.with_label_values(&["open"]).set(redis.connections as i64);
+        self.redis_pool.with_label_values(&["idle"]).set(redis.idle as i64);
+        self.redis_pool.with_label_values(&["in_use"]).set(redis.in_use as i64);
+        self.redis_wait_count.set(redis.wait_count as i64);
+        self.redis_wait_seconds.set(redis.wait_duration.as_secs_f64());
+
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!(state.log, "encode metrics failed: {}", e);
+        }
+
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 统计每个请求的路由、状态码和耗时，路由使用注册时的路径模板，未匹配的请求统一记为 unmatched
+pub async fn middleware(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let metrics = req.app_data::<web::Data<AppState>>().map(|v| v.metrics.clone());
+
+    let res = next.call(req).await?;
+
+    if let Some(metrics) = metrics {
+        let route = res.request().match_pattern().unwrap_or_else(|| String::from("unmatched"));
+        let status = res.status().as_u16().to_string();
+        metrics.http_requests.with_label_values(&[&method, &route, &status]).inc();
+        metrics.http_duration.with_label_values(&[&method, &route]).observe(start.elapsed().as_secs_f64());
+    }
+
+    Ok(res)
+}
\ No newline at end of file
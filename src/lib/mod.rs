@@ -5,4 +5,12 @@ pub mod redis;
 pub mod validator;
 pub mod client;
 pub mod auth;
-pub mod aes;
\ No newline at end of file
+pub mod aes;
+pub mod sms;
+pub mod storage;
+pub mod mail;
+pub mod settings;
+pub mod health;
+pub mod metrics;
+pub mod trace;
+pub mod cache;
\ No newline at end of file
@@ -4,7 +4,9 @@ use mobc::Manager;
 use redis::aio::MultiplexedConnection;
 use redis::Client;
 use std::time::Duration;
+use super::cache::Cache;
 use super::error;
+use super::settings::RedisSettings;
 
 pub struct RedisConnectionManager {
     client: Client,
@@ -32,185 +34,103 @@ impl Manager for RedisConnectionManager {
     }
 }
 
-pub async fn conn(settings: &config::Config) -> Pool<RedisConnectionManager> {
-    let host = settings.get::<String>("redis.host").unwrap();
-    let port = settings.get::<String>("redis.port").unwrap();
-    let password = settings.get::<String>("redis.password").unwrap();
-    let db = settings.get::<String>("redis.db").unwrap();
-    let pool_get_timeout_seconds = settings.get::<u64>("redis.pool_get_timeout_seconds").unwrap();
-    let pool_max_open = settings.get::<u64>("redis.pool_max_open").unwrap();
-    let pool_max_idle = settings.get::<u64>("redis.pool_max_idle").unwrap();
-    let pool_max_lifetime_seconds = settings.get::<u64>("redis.pool_max_lifetime_seconds").unwrap();
-
-    let client = redis::Client::open(&format!("redis://:{}@{}:{}/{}", password, host, port, db)[..]).unwrap();
+pub async fn conn(settings: &RedisSettings) -> Pool<RedisConnectionManager> {
+    let client = redis::Client::open(&format!("redis://:{}@{}:{}/{}", settings.password.expose(), settings.host, settings.port, settings.db)[..]).unwrap();
     let manager = RedisConnectionManager::new(client);
-    let pool = Pool::builder()
-        .get_timeout(Some(Duration::from_secs(pool_get_timeout_seconds)))
-        .max_open(pool_max_open)
-        .max_idle(pool_max_idle)
-        .max_lifetime(Some(Duration::from_secs(pool_max_lifetime_seconds)))
-        .build(manager);
-    
-    pool
+
+    Pool::builder()
+        .get_timeout(Some(Duration::from_secs(settings.pool_get_timeout_seconds)))
+        .max_open(settings.pool_max_open)
+        .max_idle(settings.pool_max_idle)
+        .max_lifetime(Some(Duration::from_secs(settings.pool_max_lifetime_seconds)))
+        .build(manager)
 }
 
+// 以下命令直接使用原始key，不添加缓存的命名空间前缀
+// 通过 Cache::run 执行，获取连接和执行命令共用 cache.timeout_ms 超时，超时、连接池和redis错误都记录日志并返回500
+
 // 设置过期时间（秒）
-pub async fn expire(key: String, value: i64, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<(), error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("EXPIRE")
-        .arg(key)
-        .arg(value)
-        .query_async::<i16>(&mut con as &mut redis::aio::MultiplexedConnection)
-        .await;
-    if let Err(err) = result {
-        error!(log, "{}", err);
-        return Err(error::err500());
-    }
+pub async fn expire(key: String, value: i64, cache: &Cache, log: &slog::Logger)  -> Result<(), error::Error> {
+    let cmd = redis::cmd("EXPIRE").arg(key).arg(value).to_owned();
+    cache.run("EXPIRE", log, |mut con| async move { cmd.query_async::<i16>(&mut *con).await }).await?;
 
     Ok(())
 }
 
 // 获取某key的过期时间(秒)
-pub async fn get_expire(key: String, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<i64, error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("TTL").arg(key).query_async::<i64>(&mut con as &mut MultiplexedConnection).await;
-    match result {
-        Ok(v) => Ok(v),
-        Err(e) => {
-            error!(log, "{}", e);
-            Err(error::err500())
-        }
-    }
+pub async fn get_expire(key: String, cache: &Cache, log: &slog::Logger)  -> Result<i64, error::Error> {
+    let cmd = redis::cmd("TTL").arg(key).to_owned();
+
+    cache.run("TTL", log, |mut con| async move { cmd.query_async::<i64>(&mut *con).await }).await
 }
 
-pub async fn del(key: String, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<(), error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("DEL").arg(key).query_async::<i32>(&mut con as &mut MultiplexedConnection).await;
-    if let Err(err) = result {
-        error!(log, "{}", err);
-        return Err(error::err500());
-    }
+pub async fn del(key: String, cache: &Cache, log: &slog::Logger)  -> Result<(), error::Error> {
+    let cmd = redis::cmd("DEL").arg(key).to_owned();
+    cache.run("DEL", log, |mut con| async move { cmd.query_async::<i32>(&mut *con).await }).await?;
 
     Ok(())
 }
 
-pub async fn has_key(key: String, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<bool, error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("EXISTS").arg(key).query_async::<i32>(&mut con as &mut MultiplexedConnection).await;
-    match result {
-        Ok(v) => {
-            if v > 0 {
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        },
-        Err(e) => {
-            error!(log, "{}", e);
-            Err(error::err500())
-        }
-    }
+pub async fn has_key(key: String, cache: &Cache, log: &slog::Logger)  -> Result<bool, error::Error> {
+    let cmd = redis::cmd("EXISTS").arg(key).to_owned();
+    let v = cache.run("EXISTS", log, |mut con| async move { cmd.query_async::<i32>(&mut *con).await }).await?;
+
+    Ok(v > 0)
 }
 
-pub async fn set<T: redis::ToRedisArgs>(key: String, value: T, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<(), error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("SET").arg(key).arg(value).query_async::<String>(&mut con as &mut MultiplexedConnection).await;
-    if let Err(err) = result {
-        error!(log, "{}", err);
-        return Err(error::err500());
-    }
+pub async fn set<T: redis::ToRedisArgs>(key: String, value: T, cache: &Cache, log: &slog::Logger)  -> Result<(), error::Error> {
+    let cmd = redis::cmd("SET").arg(key).arg(value).to_owned();
+    cache.run("SET", log, |mut con| async move { cmd.query_async::<String>(&mut *con).await }).await?;
 
     Ok(())
 }
 
-pub async fn set_with_expire<T: redis::ToRedisArgs>(key: String, value: T, time: i64, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<(), error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("SET").arg(key).arg(value).arg("EX").arg(time).query_async::<String>(&mut con as &mut MultiplexedConnection).await;
-    if let Err(err) = result {
-        error!(log, "{}", err);
-        return Err(error::err500());
-    }
+pub async fn set_with_expire<T: redis::ToRedisArgs>(key: String, value: T, time: i64, cache: &Cache, log: &slog::Logger)  -> Result<(), error::Error> {
+    let cmd = redis::cmd("SET").arg(key).arg(value).arg("EX").arg(time).to_owned();
+    cache.run("SET", log, |mut con| async move { cmd.query_async::<String>(&mut *con).await }).await?;
 
     Ok(())
 }
 
-pub async fn get<T: redis::FromRedisValue>(key: String, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<T, error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("GET").arg(key).query_async::<T>(&mut con as &mut MultiplexedConnection).await;
-    match result {
-        Ok(v) => Ok(v),
-        Err(e) => {
-            error!(log, "{}", e);
-            Err(error::err500())
-        }
-    }
-}
-
+pub async fn get<T: redis::FromRedisValue>(key: String, cache: &Cache, log: &slog::Logger)  -> Result<T, error::Error> {
+    let cmd = redis::cmd("GET").arg(key).to_owned();
 
+    cache.run("GET", log, |mut con| async move { cmd.query_async::<T>(&mut *con).await }).await
+}
 
-pub async fn hset<T: redis::ToRedisArgs>(key: String, item: String, value: T, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<(), error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("HSET").arg(key).arg(item).arg(value).query_async::<i32>(&mut con as &mut MultiplexedConnection).await;
-    if let Err(err) = result {
-        error!(log, "{}", err);
-        return Err(error::err500());
-    }
+pub async fn hset<T: redis::ToRedisArgs>(key: String, item: String, value: T, cache: &Cache, log: &slog::Logger)  -> Result<(), error::Error> {
+    let cmd = redis::cmd("HSET").arg(key).arg(item).arg(value).to_owned();
+    cache.run("HSET", log, |mut con| async move { cmd.query_async::<i32>(&mut *con).await }).await?;
 
     Ok(())
 }
 
-pub async fn hset_with_expire<T: redis::ToRedisArgs>(key: String, item: String, value: T, time: i64, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<(), error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("HSET").arg(&key).arg(item).arg(value).query_async::<i32>(&mut con as &mut MultiplexedConnection).await;
-    if let Err(err) = result {
-        error!(log, "{}", err);
-        return Err(error::err500());
-    }
+pub async fn hset_with_expire<T: redis::ToRedisArgs>(key: String, item: String, value: T, time: i64, cache: &Cache, log: &slog::Logger)  -> Result<(), error::Error> {
+    hset(key.clone(), item, value, cache, log).await?;
 
     if time > 0 {
-        expire(key, time, &pool, &log).await?;
+        expire(key, time, cache, log).await?;
     }
 
     Ok(())
 }
 
-pub async fn hget<T: redis::FromRedisValue>(key: String, item: String, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<T, error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("HGET").arg(key).arg(item).query_async::<T>(&mut con as &mut MultiplexedConnection).await;
-    match result {
-        Ok(v) => Ok(v),
-        Err(e) => {
-            error!(log, "{}", e);
-            Err(error::err500())
-        }
-    }
+pub async fn hget<T: redis::FromRedisValue>(key: String, item: String, cache: &Cache, log: &slog::Logger)  -> Result<T, error::Error> {
+    let cmd = redis::cmd("HGET").arg(key).arg(item).to_owned();
+
+    cache.run("HGET", log, |mut con| async move { cmd.query_async::<T>(&mut *con).await }).await
 }
 
-pub async fn hhas_key(key: String, item: String, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<bool, error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("HEXISTS").arg(key).arg(item).query_async::<i32>(&mut con as &mut MultiplexedConnection).await;
-    match result {
-        Ok(v) => {
-            if v > 0 {
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        },
-        Err(e) => {
-            error!(log, "{}", e);
-            Err(error::err500())
-        }
-    }
+pub async fn hhas_key(key: String, item: String, cache: &Cache, log: &slog::Logger)  -> Result<bool, error::Error> {
+    let cmd = redis::cmd("HEXISTS").arg(key).arg(item).to_owned();
+    let v = cache.run("HEXISTS", log, |mut con| async move { cmd.query_async::<i32>(&mut *con).await }).await?;
+
+    Ok(v > 0)
 }
 
-pub async fn hdel(key: String, item: String, pool: &mobc::Pool<RedisConnectionManager>, log: &slog::Logger)  -> Result<(), error::Error> {
-    let mut con = pool.get().await.unwrap();
-    let result = redis::cmd("HDEL").arg(key).arg(item).query_async::<i32>(&mut con as &mut MultiplexedConnection).await;
-    if let Err(err) = result {
-        error!(log, "{}", err);
-        return Err(error::err500());
-    }
+pub async fn hdel(key: String, item: String, cache: &Cache, log: &slog::Logger)  -> Result<(), error::Error> {
+    let cmd = redis::cmd("HDEL").arg(key).arg(item).to_owned();
+    cache.run("HDEL", log, |mut con| async move { cmd.query_async::<i32>(&mut *con).await }).await?;
 
     Ok(())
-}
\ No newline at end of file
+}
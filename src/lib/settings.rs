@@ -0,0 +1,810 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use super::{aes, db, log};
+
+pub const DEFAULT_PATH: &str = "data/config/app.toml";
+pub const PROFILES: [&str; 3] = ["dev", "test", "prod"];
+// 加密配置的主密钥环境变量，也可以通过 APP_MASTER_KEY_FILE 指定密钥文件
+pub const MASTER_KEY_ENV: &str = "APP_MASTER_KEY";
+
+// 配置按以下顺序加载，后加载的覆盖先加载的：
+// 1. 配置文件，默认 data/config/app.toml，可通过 --config 指定
+// 2. 同目录下的环境配置文件 app.{profile}.toml（可选），profile 由环境变量 APP_PROFILE 指定，默认 dev
+// 3. 环境变量 APP__SECTION__KEY，如 APP__PG__HOST 覆盖 pg.host
+// 密码等敏感配置见 resolve_secrets
+#[derive(Debug, Clone, Deserialize)]
+pub struct Settings {
+    #[serde(skip)]
+    pub profile: String,
+    pub app: AppSettings,
+    // 数据库连接，只需要配置编译时启用的数据库
+    #[cfg_attr(feature = "mysql", serde(default))]
+    pub pg: DbSettings,
+    #[cfg_attr(feature = "postgres", serde(default))]
+    pub mysql: DbSettings,
+    pub redis: RedisSettings,
+    pub auth: AuthSettings,
+    #[serde(default)]
+    pub tenant: TenantSettings,
+    #[serde(default)]
+    pub user: UserSettings,
+    #[serde(default)]
+    pub repository: RepositorySettings,
+    #[serde(default)]
+    pub storage: StorageSettings,
+    #[serde(default)]
+    pub avatar: AvatarSettings,
+    #[serde(default)]
+    pub invitation: InvitationSettings,
+    #[serde(default)]
+    pub preferences: PreferencesSettings,
+    #[serde(default)]
+    pub cors: CorsSettings,
+    #[serde(default)]
+    pub log: LogSettings,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    #[serde(default)]
+    pub health: HealthSettings,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    #[serde(default)]
+    pub trace: TraceSettings,
+    #[serde(default)]
+    pub shutdown: ShutdownSettings,
+    // 加密的敏感配置，如 [secrets.pg] password = "..."，使用主密钥解密
+    #[serde(default)]
+    pub secrets: HashMap<String, HashMap<String, String>>,
+}
+
+// 敏感配置值，输出日志时隐藏内容，使用时通过 expose 获取
+#[derive(Clone, PartialEq, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "\"\"")
+        } else {
+            write!(f, "\"******\"")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AppSettings {
+    pub name: String,
+    pub port: u16,
+    // 是否部署在反向代理之后，是则从代理头获取客户端ip
+    #[serde(default)]
+    pub behind_proxy: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct DbSettings {
+    pub user: String,
+    #[serde(default)]
+    pub password: Secret,
+    // 密码文件，如 docker/kubernetes secret 挂载的文件
+    #[serde(default)]
+    pub password_file: Option<String>,
+    pub host: String,
+    pub port: u16,
+    pub dbname: String,
+    pub connect_timeout: u64,
+    pub idle_timeout: u64,
+    pub max: u32,
+    #[serde(default)]
+    pub migrate_on_start: bool,
+    // 只读副本地址 host 或 host:port，其他连接参数与主库相同
+    #[serde(default)]
+    pub replicas: Vec<String>,
+    // 写入后在该时间内读取写入的行使用主库，避免复制延迟读到旧数据，0表示不启用
+    #[serde(default)]
+    pub read_your_writes_ms: u64,
+}
+
+impl DbSettings {
+    // 副本的连接配置，只替换 host 和 port，地址无效时返回 None
+    pub fn replica(&self, addr: &str) -> Option<DbSettings> {
+        let (host, port) = match addr.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (addr, self.port),
+        };
+        if host.is_empty() {
+            return None;
+        }
+
+        Some(DbSettings {
+            host: String::from(host),
+            port,
+            replicas: Vec::new(),
+            ..self.clone()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RedisSettings {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub password: Secret,
+    #[serde(default)]
+    pub password_file: Option<String>,
+    pub db: i64,
+    pub pool_get_timeout_seconds: u64,
+    pub pool_max_open: u64,
+    pub pool_max_idle: u64,
+    pub pool_max_lifetime_seconds: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AuthSettings {
+    pub access_token_expire: i64,
+    pub refresh_token_expire: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TenantSettings {
+    pub header: String,
+    pub base_domain: Option<String>,
+    pub default: String,
+    // 按代码查询到的租户在进程内缓存的时间，每个请求都需要解析租户，0表示不缓存
+    pub cache_seconds: u64,
+}
+
+impl Default for TenantSettings {
+    fn default() -> Self {
+        Self {
+            header: String::from("X-Tenant"),
+            base_domain: None,
+            default: String::from("default"),
+            cache_seconds: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    pub delete_grace_days: i64,
+    pub anonymize_interval_seconds: u64,
+    pub import_max_size: usize,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            delete_grace_days: 30,
+            anonymize_interval_seconds: 3600,
+            import_max_size: 5 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RepositorySettings {
+    // 用户、授权、日志和token黑名单的存储，postgres 或 memory
+    pub backend: String,
+}
+
+impl Default for RepositorySettings {
+    fn default() -> Self {
+        Self {
+            backend: String::from("postgres"),
+        }
+    }
+}
+
+impl RepositorySettings {
+    pub fn is_memory(&self) -> bool {
+        self.backend == "memory"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct StorageSettings {
+    pub backend: String,
+    pub local_root: String,
+    pub base_url: String,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            backend: String::from("local"),
+            local_root: String::from("data/uploads"),
+            base_url: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AvatarSettings {
+    pub max_size: usize,
+    pub min_dimension: u32,
+    pub max_dimension: u32,
+    pub thumb_size: u32,
+}
+
+impl Default for AvatarSettings {
+    fn default() -> Self {
+        Self {
+            max_size: 2 * 1024 * 1024,
+            min_dimension: 64,
+            max_dimension: 4096,
+            thumb_size: 128,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct InvitationSettings {
+    pub expire_hours: i64,
+    pub max_expire_hours: i64,
+    pub accept_url: String,
+}
+
+impl Default for InvitationSettings {
+    fn default() -> Self {
+        Self {
+            expire_hours: 72,
+            max_expire_hours: 720,
+            accept_url: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PreferencesSettings {
+    pub allow_unknown_keys: bool,
+    pub max_keys: usize,
+    pub max_size: usize,
+    pub cache_ttl: i64,
+}
+
+impl Default for PreferencesSettings {
+    fn default() -> Self {
+        Self {
+            allow_unknown_keys: true,
+            max_keys: 100,
+            max_size: 16384,
+            cache_ttl: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct CorsSettings {
+    // 允许跨域的来源，如 https://example.com，为空时允许所有来源
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    // 最低日志级别 trace/debug/info/warning/error/critical，对所有输出生效
+    pub level: String,
+    pub file: LogFileSettings,
+    pub terminal: LogOutputSettings,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: String::from("info"),
+            file: LogFileSettings::default(),
+            terminal: LogOutputSettings::default(),
+        }
+    }
+}
+
+impl LogSettings {
+    pub fn level(&self) -> slog::Level {
+        parse_level(&self.level).unwrap_or(slog::Level::Info)
+    }
+}
+
+// 屏幕输出
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LogOutputSettings {
+    pub enabled: bool,
+    // json 或 text
+    pub format: String,
+    // 该输出的最低日志级别
+    pub level: String,
+}
+
+impl Default for LogOutputSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            format: String::from("text"),
+            level: String::from("trace"),
+        }
+    }
+}
+
+// 文件输出，按大小或时间切分
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LogFileSettings {
+    pub enabled: bool,
+    pub format: String,
+    pub level: String,
+    pub path: String,
+    // 文件超过该大小(MB)时切分，0为不按大小切分
+    pub max_size_mb: u64,
+    // 按时间切分 none/hourly/daily
+    pub rotate: String,
+    // 保留的历史文件数，0为全部保留
+    pub max_files: usize,
+    // 是否gzip压缩历史文件
+    pub compress: bool,
+}
+
+impl Default for LogFileSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            format: String::from("json"),
+            level: String::from("trace"),
+            path: String::from("data/logs/app.log"),
+            max_size_mb: 100,
+            rotate: String::from("daily"),
+            max_files: 7,
+            compress: true,
+        }
+    }
+}
+
+pub fn parse_level(level: &str) -> Option<slog::Level> {
+    match &level.to_lowercase()[..] {
+        "trace" => Some(slog::Level::Trace),
+        "debug" => Some(slog::Level::Debug),
+        "info" => Some(slog::Level::Info),
+        "warn" | "warning" => Some(slog::Level::Warning),
+        "error" => Some(slog::Level::Error),
+        "critical" => Some(slog::Level::Critical),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    // 缓存key的前缀，key为 {namespace}:{key}，多个应用共用redis时区分
+    pub namespace: String,
+    // 每次缓存操作(含获取连接)的超时时间
+    pub timeout_ms: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            namespace: String::from("app"),
+            timeout_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HealthSettings {
+    // 每个依赖检查的超时时间
+    pub timeout_ms: u64,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Default)]
+#[serde(default)]
+pub struct MetricsSettings {
+    // 是否开放 /metrics，默认关闭，开放时应在网关限制只允许内网访问
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TraceSettings {
+    // none/stdout/otlp
+    pub exporter: String,
+    // otlp http 接收地址
+    pub endpoint: String,
+    // 采样比例 0-1，上游请求已采样时跟随上游
+    pub sample_ratio: f64,
+}
+
+impl Default for TraceSettings {
+    fn default() -> Self {
+        Self {
+            exporter: String::from("none"),
+            endpoint: String::from("http://localhost:4318/v1/traces"),
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ShutdownSettings {
+    // 收到停止信号后 /readyz 先返回503，等待 delay_seconds 让负载均衡摘除流量后再停止服务
+    pub delay_seconds: u64,
+    // 停止服务时等待处理中请求完成的最长时间
+    pub timeout_seconds: u64,
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self {
+            delay_seconds: 5,
+            timeout_seconds: 30,
+        }
+    }
+}
+
+// 环境配置文件路径，如 data/config/app.toml 对应 data/config/app.prod
+fn profile_path(path: &str, profile: &str) -> String {
+    let p = Path::new(path);
+    let stem = p.file_stem().and_then(|v| v.to_str()).unwrap_or("app");
+    match p.parent().and_then(|v| v.to_str()) {
+        Some(dir) if !dir.is_empty() => format!("{}/{}.{}", dir, stem, profile),
+        _ => format!("{}.{}", stem, profile),
+    }
+}
+
+// 加载并校验配置，出错时返回可直接输出的错误信息
+pub fn load(path: &str) -> Result<Settings, String> {
+    let profile = std::env::var("APP_PROFILE").unwrap_or_else(|_| String::from("dev"));
+    if !PROFILES.contains(&&profile[..]) {
+        return Err(format!("APP_PROFILE: 不支持的环境{}，可选 {}", profile, PROFILES.join("/")));
+    }
+
+    let config = config::Config::builder()
+        .add_source(config::File::with_name(path))
+        .add_source(config::File::with_name(&profile_path(path, &profile)).required(false))
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("__")
+                .separator("__")
+                .try_parsing(true),
+        )
+        .build();
+    let config = match config {
+        Ok(v) => v,
+        Err(e) => return Err(format!("读取配置失败: {}", e)),
+    };
+
+    let mut settings = match config.try_deserialize::<Settings>() {
+        Ok(v) => v,
+        Err(e) => return Err(format!("配置格式错误: {}", e)),
+    };
+    settings.profile = profile;
+
+    let mut errors = settings.resolve_secrets();
+    errors.extend(settings.validate());
+    if !errors.is_empty() {
+        return Err(format!("配置校验失败:\n  {}", errors.join("\n  ")));
+    }
+
+    Ok(settings)
+}
+
+// 读取密钥文件，去掉末尾换行
+fn read_secret_file(path: &str) -> Result<String, String> {
+    match std::fs::read_to_string(path) {
+        Ok(v) => Ok(v.trim_end_matches(['\r', '\n']).to_string()),
+        Err(e) => Err(format!("{}: {}", path, e)),
+    }
+}
+
+// 主密钥，优先使用 APP_MASTER_KEY，其次读取 APP_MASTER_KEY_FILE 指定的文件
+pub fn master_key() -> Result<Option<String>, String> {
+    let key = match std::env::var(MASTER_KEY_ENV) {
+        Ok(v) => v,
+        Err(_) => match std::env::var(format!("{}_FILE", MASTER_KEY_ENV)) {
+            Ok(path) => read_secret_file(&path)?,
+            Err(_) => return Ok(None),
+        },
+    };
+
+    if key.len() < 16 {
+        return Err(format!("{}: 主密钥长度不能少于16位", MASTER_KEY_ENV));
+    }
+
+    Ok(Some(key))
+}
+
+impl Settings {
+    // 编译时启用的数据库的连接配置
+    #[cfg(feature = "postgres")]
+    pub fn db(&self) -> &DbSettings {
+        &self.pg
+    }
+
+    #[cfg(feature = "mysql")]
+    pub fn db(&self) -> &DbSettings {
+        &self.mysql
+    }
+
+    // 解析敏感配置，优先级从高到低：
+    // 1. 环境变量 APP__SECTION__KEY，如 APP__PG__PASSWORD
+    // 2. 密钥文件 section.key_file，也可以通过 APP__PG__PASSWORD_FILE 指定
+    // 3. 加密配置 [secrets.section] key，使用主密钥解密
+    // 4. 配置文件中的明文
+    fn resolve_secrets(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let master_key = match master_key() {
+            Ok(v) => v,
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        };
+
+        let secrets = std::mem::take(&mut self.secrets);
+        #[cfg(feature = "postgres")]
+        let db = &mut self.pg;
+        #[cfg(feature = "mysql")]
+        let db = &mut self.mysql;
+        let targets = [
+            (db::SECTION, "password", &mut db.password, &db.password_file),
+            ("redis", "password", &mut self.redis.password, &self.redis.password_file),
+        ];
+        for (section, key, value, file) in targets {
+            let name = format!("{}.{}", section, key);
+            if std::env::var(format!("APP__{}__{}", section, key).to_uppercase()).is_ok() {
+                continue;
+            }
+
+            if let Some(path) = file {
+                match read_secret_file(path) {
+                    Ok(v) => *value = Secret(v),
+                    Err(e) => errors.push(format!("{}_file: 读取失败 {}，密码文件不在版本库中，需要先创建该文件，或去掉 {}_file 改用环境变量等方式提供密码", name, e, name)),
+                }
+                continue;
+            }
+
+            if let Some(encrypted) = secrets.get(section).and_then(|v| v.get(key)) {
+                match &master_key {
+                    None => errors.push(format!("secrets.{}: 缺少主密钥 {}", name, MASTER_KEY_ENV)),
+                    Some(master_key) => match aes::decrypt_secret(encrypted, master_key) {
+                        Some(v) => *value = Secret(v),
+                        None => errors.push(format!("secrets.{}: 解密失败", name)),
+                    },
+                }
+            }
+        }
+        self.secrets = secrets;
+
+        errors
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.app.name.is_empty() {
+            errors.push(String::from("app.name: 不能为空"));
+        }
+        if self.app.port == 0 {
+            errors.push(String::from("app.port: 不能为0"));
+        }
+        let db = self.db();
+        if db.host.is_empty() || db.user.is_empty() || db.dbname.is_empty() {
+            errors.push(format!("{0}.host, {0}.user, {0}.dbname: 不能为空", db::SECTION));
+        }
+        if db.max == 0 {
+            errors.push(format!("{}.max: 必须大于0", db::SECTION));
+        }
+        for v in &db.replicas {
+            if db.replica(v).is_none() {
+                errors.push(format!("{}.replicas: {} 不是有效的 host 或 host:port", db::SECTION, v));
+            }
+        }
+        if self.redis.host.is_empty() {
+            errors.push(String::from("redis.host: 不能为空"));
+        }
+        if self.redis.pool_max_open == 0 {
+            errors.push(String::from("redis.pool_max_open: 必须大于0"));
+        }
+        if self.auth.access_token_expire <= 0 {
+            errors.push(String::from("auth.access_token_expire: 必须大于0"));
+        }
+        if self.auth.refresh_token_expire <= self.auth.access_token_expire {
+            errors.push(String::from("auth.refresh_token_expire: 必须大于 auth.access_token_expire"));
+        }
+        if self.tenant.header.is_empty() || self.tenant.default.is_empty() {
+            errors.push(String::from("tenant.header, tenant.default: 不能为空"));
+        }
+        if self.user.delete_grace_days < 0 {
+            errors.push(String::from("user.delete_grace_days: 不能小于0"));
+        }
+        if self.user.anonymize_interval_seconds == 0 {
+            errors.push(String::from("user.anonymize_interval_seconds: 必须大于0"));
+        }
+        if self.repository.backend != "postgres" && self.repository.backend != "memory" {
+            errors.push(format!("repository.backend: 不支持的存储类型{}，可选 postgres/memory", self.repository.backend));
+        }
+        if self.storage.backend != "local" && self.storage.backend != "memory" {
+            errors.push(format!("storage.backend: 不支持的存储类型{}，可选 local/memory", self.storage.backend));
+        }
+        if self.avatar.min_dimension > self.avatar.max_dimension {
+            errors.push(String::from("avatar.min_dimension: 不能大于 avatar.max_dimension"));
+        }
+        if self.avatar.thumb_size == 0 {
+            errors.push(String::from("avatar.thumb_size: 必须大于0"));
+        }
+        if self.invitation.expire_hours < 1 || self.invitation.expire_hours > self.invitation.max_expire_hours {
+            errors.push(String::from("invitation.expire_hours: 必须在1到 invitation.max_expire_hours 之间"));
+        }
+        if self.preferences.cache_ttl <= 0 {
+            errors.push(String::from("preferences.cache_ttl: 必须大于0"));
+        }
+        for origin in &self.cors.allowed_origins {
+            if !origin.starts_with("http://") && !origin.starts_with("https://") {
+                errors.push(format!("cors.allowed_origins: 无效的来源{}", origin));
+            }
+        }
+        if self.cache.timeout_ms == 0 {
+            errors.push(String::from("cache.timeout_ms: 必须大于0"));
+        }
+        if self.cache.namespace.contains(char::is_whitespace) {
+            errors.push(String::from("cache.namespace: 不能包含空白字符"));
+        }
+        if self.health.timeout_ms == 0 {
+            errors.push(String::from("health.timeout_ms: 必须大于0"));
+        }
+        if !["none", "stdout", "otlp"].contains(&&self.trace.exporter[..]) {
+            errors.push(format!("trace.exporter: 不支持的类型{}，可选 none/stdout/otlp", self.trace.exporter));
+        }
+        if !(0.0..=1.0).contains(&self.trace.sample_ratio) {
+            errors.push(String::from("trace.sample_ratio: 必须在0到1之间"));
+        }
+        for (key, level) in [("log.level", &self.log.level), ("log.file.level", &self.log.file.level), ("log.terminal.level", &self.log.terminal.level)] {
+            if parse_level(level).is_none() {
+                errors.push(format!("{}: 不支持的日志级别{}", key, level));
+            }
+        }
+        for (key, format) in [("log.file.format", &self.log.file.format), ("log.terminal.format", &self.log.terminal.format)] {
+            if format != "json" && format != "text" {
+                errors.push(format!("{}: 不支持的格式{}，可选 json/text", key, format));
+            }
+        }
+        if self.log.file.enabled && self.log.file.path.is_empty() {
+            errors.push(String::from("log.file.path: 不能为空"));
+        }
+        if !["none", "hourly", "daily"].contains(&&self.log.file.rotate[..]) {
+            errors.push(format!("log.file.rotate: 不支持的切分方式{}，可选 none/hourly/daily", self.log.file.rotate));
+        }
+
+        errors
+    }
+}
+
+
+// 运行中可以重新加载的配置，修改其他配置需要重启
+pub const RELOADABLE: [&str; 4] = ["auth.access_token_expire", "auth.refresh_token_expire", "cors.allowed_origins", "log.level"];
+
+// 运行中的配置，重新加载时整体替换，handler 通过 get 获取当前配置
+pub struct SharedSettings {
+    path: String,
+    current: RwLock<Arc<Settings>>,
+}
+
+impl SharedSettings {
+    pub fn new(path: &str, settings: Settings) -> Self {
+        SharedSettings {
+            path: path.to_string(),
+            current: RwLock::new(Arc::new(settings)),
+        }
+    }
+
+    pub fn get(&self) -> Arc<Settings> {
+        self.current.read().unwrap().clone()
+    }
+
+    // 重新读取配置文件和环境变量，只替换 RELOADABLE 中的配置，返回修改内容
+    // 新配置校验失败时不做任何修改
+    pub fn reload(&self, logger: &slog::Logger) -> Result<Vec<String>, String> {
+        let loaded = match load(&self.path) {
+            Ok(v) => v,
+            Err(e) => {
+                error!(logger, "reload config failed: {}", e);
+                return Err(e);
+            }
+        };
+
+        let mut current = self.current.write().unwrap();
+        let mut next = (**current).clone();
+        next.auth = loaded.auth.clone();
+        next.cors = loaded.cors.clone();
+        next.log.level = loaded.log.level.clone();
+
+        let changes = diff(&current, &next);
+        let restart = restart_required(&next, &loaded);
+        if !restart.is_empty() {
+            warn!(logger, "reload config: {} changed, only {} can be reloaded, other changes require restart", restart.join(", "), RELOADABLE.join(", "));
+        }
+        if changes.is_empty() {
+            info!(logger, "reload config: no changes");
+            return Ok(changes);
+        }
+
+        for v in &changes {
+            info!(logger, "reload config: {}", v);
+        }
+        log::set_level(next.log.level());
+        *current = Arc::new(next);
+
+        Ok(changes)
+    }
+}
+
+// 逐个比较不能重新加载的配置，返回有修改的部分，reloadable 中的配置已经与新配置一致
+fn restart_required(current: &Settings, loaded: &Settings) -> Vec<&'static str> {
+    let sections = [
+        ("app", current.app == loaded.app),
+        ("pg", current.pg == loaded.pg),
+        ("mysql", current.mysql == loaded.mysql),
+        ("redis", current.redis == loaded.redis),
+        ("tenant", current.tenant == loaded.tenant),
+        ("user", current.user == loaded.user),
+        ("repository", current.repository == loaded.repository),
+        ("storage", current.storage == loaded.storage),
+        ("avatar", current.avatar == loaded.avatar),
+        ("invitation", current.invitation == loaded.invitation),
+        ("preferences", current.preferences == loaded.preferences),
+        ("log", current.log == loaded.log),
+        ("cache", current.cache == loaded.cache),
+        ("health", current.health == loaded.health),
+        ("metrics", current.metrics == loaded.metrics),
+        ("trace", current.trace == loaded.trace),
+        ("shutdown", current.shutdown == loaded.shutdown),
+        ("secrets", current.secrets == loaded.secrets),
+    ];
+
+    sections.iter().filter(|(_, same)| !same).map(|(name, _)| *name).collect()
+}
+
+fn diff(old: &Settings, new: &Settings) -> Vec<String> {
+    let pairs = [
+        (format!("{}", old.auth.access_token_expire), format!("{}", new.auth.access_token_expire)),
+        (format!("{}", old.auth.refresh_token_expire), format!("{}", new.auth.refresh_token_expire)),
+        (format!("{:?}", old.cors.allowed_origins), format!("{:?}", new.cors.allowed_origins)),
+        (old.log.level.clone(), new.log.level.clone()),
+    ];
+
+    RELOADABLE.iter().zip(pairs)
+        .filter(|(_, (old, new))| old != new)
+        .map(|(key, (old, new))| format!("{}: {} -> {}", key, old, new))
+        .collect()
+}
\ No newline at end of file
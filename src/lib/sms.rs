@@ -0,0 +1,23 @@
+use super::error;
+
+// 短信模板id，接入短信服务商时替换为服务商的模板编号
+pub const TEMPLATE_MOBILE_CODE: &str = "mobile_code";
+pub const TEMPLATE_INVITATION: &str = "invitation";
+
+// 发送模板短信，暂未接入短信服务商，仅记录日志
+// 参数中有验证码、邀请码等敏感信息，日志只记录脱敏的手机号和模板id
+pub async fn send(mobile: &str, template: &str, _params: &[&str], log: &slog::Logger) -> Result<(), error::Error> {
+    info!(log, "sms to {}, template {}", mask(mobile), template);
+
+    Ok(())
+}
+
+// 手机号脱敏，保留前3位和后4位
+pub fn mask(mobile: &str) -> String {
+    let chars: Vec<char> = mobile.chars().collect();
+    if chars.len() <= 7 {
+        return "*".repeat(chars.len());
+    }
+
+    format!("{}****{}", chars[..3].iter().collect::<String>(), chars[chars.len() - 4..].iter().collect::<String>())
+}
@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+use async_trait::async_trait;
+use crate::lib::error;
+use super::{Storage, valid_key};
+
+// 本地文件系统存储
+pub struct LocalStorage {
+    root: PathBuf,
+    base_url: String,
+    log: slog::Logger,
+}
+
+impl LocalStorage {
+    pub fn new(root: &str, base_url: &str, log: slog::Logger) -> Self {
+        Self {
+            root: PathBuf::from(root),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            log,
+        }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, error::Error> {
+        if !valid_key(key) {
+            error!(self.log, "invalid storage key: {}", key);
+            return Err(error::err500());
+        }
+
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), error::Error> {
+        let path = self.path(key)?;
+        if let Some(dir) = path.parent() {
+            if let Err(e) = tokio::fs::create_dir_all(dir).await {
+                error!(self.log, "{}", e);
+                return Err(error::err500());
+            }
+        }
+
+        if let Err(e) = tokio::fs::write(&path, data).await {
+            error!(self.log, "{}", e);
+            return Err(error::err500());
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, error::Error> {
+        let path = self.path(key)?;
+        match tokio::fs::read(&path).await {
+            Ok(v) => Ok(Some(v)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                error!(self.log, "{}", e);
+                Err(error::err500())
+            }
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), error::Error> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => {
+                error!(self.log, "{}", e);
+                Err(error::err500())
+            }
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/files/{}", self.base_url, key)
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use crate::lib::error;
+use super::{Storage, valid_key};
+
+// 内存存储，用于测试和没有文件系统的环境，重启后数据丢失
+pub struct MemoryStorage {
+    files: Mutex<HashMap<String, Vec<u8>>>,
+    base_url: String,
+}
+
+impl MemoryStorage {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            files: Mutex::new(HashMap::new()),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), error::Error> {
+        if !valid_key(key) {
+            return Err(error::err500());
+        }
+        self.files.lock().unwrap().insert(key.to_string(), data);
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, error::Error> {
+        Ok(self.files.lock().unwrap().get(key).cloned())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), error::Error> {
+        self.files.lock().unwrap().remove(key);
+
+        Ok(())
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/files/{}", self.base_url, key)
+    }
+}
@@ -0,0 +1,31 @@
+pub mod local;
+pub mod memory;
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use super::error;
+use super::settings::StorageSettings;
+
+// 文件存储，key为不以/开头的相对路径，如 avatars/xxx.png
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), error::Error>;
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, error::Error>;
+    async fn delete(&self, key: &str) -> Result<(), error::Error>;
+    fn url(&self, key: &str) -> String;
+}
+
+// key只允许字母数字和 -_./，且不能包含 ..
+pub fn valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('/')
+        && !key.split('/').any(|v| v.is_empty() || v == "." || v == "..")
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '/')
+}
+
+pub fn conn(settings: &StorageSettings, log: &slog::Logger) -> Arc<dyn Storage> {
+    match &settings.backend[..] {
+        "memory" => Arc::new(memory::MemoryStorage::new(&settings.base_url)),
+        _ => Arc::new(local::LocalStorage::new(&settings.local_root, &settings.base_url, log.clone())),
+    }
+}
@@ -0,0 +1,154 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::HeaderMap;
+use actix_web::middleware::Next;
+use opentelemetry::context::FutureExt;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::{SpanKind, Status, TraceContextExt, Tracer};
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::fmt::Display;
+use std::future::Future;
+use super::settings::TraceSettings;
+
+const TRACER_NAME: &str = "rust-actix-rest-api-boilerplate";
+
+// 初始化链路追踪，exporter 为 none 时不导出，返回的 provider 用于停止服务时导出剩余的span
+pub fn init(settings: &TraceSettings, service_name: &str) -> Result<Option<SdkTracerProvider>, String> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let builder = SdkTracerProvider::builder()
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(settings.sample_ratio))))
+        .with_resource(Resource::builder().with_service_name(service_name.to_string()).build());
+
+    let provider = match &settings.exporter[..] {
+        "otlp" => {
+            use opentelemetry_otlp::WithExportConfig;
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(&settings.endpoint)
+                .build();
+            match exporter {
+                Ok(v) => builder.with_batch_exporter(v).build(),
+                Err(e) => return Err(format!("trace: 创建otlp exporter失败 {}", e)),
+            }
+        },
+        "stdout" => builder.with_simple_exporter(opentelemetry_stdout::SpanExporter::default()).build(),
+        _ => return Ok(None),
+    };
+
+    global::set_tracer_provider(provider.clone());
+
+    Ok(Some(provider))
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|v| v.as_str()).collect()
+    }
+}
+
+// 为每个请求创建span，从请求头 traceparent/tracestate 继承上游的链路
+pub async fn middleware(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let parent = global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(req.headers())));
+    let method = req.method().to_string();
+    let span = global::tracer(TRACER_NAME)
+        .span_builder(format!("{} {}", method, req.path()))
+        .with_kind(SpanKind::Server)
+        .with_attributes(vec![
+            KeyValue::new("http.request.method", method.clone()),
+            KeyValue::new("url.path", req.path().to_string()),
+        ])
+        .start_with_context(&global::tracer(TRACER_NAME), &parent);
+    let cx = parent.with_span(span);
+
+    let res = next.call(req).with_context(cx.clone()).await;
+
+    let span = cx.span();
+    match &res {
+        Ok(res) => {
+            // 使用路由模板作为span名称，避免路径参数导致名称过多
+            if let Some(route) = res.request().match_pattern() {
+                span.update_name(format!("{} {}", method, route));
+                span.set_attribute(KeyValue::new("http.route", route));
+            }
+            span.set_attribute(KeyValue::new("http.response.status_code", res.status().as_u16() as i64));
+            if res.status().is_server_error() {
+                span.set_status(Status::error(res.status().to_string()));
+            }
+        },
+        Err(e) => span.set_status(Status::error(e.to_string())),
+    }
+    span.end();
+
+    res
+}
+
+// 在当前链路下创建子span执行future，出错时记录错误
+async fn child<F, T, E>(name: String, attributes: Vec<KeyValue>, fut: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let span = global::tracer(TRACER_NAME)
+        .span_builder(name)
+        .with_kind(SpanKind::Client)
+        .with_attributes(attributes)
+        .start(&global::tracer(TRACER_NAME));
+    let cx = Context::current_with_span(span);
+
+    let r = fut.with_context(cx.clone()).await;
+
+    if let Err(e) = &r {
+        cx.span().set_status(Status::error(e.to_string()));
+    }
+    cx.span().end();
+
+    r
+}
+
+// 数据库查询的span，operation 为 模块.函数名，如 user.get_by_id
+pub async fn sql<F, T, E>(operation: &'static str, fut: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    child(format!("sql {}", operation), vec![
+        KeyValue::new("db.system.name", crate::lib::db::SYSTEM_NAME),
+        KeyValue::new("db.operation.name", operation),
+    ], fut).await
+}
+
+// redis命令的span
+pub async fn redis<F, T, E>(command: &'static str, fut: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    child(format!("redis {}", command), vec![
+        KeyValue::new("db.system.name", "redis"),
+        KeyValue::new("db.operation.name", command),
+    ], fut).await
+}
+
+// 为数据库查询添加span，在 model 中使用：sqlx::query(...).fetch_one(db).traced("user.get_by_id").await
+pub trait Traced<T, E: Display>: Future<Output = Result<T, E>> + Sized {
+    fn traced(self, operation: &'static str) -> impl Future<Output = Result<T, E>> {
+        sql(operation, self)
+    }
+}
+
+impl<F, T, E> Traced<T, E> for F
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display,
+{
+}
\ No newline at end of file
@@ -2,7 +2,7 @@ use regex::Regex;
 use crate::lib::error;
 
 pub fn not_none<T>(v: Option<T>, name: &str) -> Result<(), error::Error> {
-    if let None = v {
+    if v.is_none() {
         return Err(error::new(400002, &format!("{}不能为空", name)[..], 422));
     }
 
@@ -44,5 +44,13 @@ pub fn uuid(v: &str, name: &str) -> Result<(), error::Error> {
         return Err(error::new(400002, &format!("{}格式错误", name)[..], 422));
     }
 
+    Ok(())
+}
+
+pub fn max_len(v: &str, max: usize, name: &str) -> Result<(), error::Error> {
+    if v.chars().count() > max {
+        return Err(error::new(400002, &format!("{}不能超过{}个字符", name, max)[..], 422));
+    }
+
     Ok(())
 }
\ No newline at end of file
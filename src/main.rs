@@ -1,7 +1,10 @@
-#![allow(special_module_name)]
+#![allow(special_module_name, clippy::too_many_arguments)]
 pub mod api;
 pub mod lib;
 mod routes;
+mod cli;
+#[cfg(test)]
+mod tests;
 
 #[macro_use]
 extern crate slog;
@@ -10,68 +13,260 @@ extern crate slog_async;
 extern crate slog_json;
 
 use actix_cors::Cors;
-use actix_web::middleware::ErrorHandlers;
+use actix_web::middleware::{self, ErrorHandlers};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
 use actix_web::{http, web, App, HttpServer, Result, HttpResponse};
 use lib::error;
-use routes::{hello, authorizations, user};
+use routes::{hello, health, metrics, authorizations, user, files, invitation, preferences, settings as settings_route};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub config: config::Config,
+    pub config: std::sync::Arc<lib::settings::SharedSettings>,
     pub log: slog::Logger,
-    pub db: sqlx::Pool<sqlx::Postgres>,
-    pub redis: mobc::Pool<lib::redis::RedisConnectionManager>
+    pub db: lib::db::Pool,
+    pub redis: mobc::Pool<lib::redis::RedisConnectionManager>,
+    pub cache: lib::cache::Cache,
+    pub storage: std::sync::Arc<dyn lib::storage::Storage>,
+    pub repo: api::repository::Repositories,
+    // 是否可以接收流量，停止服务前置为false
+    pub ready: std::sync::Arc<AtomicBool>,
+    pub metrics: std::sync::Arc<lib::metrics::Metrics>,
 }
 
 async fn index() -> Result<HttpResponse, error::Error> {
     Ok(HttpResponse::Ok().body(""))
 }
 
+// 路由、错误处理和中间件，HTTP服务和集成测试共用
+pub fn app(state: web::Data<AppState>) -> App<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error, InitError = ()>> {
+    // 允许的来源从当前配置读取，重新加载配置后立即生效
+    let cors_config = state.config.clone();
+    let cors = Cors::permissive().allowed_origin_fn(move |origin, _| {
+        let allowed_origins = &cors_config.get().cors.allowed_origins;
+        allowed_origins.is_empty() || allowed_origins.iter().any(|v| origin.as_bytes() == v.as_bytes())
+    });
+    let memory = state.config.get().repository.is_memory();
+
+    App::new()
+        .app_data(state)
+        .wrap(
+            ErrorHandlers::new()
+                .handler(http::StatusCode::METHOD_NOT_ALLOWED, error::render_405)
+                .handler(http::StatusCode::NOT_FOUND, error::render_404)
+                .handler(http::StatusCode::INTERNAL_SERVER_ERROR, error::render_500)
+                .handler(http::StatusCode::BAD_REQUEST, error::render_400),
+        )
+        .wrap(cors)
+        .wrap(middleware::from_fn(lib::metrics::middleware))
+        .wrap(middleware::from_fn(lib::trace::middleware))
+        .wrap(middleware::from_fn(lib::log::middleware))
+        .configure(hello::route)
+        .configure(health::route)
+        .configure(metrics::route)
+        .configure(authorizations::route)
+        .configure(user::route)
+        .configure(files::route)
+        // 邀请、偏好设置和手机验证码直接使用数据库和redis，内存存储时不注册这些路由
+        .configure(|cfg| {
+            if !memory {
+                invitation::route(cfg);
+                preferences::route(cfg);
+                user::mobile_code_route(cfg);
+            }
+        })
+        .configure(settings_route::route)
+        .service(web::resource("/").route(web::get().to(index)))
+}
+
+// 等待停止信号 SIGTERM 或 Ctrl-C
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).unwrap();
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = terminate.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // 命令行子命令，未指定时启动HTTP服务
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let config_path = cli::take_option(&mut args, "--config")?.unwrap_or_else(|| String::from(lib::settings::DEFAULT_PATH));
+    let command = args.first().cloned().unwrap_or_else(|| String::from("serve"));
+
+    // 加密敏感配置只需要主密钥，不加载配置文件
+    if command == "encrypt-secret" {
+        return cli::encrypt_secret(&args[1..]);
+    }
+
     // config
-    let settings = config::Config::builder()
-        .add_source(config::File::with_name("data/config/app.toml"))
-        .build()
-        .unwrap();
-    let port = settings.get::<String>("app.port").unwrap();
+    let settings = match lib::settings::load(&config_path) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+        }
+    };
+
+    if command == "check-config" {
+        return cli::check_config(&settings);
+    }
+
+    let port = settings.app.port;
 
     // log
-    let logger = lib::log::get_logger();
-    info!(logger, "==> 🚀 {} listening at {}", settings.get::<String>("app.name").unwrap(), settings.get::<String>("app.port").unwrap());
+    lib::log::set_level(settings.log.level());
+    let logger = match lib::log::get_logger(&settings.log) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+    info!(logger, "config loaded, profile {}", settings.profile; "config" => format!("{:?}", settings));
+
+    // trace
+    let tracer_provider = match lib::trace::init(&settings.trace, &settings.app.name) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(logger, "{}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+
+    // 内存存储的数据不能在命令之间共享
+    let memory = settings.repository.is_memory();
+    if memory && command != "serve" {
+        eprintln!("command {} requires repository.backend = \"postgres\"", command);
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "memory repository"));
+    }
 
-    // database
-    let db_pool = lib::db::pg::conn(&settings).await;
+    // database，使用内存存储时不连接数据库
+    let db_pool = if memory {
+        lib::db::conn_lazy(settings.db())
+    } else {
+        lib::db::conn(settings.db()).await
+    };
+    if command == "serve" && settings.db().migrate_on_start && !memory {
+        if let Err(e) = lib::db::migrate(&db_pool, &logger).await {
+            error!(logger, "migrate failed: {}", e);
+            return Err(std::io::Error::other(e));
+        }
+    }
+
+    // 只读副本
+    let replica_pools = if memory {
+        Vec::new()
+    } else {
+        lib::db::replica::conn(settings.db()).await
+    };
 
     // redis
-    let redis_pool = lib::redis::conn(&settings).await;
-
-    HttpServer::new(move || {
-        let cors = Cors::permissive();
-
-        println!("==> 🚀 {} listening at {}", settings.get::<String>("app.name").unwrap(), settings.get::<String>("app.port").unwrap());
-
-        App::new()
-            .app_data(web::Data::new(AppState {
-                config: settings.clone(),
-                log: logger.clone(),
-                db: db_pool.clone(),
-                redis: redis_pool.clone(),
-            }))
-            .wrap(
-                ErrorHandlers::new()
-                    .handler(http::StatusCode::METHOD_NOT_ALLOWED, error::render_405)
-                    .handler(http::StatusCode::NOT_FOUND, error::render_404)
-                    .handler(http::StatusCode::INTERNAL_SERVER_ERROR, error::render_500)
-                    .handler(http::StatusCode::BAD_REQUEST, error::render_400),
-            )
-            .wrap(cors)
-            .configure(hello::route)
-            .configure(authorizations::route)
-            .configure(user::route)
-            .service(web::resource("/").route(web::get().to(index)))
+    let redis_pool = lib::redis::conn(&settings.redis).await;
+    let cache = lib::cache::Cache::new(redis_pool.clone(), &settings.cache);
+
+    // storage
+    let storage = lib::storage::conn(&settings.storage, &logger);
+
+    // repository
+    let metrics = std::sync::Arc::new(lib::metrics::Metrics::new());
+    let repo = api::repository::conn(&settings, &db_pool, replica_pools, &cache, &metrics);
+
+    let state = web::Data::new(AppState {
+        config: std::sync::Arc::new(lib::settings::SharedSettings::new(&config_path, settings.clone())),
+        log: logger.clone(),
+        db: db_pool,
+        cache,
+        redis: redis_pool,
+        storage,
+        repo,
+        ready: std::sync::Arc::new(AtomicBool::new(true)),
+        metrics,
+    });
+
+    if command != "serve" {
+        return cli::run(&args, &state).await;
+    }
+
+    if memory {
+        cli::seed_admin(&state).await?;
+    }
+
+    info!(logger, "==> 🚀 {} listening at {}, profile {}", settings.app.name, settings.app.port, settings.profile);
+
+    // 定时匿名化已过宽限期的注销用户
+    let job_state = state.clone();
+    let anonymize_interval = settings.user.anonymize_interval_seconds;
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(anonymize_interval));
+        loop {
+            interval.tick().await;
+            if let Err(e) = api::user::service::anonymize_expired(&job_state).await {
+                error!(job_state.log, "anonymize users failed: {}", e.errmsg);
+            }
+        }
+    });
+
+    // 收到SIGHUP时重新加载配置
+    #[cfg(unix)]
+    {
+        let reload_state = state.clone();
+        actix_web::rt::spawn(async move {
+            let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+                Ok(v) => v,
+                Err(e) => {
+                    error!(reload_state.log, "listen SIGHUP failed: {}", e);
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                info!(reload_state.log, "SIGHUP received, reloading config");
+                let _ = reload_state.config.reload(&reload_state.log);
+            }
+        });
+    }
+
+    let shutdown = settings.shutdown.clone();
+    let shutdown_state = state.clone();
+
+    let server = HttpServer::new(move || {
+        println!("==> 🚀 {} listening at {}", settings.app.name, settings.app.port);
+
+        app(state.clone())
     })
     .bind(format!("0.0.0.0:{}", port))?
-    .run()
-    .await
-}
+    .disable_signals()
+    .shutdown_timeout(shutdown.timeout_seconds)
+    .run();
+
+    // 收到停止信号后先让 /readyz 返回503，等待负载均衡摘除流量，再停止接收新请求并等待处理中的请求完成
+    let handle = server.handle();
+    actix_web::rt::spawn(async move {
+        shutdown_signal().await;
+        shutdown_state.ready.store(false, Ordering::Relaxed);
+        info!(shutdown_state.log, "shutting down, waiting {}s before stop", shutdown.delay_seconds);
+        actix_web::rt::time::sleep(Duration::from_secs(shutdown.delay_seconds)).await;
+        handle.stop(true).await;
+    });
+
+    server.await?;
+
+    // 导出剩余的span
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("trace shutdown failed: {}", e);
+        }
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -5,4 +5,7 @@ pub fn route(cfg: &mut web::ServiceConfig) {
     cfg.service(authorizations::controller::create_auth);
     cfg.service(authorizations::controller::refresh_auth);
     cfg.service(authorizations::controller::delete_auth);
+    cfg.service(authorizations::admin_controller::log_types);
+    cfg.service(authorizations::admin_controller::list_logs);
+    cfg.service(authorizations::admin_controller::export_logs);
 }
\ No newline at end of file
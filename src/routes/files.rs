@@ -0,0 +1,6 @@
+use actix_web::web;
+use crate::api::files::get_file;
+
+pub fn route(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_file);
+}
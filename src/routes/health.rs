@@ -0,0 +1,7 @@
+use actix_web::web;
+use crate::api::health;
+
+pub fn route(cfg: &mut web::ServiceConfig) {
+    cfg.service(health::healthz);
+    cfg.service(health::readyz);
+}
\ No newline at end of file
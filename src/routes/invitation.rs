@@ -0,0 +1,9 @@
+use actix_web::{web};
+use crate::api::invitation;
+
+pub fn route(cfg: &mut web::ServiceConfig) {
+    cfg.service(invitation::controller::accept);
+    cfg.service(invitation::admin_controller::create);
+    cfg.service(invitation::admin_controller::list);
+    cfg.service(invitation::admin_controller::revoke);
+}
\ No newline at end of file
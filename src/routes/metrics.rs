@@ -0,0 +1,6 @@
+use actix_web::web;
+use crate::api::metrics::metrics;
+
+pub fn route(cfg: &mut web::ServiceConfig) {
+    cfg.service(metrics);
+}
\ No newline at end of file
@@ -1,3 +1,9 @@
 pub mod hello;
 pub mod authorizations;
-pub mod user;
\ No newline at end of file
+pub mod user;
+pub mod files;
+pub mod invitation;
+pub mod preferences;
+pub mod settings;
+pub mod health;
+pub mod metrics;
\ No newline at end of file
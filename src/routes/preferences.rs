@@ -0,0 +1,8 @@
+use actix_web::{web};
+use crate::api::preferences;
+
+pub fn route(cfg: &mut web::ServiceConfig) {
+    cfg.service(preferences::controller::get);
+    cfg.service(preferences::controller::replace);
+    cfg.service(preferences::controller::merge);
+}
\ No newline at end of file
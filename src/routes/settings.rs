@@ -0,0 +1,6 @@
+use actix_web::web;
+use crate::api::settings;
+
+pub fn route(cfg: &mut web::ServiceConfig) {
+    cfg.service(settings::reload);
+}
\ No newline at end of file
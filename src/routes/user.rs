@@ -4,4 +4,23 @@ use crate::api::user;
 pub fn route(cfg: &mut web::ServiceConfig) {
     cfg.service(user::controller::get_info);
     cfg.service(user::controller::change_password);
-}
\ No newline at end of file
+    cfg.service(user::controller::update_profile);
+    cfg.service(user::controller::export);
+    cfg.service(user::controller::close_account);
+    cfg.service(user::controller::upload_avatar);
+    cfg.service(user::admin_controller::list);
+    cfg.service(user::admin_controller::import_users);
+    cfg.service(user::admin_controller::get_user);
+    cfg.service(user::admin_controller::create_user);
+    cfg.service(user::admin_controller::update_user);
+    cfg.service(user::admin_controller::enable_user);
+    cfg.service(user::admin_controller::disable_user);
+    cfg.service(user::admin_controller::delete_user);
+    cfg.service(user::admin_controller::restore_user);
+    cfg.service(user::admin_controller::reset_password);
+}
+
+// 手机验证码保存在redis中，内存存储时不注册
+pub fn mobile_code_route(cfg: &mut web::ServiceConfig) {
+    cfg.service(user::controller::send_mobile_code);
+}
@@ -0,0 +1,369 @@
+use super::*;
+use crate::api::authorizations::service;
+use crate::lib::client::ClientInfo;
+
+#[actix_web::test]
+async fn create_auth() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+
+    let tokens = login(&app, "alice", "secret").await;
+    assert!(!tokens.access_token.is_empty() && !tokens.refresh_token.is_empty());
+
+    let (status, json) = call(&app, request(Method::GET, "/user", &tokens.access_token)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["username"], "alice");
+
+    let log = single_log(&state, LogType::Login).await;
+    assert_eq!(log.user_id, Some(user_id));
+    assert!(log.auth_id.unwrap_or_default() > 0);
+    assert_eq!(log.ip.as_deref(), Some("127.0.0.1"));
+    assert_eq!(log.user_agent.as_deref(), Some("integration-test"));
+}
+
+#[actix_web::test]
+async fn create_auth_bad_request() {
+    let state = state().await;
+    let app = app(&state).await;
+
+    let req = request(Method::POST, "/authorizations", "").set_json(serde_json::json!({"username": "alice"}));
+    assert_error(&call(&app, req).await, 422, 400002);
+
+    // 请求体不是JSON时由 ErrorHandlers 输出
+    let req = request(Method::POST, "/authorizations", "").insert_header(("Content-Type", "application/json")).set_payload("{");
+    assert_error(&call(&app, req).await, 400, 400);
+
+    assert!(state.repo.logs.search(&LogFilter { tenant_id: 1, ..Default::default() }, 0, 100, &state.log).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn create_auth_user_not_found() {
+    let state = state().await;
+    let app = app(&state).await;
+
+    let req = request(Method::POST, "/authorizations", "").set_json(serde_json::json!({"username": "nobody", "password": "secret"}));
+    assert_error(&call(&app, req).await, 422, 100400);
+
+    let log = single_log(&state, LogType::LoginUserNotFound).await;
+    assert_eq!(log.user_id, Some(0));
+    assert_eq!(log.log.as_deref(), Some("nobody"));
+}
+
+#[actix_web::test]
+async fn create_auth_wrong_password() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+
+    let req = request(Method::POST, "/authorizations", "").set_json(serde_json::json!({"username": "alice", "password": "wrong"}));
+    assert_error(&call(&app, req).await, 422, 100400);
+
+    assert_eq!(single_log(&state, LogType::LoginWrongPassword).await.user_id, Some(user_id));
+    assert!(logs(&state, LogType::Login).await.is_empty());
+}
+
+#[actix_web::test]
+async fn create_auth_user_disabled() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    disable_user(&state, user_id).await;
+
+    let req = request(Method::POST, "/authorizations", "").set_json(serde_json::json!({"username": "alice", "password": "secret"}));
+    assert_error(&call(&app, req).await, 422, 100400);
+
+    assert_eq!(single_log(&state, LogType::LoginUserDisabled).await.user_id, Some(user_id));
+}
+
+#[actix_web::test]
+async fn create_auth_user_deleted() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    delete_user(&state, user_id).await;
+
+    let req = request(Method::POST, "/authorizations", "").set_json(serde_json::json!({"username": "alice", "password": "secret"}));
+    assert_error(&call(&app, req).await, 422, 100400);
+
+    assert_eq!(single_log(&state, LogType::LoginUserDeleted).await.user_id, Some(user_id));
+}
+
+#[actix_web::test]
+async fn refresh_auth() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    let path = format!("/authorizations/{}", tokens.id);
+    let (status, json) = call(&app, request(Method::PUT, &path, &tokens.refresh_token)).await;
+    assert_eq!(status, StatusCode::OK, "{}", json);
+    assert_eq!(json["id"], tokens.id);
+    let access_token = json["access_token"].as_str().unwrap();
+    assert_ne!(access_token, tokens.access_token);
+
+    // 旧的access token加入黑名单
+    assert_error(&call(&app, request(Method::GET, "/user", &tokens.access_token)).await, 401, 100403);
+    assert_eq!(call(&app, request(Method::GET, "/user", access_token)).await.0, StatusCode::OK);
+
+    assert_eq!(single_log(&state, LogType::Refresh).await.user_id, Some(user_id));
+
+    // 新的refresh token可以继续刷新
+    let refresh_token = json["refresh_token"].as_str().unwrap();
+    assert_eq!(call(&app, request(Method::PUT, &path, refresh_token)).await.0, StatusCode::OK);
+    assert_eq!(logs(&state, LogType::Refresh).await.len(), 2);
+}
+
+#[actix_web::test]
+async fn refresh_auth_bad_request() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    assert_error(&call(&app, request(Method::PUT, "/authorizations/abc", &tokens.refresh_token)).await, 422, 400002);
+    assert_error(&call(&app, request(Method::PUT, &format!("/authorizations/{}", tokens.id), "")).await, 401, 100403);
+    assert_error(&call(&app, request(Method::PUT, &format!("/authorizations/{}", tokens.id), "invalid")).await, 401, 100403);
+    assert!(logs(&state, LogType::Refresh).await.is_empty());
+}
+
+#[actix_web::test]
+async fn refresh_auth_no_permission() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    // access token 没有 ROLE_REFRESH_TOKEN
+    let path = format!("/authorizations/{}", tokens.id);
+    assert_error(&call(&app, request(Method::PUT, &path, &tokens.access_token)).await, 403, 100404);
+
+    assert_eq!(single_log(&state, LogType::RefreshNoPermission).await.user_id, Some(0));
+}
+
+#[actix_web::test]
+async fn refresh_auth_not_found() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    let token = auth::create_refresh_token(999, uuid::Uuid::new_v4(), 1, &state.config.get());
+    let path = format!("/authorizations/{}", tokens.id);
+    assert_error(&call(&app, request(Method::PUT, &path, &token.token)).await, 401, 100403);
+
+    assert_eq!(single_log(&state, LogType::RefreshAuthNotFound).await.auth_id, Some(999));
+}
+
+#[actix_web::test]
+async fn refresh_auth_id_mismatch() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    let path = format!("/authorizations/{}", uuid::Uuid::new_v4());
+    assert_error(&call(&app, request(Method::PUT, &path, &tokens.refresh_token)).await, 401, 100403);
+
+    assert_eq!(single_log(&state, LogType::RefreshIdMismatch).await.user_id, Some(user_id));
+}
+
+#[actix_web::test]
+async fn refresh_auth_token_reused() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    // 刷新后旧的refresh token失效
+    let path = format!("/authorizations/{}", tokens.id);
+    assert_eq!(call(&app, request(Method::PUT, &path, &tokens.refresh_token)).await.0, StatusCode::OK);
+    assert_error(&call(&app, request(Method::PUT, &path, &tokens.refresh_token)).await, 401, 100403);
+
+    assert_eq!(single_log(&state, LogType::RefreshAuthInvalid).await.user_id, Some(user_id));
+}
+
+#[actix_web::test]
+async fn refresh_auth_revoked() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    let path = format!("/authorizations/{}", tokens.id);
+    assert_eq!(call(&app, request(Method::DELETE, &path, "")).await.0, StatusCode::OK);
+    assert_error(&call(&app, request(Method::PUT, &path, &tokens.refresh_token)).await, 401, 100403);
+
+    assert_eq!(single_log(&state, LogType::RefreshAuthInvalid).await.user_id, Some(user_id));
+}
+
+#[actix_web::test]
+async fn refresh_auth_user_disabled() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+    disable_user(&state, user_id).await;
+
+    let path = format!("/authorizations/{}", tokens.id);
+    assert_error(&call(&app, request(Method::PUT, &path, &tokens.refresh_token)).await, 401, 100403);
+
+    assert_eq!(single_log(&state, LogType::RefreshUserDisabled).await.user_id, Some(user_id));
+}
+
+#[actix_web::test]
+async fn refresh_auth_user_deleted() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+    delete_user(&state, user_id).await;
+
+    let path = format!("/authorizations/{}", tokens.id);
+    assert_error(&call(&app, request(Method::PUT, &path, &tokens.refresh_token)).await, 401, 100403);
+
+    assert_eq!(single_log(&state, LogType::RefreshUserDeleted).await.user_id, Some(user_id));
+}
+
+#[actix_web::test]
+async fn delete_auth() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    let path = format!("/authorizations/{}", tokens.id);
+    let (status, json) = call(&app, request(Method::DELETE, &path, "")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(json.is_null());
+
+    // access token加入黑名单
+    assert_error(&call(&app, request(Method::GET, "/user", &tokens.access_token)).await, 401, 100403);
+
+    let log = single_log(&state, LogType::Logout).await;
+    assert_eq!(log.user_id, Some(user_id));
+    assert_eq!(log.auth_id, single_log(&state, LogType::Login).await.auth_id);
+}
+
+#[actix_web::test]
+async fn delete_auth_not_found() {
+    let state = state().await;
+    let app = app(&state).await;
+
+    assert_error(&call(&app, request(Method::DELETE, "/authorizations/abc", "")).await, 422, 400002);
+
+    let path = format!("/authorizations/{}", uuid::Uuid::new_v4());
+    assert_error(&call(&app, request(Method::DELETE, &path, "")).await, 401, 100403);
+
+    assert_eq!(single_log(&state, LogType::LogoutAuthNotFound).await.user_id, Some(0));
+}
+
+#[actix_web::test]
+async fn delete_auth_disabled() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    let path = format!("/authorizations/{}", tokens.id);
+    assert_eq!(call(&app, request(Method::DELETE, &path, "")).await.0, StatusCode::OK);
+    assert_error(&call(&app, request(Method::DELETE, &path, "")).await, 401, 100403);
+
+    assert_eq!(single_log(&state, LogType::LogoutAuthDisabled).await.user_id, Some(user_id));
+    assert_eq!(logs(&state, LogType::Logout).await.len(), 1);
+}
+// 事务未提交时撤销其中的写入，提交后写入保留
+#[actix_web::test]
+async fn create_auth_transaction() {
+    let state = state().await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let client = ClientInfo { ip: String::from("127.0.0.1"), user_agent: String::from("integration-test") };
+
+    let mut tx = service::begin(&state).await.unwrap();
+    let auth = auth::create_auth(user_id, 0, TENANT_ID, &client, tx.as_mut(), &state).await.unwrap();
+    service::insert_log_tx(LogType::Login, "", user_id, auth.auth_id, TENANT_ID, &client, tx.as_mut(), &state).await.unwrap();
+    drop(tx);
+
+    assert!(state.repo.authorizations.get_by_id(auth.auth_id, TENANT_ID, &state.log).await.unwrap().is_none());
+    assert!(logs(&state, LogType::Login).await.is_empty());
+    let user = state.repo.users.get_by_id(user_id, TENANT_ID, &state.log).await.unwrap().unwrap();
+    assert!(user.last_login_time.is_none());
+
+    let mut tx = service::begin(&state).await.unwrap();
+    let auth = auth::create_auth(user_id, 0, TENANT_ID, &client, tx.as_mut(), &state).await.unwrap();
+    service::insert_log_tx(LogType::Login, "", user_id, auth.auth_id, TENANT_ID, &client, tx.as_mut(), &state).await.unwrap();
+    service::commit(tx, &state).await.unwrap();
+
+    assert!(state.repo.authorizations.get_by_id(auth.auth_id, TENANT_ID, &state.log).await.unwrap().is_some());
+    assert_eq!(single_log(&state, LogType::Login).await.auth_id, Some(auth.auth_id));
+    let user = state.repo.users.get_by_id(user_id, TENANT_ID, &state.log).await.unwrap().unwrap();
+    assert!(user.last_login_time.is_some());
+}
+
+// 同一个refresh token只能更新一次授权，并发刷新时后提交的请求失败
+#[actix_web::test]
+async fn update_auth_refresh_token_reused() {
+    let state = state().await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let client = ClientInfo { ip: String::from("127.0.0.1"), user_agent: String::from("integration-test") };
+
+    let mut tx = service::begin(&state).await.unwrap();
+    let auth = auth::create_auth(user_id, 0, TENANT_ID, &client, tx.as_mut(), &state).await.unwrap();
+    service::commit(tx, &state).await.unwrap();
+
+    let mut authorization = state.repo.authorizations.get_by_id(auth.auth_id, TENANT_ID, &state.log).await.unwrap().unwrap();
+    let old_refresh_token = authorization.refresh_token.unwrap();
+
+    let mut tx = service::begin(&state).await.unwrap();
+    authorization.refresh_token = Some(uuid::Uuid::new_v4());
+    assert!(service::update_auth(&authorization, old_refresh_token, TENANT_ID, tx.as_mut(), &state).await.unwrap().is_some());
+    authorization.refresh_token = Some(uuid::Uuid::new_v4());
+    assert!(service::update_auth(&authorization, old_refresh_token, TENANT_ID, tx.as_mut(), &state).await.unwrap().is_none());
+    service::commit(tx, &state).await.unwrap();
+
+    let result = state.repo.authorizations.get_by_id(auth.auth_id, TENANT_ID, &state.log).await.unwrap().unwrap();
+    assert_ne!(result.refresh_token, Some(old_refresh_token));
+    assert_ne!(result.refresh_token, authorization.refresh_token);
+}
+
+// 授权按租户隔离，其他租户下不能读取、更新或撤销
+#[actix_web::test]
+async fn auth_tenant_scoped() {
+    let state = state().await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let client = ClientInfo { ip: String::from("127.0.0.1"), user_agent: String::from("integration-test") };
+    let other_tenant = TENANT_ID + 1;
+
+    let mut tx = service::begin(&state).await.unwrap();
+    let auth = auth::create_auth(user_id, 0, TENANT_ID, &client, tx.as_mut(), &state).await.unwrap();
+    service::commit(tx, &state).await.unwrap();
+
+    let mut authorization = state.repo.authorizations.get_by_id(auth.auth_id, TENANT_ID, &state.log).await.unwrap().unwrap();
+    let old_refresh_token = authorization.refresh_token.unwrap();
+    authorization.refresh_token = Some(uuid::Uuid::new_v4());
+
+    let mut tx = service::begin(&state).await.unwrap();
+    assert!(service::update_auth(&authorization, old_refresh_token, other_tenant, tx.as_mut(), &state).await.unwrap().is_none());
+    service::revoke_auth(auth.auth_id, other_tenant, tx.as_mut(), &state).await.unwrap();
+    service::commit(tx, &state).await.unwrap();
+    service::revoke_all_by_user(user_id, other_tenant, &state).await.unwrap();
+
+    assert!(service::get_by_user_id(user_id, other_tenant, &state).await.unwrap().is_empty());
+    let result = state.repo.authorizations.get_by_id(auth.auth_id, TENANT_ID, &state.log).await.unwrap().unwrap();
+    assert_eq!(result.refresh_token, Some(old_refresh_token));
+    assert_eq!(result.is_enabled, Some(1));
+}
+
+// 超过日志字段长度的内容截断保存
+#[actix_web::test]
+async fn insert_log_truncated() {
+    let state = state().await;
+    let client = ClientInfo { ip: String::from("127.0.0.1"), user_agent: String::from("integration-test") };
+
+    let msg = "配".repeat(300);
+    service::insert_log(LogType::AdminReloadSettings, &msg, 1, 0, TENANT_ID, &client, &state).await.unwrap();
+
+    let log = single_log(&state, LogType::AdminReloadSettings).await.log.unwrap();
+    assert_eq!(log.chars().count(), 250);
+}
@@ -0,0 +1,172 @@
+// 集成测试，使用与 main 相同的 App，用户、授权和日志保存在内存存储中，不需要postgres和redis
+mod authorizations;
+mod user;
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{test, web};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use crate::AppState;
+use crate::api::{self, user::User, authorizations::{AuthorizationLog, LogFilter, LogType}};
+use crate::lib::{self, auth, settings::Settings};
+
+const TENANT_ID: i32 = 1;
+
+// 使用 data/config/app.toml，存储改为内存
+fn settings() -> Settings {
+    let config = config::Config::builder()
+        .add_source(config::File::from_str(include_str!("../../data/config/app.toml"), config::FileFormat::Toml))
+        .set_override("repository.backend", "memory").unwrap()
+        .set_override("storage.backend", "memory").unwrap()
+        .build()
+        .unwrap();
+
+    let mut settings = config.try_deserialize::<Settings>().unwrap();
+    settings.profile = String::from("test");
+
+    settings
+}
+
+pub async fn state() -> web::Data<AppState> {
+    let settings = settings();
+    let log = slog::Logger::root(slog::Discard, o!());
+
+    // 内存存储不会使用数据库和redis连接
+    let db = lib::db::conn_lazy(settings.db());
+    let redis = lib::redis::conn(&settings.redis).await;
+    let cache = lib::cache::Cache::new(redis.clone(), &settings.cache);
+    let metrics = Arc::new(lib::metrics::Metrics::new());
+    let repo = api::repository::conn(&settings, &db, Vec::new(), &cache, &metrics);
+
+    web::Data::new(AppState {
+        config: Arc::new(lib::settings::SharedSettings::new(lib::settings::DEFAULT_PATH, settings.clone())),
+        log: log.clone(),
+        db,
+        cache,
+        redis,
+        storage: lib::storage::conn(&settings.storage, &log),
+        repo,
+        ready: Arc::new(AtomicBool::new(true)),
+        metrics,
+    })
+}
+
+pub async fn app(state: &web::Data<AppState>) -> impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error> {
+    test::init_service(crate::app(state.clone())).await
+}
+
+// 带客户端地址和User-Agent的请求，token不为空时添加Authorization头
+pub fn request(method: Method, path: &str, token: &str) -> test::TestRequest {
+    let mut req = test::TestRequest::default()
+        .method(method)
+        .uri(path)
+        .peer_addr("127.0.0.1:40000".parse().unwrap())
+        .insert_header(("User-Agent", "integration-test"));
+    if !token.is_empty() {
+        req = req.insert_header(("Authorization", format!("Bearer {}", token)));
+    }
+
+    req
+}
+
+// 返回状态码和JSON响应，响应为空时返回 Null
+pub async fn call<S, B>(app: &S, req: test::TestRequest) -> (StatusCode, serde_json::Value)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let res = test::call_service(app, req.to_request()).await;
+    let status = res.status();
+    let body = test::read_body(res).await;
+    let json = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+
+    (status, json)
+}
+
+// 在默认租户中创建用户，返回用户id
+pub async fn create_user(state: &web::Data<AppState>, username: &str, password: &str) -> i32 {
+    let salt = auth::salt();
+    let mut user = User::new();
+    user.username = Some(username.to_string());
+    user.password = Some(auth::crypt_password(password, &salt));
+    user.salt = Some(salt);
+    user.tenant_id = Some(TENANT_ID);
+
+    api::user::service::insert(&user, state).await.unwrap().id.unwrap()
+}
+
+// 创建管理员用户，返回用户id
+pub async fn create_admin(state: &web::Data<AppState>, username: &str, password: &str) -> i32 {
+    let id = create_user(state, username, password).await;
+    let mut user = User::new();
+    user.id = Some(id);
+    user.user_type = Some(10);
+    api::user::service::update(&user, TENANT_ID, state).await.unwrap();
+
+    id
+}
+
+pub async fn disable_user(state: &web::Data<AppState>, user_id: i32) {
+    let mut user = User::new();
+    user.id = Some(user_id);
+    user.is_enabled = Some(0);
+
+    api::user::service::update(&user, TENANT_ID, state).await.unwrap();
+}
+
+pub async fn delete_user(state: &web::Data<AppState>, user_id: i32) {
+    api::user::service::delete(user_id, TENANT_ID, state).await.unwrap();
+}
+
+pub struct Tokens {
+    pub id: String,
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+pub async fn login<S, B>(app: &S, username: &str, password: &str) -> Tokens
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let req = request(Method::POST, "/authorizations", "").set_json(serde_json::json!({"username": username, "password": password}));
+    let (status, json) = call(app, req).await;
+    assert_eq!(status, StatusCode::OK, "login {}: {}", username, json);
+
+    Tokens {
+        id: json["id"].as_str().unwrap().to_string(),
+        access_token: json["access_token"].as_str().unwrap().to_string(),
+        refresh_token: json["refresh_token"].as_str().unwrap().to_string(),
+    }
+}
+
+// 默认租户中指定类型的日志
+pub async fn logs(state: &web::Data<AppState>, log_type: LogType) -> Vec<AuthorizationLog> {
+    let filter = LogFilter {
+        tenant_id: TENANT_ID,
+        log_type: Some(log_type.code()),
+        ..Default::default()
+    };
+
+    state.repo.logs.search(&filter, 0, 100, &state.log).await.unwrap()
+}
+
+// 断言只有一条指定类型的日志并返回
+pub async fn single_log(state: &web::Data<AppState>, log_type: LogType) -> AuthorizationLog {
+    let mut result = logs(state, log_type).await;
+    assert_eq!(result.len(), 1, "log type {:?}", log_type);
+
+    result.remove(0)
+}
+
+pub fn assert_error(result: &(StatusCode, serde_json::Value), status: u16, errcode: u32) {
+    assert_eq!(result.0.as_u16(), status, "{}", result.1);
+    assert_eq!(result.1["errcode"], errcode, "{}", result.1);
+}
\ No newline at end of file
@@ -0,0 +1,233 @@
+use super::*;
+
+fn change_password(token: &str, old_password: &str, new_password: &str, confirm_password: &str) -> test::TestRequest {
+    request(Method::PUT, "/user/password", token).set_json(serde_json::json!({
+        "old_password": old_password,
+        "new_password": new_password,
+        "confirm_password": confirm_password,
+    }))
+}
+
+#[actix_web::test]
+async fn get_info() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    let (status, json) = call(&app, request(Method::GET, "/user", &tokens.access_token)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["id"], user_id);
+    assert_eq!(json["username"], "alice");
+    assert_eq!(json["last_login_ip"], "127.0.0.1");
+    assert!(json.get("password").is_none() && json.get("salt").is_none());
+}
+
+#[actix_web::test]
+async fn get_info_unauthorized() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    assert_error(&call(&app, request(Method::GET, "/user", "")).await, 401, 100403);
+    assert_error(&call(&app, request(Method::GET, "/user", "invalid")).await, 401, 100403);
+    // refresh token 没有 ROLE_MEMBER
+    assert_error(&call(&app, request(Method::GET, "/user", &tokens.refresh_token)).await, 403, 100404);
+    // 其他租户签发的token
+    let token = auth::create_access_token(1, 0, 2, &state.config.get());
+    assert_error(&call(&app, request(Method::GET, "/user", &token.token)).await, 401, 100403);
+}
+
+#[actix_web::test]
+async fn get_info_user_deleted() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+    delete_user(&state, user_id).await;
+
+    assert_error(&call(&app, request(Method::GET, "/user", &tokens.access_token)).await, 422, 400007);
+}
+
+// 未匹配的路由由 ErrorHandlers 输出JSON
+#[actix_web::test]
+async fn error_handlers() {
+    let state = state().await;
+    let app = app(&state).await;
+
+    assert_error(&call(&app, request(Method::POST, "/user/password", "")).await, 404, 404);
+    assert_error(&call(&app, request(Method::POST, "/", "")).await, 405, 405);
+}
+
+// 内存存储不注册依赖数据库和redis的路由，导出和修改资料不访问数据库和redis
+#[actix_web::test]
+async fn memory_routes() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    assert_error(&call(&app, request(Method::GET, "/invitations", &tokens.access_token)).await, 404, 404);
+    assert_error(&call(&app, request(Method::GET, "/user/preferences", &tokens.access_token)).await, 404, 404);
+    let req = request(Method::POST, "/user/mobile/code", &tokens.access_token).set_json(serde_json::json!({"mobile": "13800000000"}));
+    assert_error(&call(&app, req).await, 404, 404);
+
+    let (status, json) = call(&app, request(Method::GET, "/user/export", &tokens.access_token)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["preferences"], serde_json::json!({}));
+
+    let req = request(Method::PATCH, "/user", &tokens.access_token).set_json(serde_json::json!({"mobile": "13800000000", "mobile_code": "123456"}));
+    assert_error(&call(&app, req).await, 422, 400012);
+}
+
+#[actix_web::test]
+async fn update_password() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    let (status, _) = call(&app, change_password(&tokens.access_token, "secret", "secret2", "secret2")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(single_log(&state, LogType::ChangePassword).await.user_id, Some(user_id));
+
+    // 旧密码不能再登录
+    let req = request(Method::POST, "/authorizations", "").set_json(serde_json::json!({"username": "alice", "password": "secret"}));
+    assert_error(&call(&app, req).await, 422, 100400);
+    assert_eq!(single_log(&state, LogType::LoginWrongPassword).await.user_id, Some(user_id));
+
+    login(&app, "alice", "secret2").await;
+}
+
+#[actix_web::test]
+async fn update_password_invalid() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+
+    assert_error(&call(&app, change_password("", "secret", "secret2", "secret2")).await, 401, 100403);
+    assert_error(&call(&app, change_password(&tokens.access_token, "secret", "", "")).await, 422, 400002);
+    assert_error(&call(&app, change_password(&tokens.access_token, "secret", "secret2", "secret3")).await, 422, 100301);
+    assert_error(&call(&app, change_password(&tokens.access_token, "wrong", "secret2", "secret2")).await, 422, 100407);
+
+    assert!(logs(&state, LogType::ChangePassword).await.is_empty());
+    login(&app, "alice", "secret").await;
+}
+
+#[actix_web::test]
+async fn update_password_user_disabled() {
+    let state = state().await;
+    let app = app(&state).await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+    disable_user(&state, user_id).await;
+
+    assert_error(&call(&app, change_password(&tokens.access_token, "secret", "secret2", "secret2")).await, 401, 100403);
+    assert!(logs(&state, LogType::ChangePassword).await.is_empty());
+}
+// 管理员禁用、删除用户或重置密码后，用户已有的授权全部失效
+#[actix_web::test]
+async fn admin_revokes_sessions() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_admin(&state, "admin", "secret").await;
+    let admin = login(&app, "admin", "secret").await;
+
+    for (i, (method, action)) in [(Method::PUT, "disable"), (Method::DELETE, ""), (Method::PUT, "password")].iter().cloned().enumerate() {
+        let username = format!("user{}", i);
+        let user_id = create_user(&state, &username, "secret").await;
+        let tokens = login(&app, &username, "secret").await;
+
+        let path = if action.is_empty() { format!("/users/{}", user_id) } else { format!("/users/{}/{}", user_id, action) };
+        let req = request(method, &path, &admin.access_token).set_json(serde_json::json!({"password": "secret2"}));
+        let (status, json) = call(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}: {}", path, json);
+
+        assert_error(&call(&app, request(Method::GET, "/user", &tokens.access_token)).await, 401, 100403);
+        let path = format!("/authorizations/{}", tokens.id);
+        assert_error(&call(&app, request(Method::PUT, &path, &tokens.refresh_token)).await, 401, 100403);
+    }
+}
+
+// 导入时手机号在文件中重复或已被使用的行逐行报告，其他行正常导入
+#[actix_web::test]
+async fn import_users_mobile() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_admin(&state, "admin", "secret").await;
+    let admin = login(&app, "admin", "secret").await;
+    let user_id = create_user(&state, "alice", "secret").await;
+    let mut user = User::new();
+    user.id = Some(user_id);
+    user.mobile = Some(String::from("13800000000"));
+    api::user::service::update(&user, TENANT_ID, &state).await.unwrap();
+
+    let csv = "username,password,mobile,user_type\nbob,secret,13800000000,0\ncarol,secret,13800000001,0\ndave,secret,13800000001,0\nerin,secret,,5\nfrank,secret,13800000002,10\n";
+    for dry_run in [true, false].iter().cloned() {
+        let req = request(Method::POST, &format!("/users/import?dry_run={}", dry_run), &admin.access_token).set_payload(csv);
+        let (status, json) = call(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", json);
+
+        let rows = json["rows"].as_array().unwrap();
+        let ok = if dry_run { "valid" } else { "created" };
+        assert_eq!(rows.iter().map(|v| v["status"].as_str().unwrap()).collect::<Vec<_>>(), vec!["invalid", ok, "invalid", "invalid", ok]);
+        assert_eq!(rows[0]["errors"], serde_json::json!(["手机号已被使用"]));
+        assert_eq!(rows[2]["errors"], serde_json::json!(["手机号在文件中重复"]));
+        assert_eq!(rows[3]["errors"], serde_json::json!(["用户类型错误"]));
+    }
+
+    login(&app, "carol", "secret").await;
+}
+
+// 清空手机号保存为NULL，多个用户可以同时没有手机号；取消管理员后已有的授权失效
+#[actix_web::test]
+async fn admin_update_user() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_admin(&state, "admin", "secret").await;
+    let admin = login(&app, "admin", "secret").await;
+
+    for (i, mobile) in ["13800000000", "13800000001"].iter().enumerate() {
+        let user_id = create_user(&state, &format!("user{}", i), "secret").await;
+        let path = format!("/users/{}", user_id);
+        let (status, json) = call(&app, request(Method::PUT, &path, &admin.access_token).set_json(serde_json::json!({"mobile": mobile}))).await;
+        assert_eq!(status, StatusCode::OK, "{}", json);
+        let (status, json) = call(&app, request(Method::PUT, &path, &admin.access_token).set_json(serde_json::json!({"mobile": ""}))).await;
+        assert_eq!(status, StatusCode::OK, "{}", json);
+        assert!(json["mobile"].is_null());
+    }
+
+    let user_id = create_admin(&state, "admin2", "secret").await;
+    let tokens = login(&app, "admin2", "secret").await;
+    let req = request(Method::PUT, &format!("/users/{}", user_id), &admin.access_token).set_json(serde_json::json!({"user_type": 0}));
+    assert_eq!(call(&app, req).await.0, StatusCode::OK);
+    assert_error(&call(&app, request(Method::GET, "/users", &tokens.access_token)).await, 401, 100403);
+}
+
+// 注销账号后授权失效，宽限期内可以恢复，匿名化后不能恢复
+#[actix_web::test]
+async fn close_account_restore() {
+    let state = state().await;
+    let app = app(&state).await;
+    create_admin(&state, "admin", "secret").await;
+    let admin = login(&app, "admin", "secret").await;
+
+    let user_id = create_user(&state, "alice", "secret").await;
+    let tokens = login(&app, "alice", "secret").await;
+    let close = || request(Method::DELETE, "/user", &tokens.access_token).set_json(serde_json::json!({"password": "secret"}));
+    assert_eq!(call(&app, close()).await.0, StatusCode::OK);
+    assert_error(&call(&app, request(Method::GET, "/user", &tokens.access_token)).await, 401, 100403);
+
+    let restore = format!("/users/{}/restore", user_id);
+    assert_eq!(call(&app, request(Method::PUT, &restore, &admin.access_token)).await.0, StatusCode::OK);
+    login(&app, "alice", "secret").await;
+
+    let tokens = login(&app, "alice", "secret").await;
+    let close = || request(Method::DELETE, "/user", &tokens.access_token).set_json(serde_json::json!({"password": "secret"}));
+    assert_eq!(call(&app, close()).await.0, StatusCode::OK);
+    state.repo.users.schedule_anonymize(user_id, chrono::Utc::now(), TENANT_ID, &state.log).await.unwrap();
+    assert_eq!(api::user::service::anonymize_expired(&state).await.unwrap(), 1);
+    assert_error(&call(&app, request(Method::PUT, &restore, &admin.access_token)).await, 422, 400025);
+}